@@ -0,0 +1,10 @@
+CREATE TABLE card_history (
+    id UUID NOT NULL PRIMARY KEY,
+    card_id UUID NOT NULL,
+    actor UUID NOT NULL,
+    action VARCHAR(16) NOT NULL,
+    changed_at TIMESTAMP NOT NULL,
+    diff JSONB NOT NULL
+);
+
+CREATE INDEX card_history_card_id_idx ON card_history (card_id, changed_at);
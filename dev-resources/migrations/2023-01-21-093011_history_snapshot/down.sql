@@ -0,0 +1,3 @@
+ALTER TABLE card_history
+  DROP snapshot,
+  DROP undone;
@@ -0,0 +1,3 @@
+ALTER TABLE card_history
+  ADD snapshot JSONB,
+  ADD undone BOOLEAN NOT NULL DEFAULT 'f';
@@ -0,0 +1,14 @@
+CREATE TABLE session (
+    id UUID NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    device VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    last_seen_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    revoked BOOLEAN NOT NULL DEFAULT 'f'
+);
+
+CREATE INDEX session_user_id_idx ON session (user_id);
+
+ALTER TABLE refresh_token
+  ADD session_id UUID;
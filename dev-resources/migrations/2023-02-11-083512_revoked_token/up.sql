@@ -0,0 +1,8 @@
+CREATE TABLE revoked_token (
+    jti UUID NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    revoked_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX revoked_token_expires_at_idx ON revoked_token (expires_at);
@@ -0,0 +1,10 @@
+CREATE TABLE password_reset (
+    id UUID NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP
+);
+
+CREATE INDEX password_reset_user_id_idx ON password_reset (user_id);
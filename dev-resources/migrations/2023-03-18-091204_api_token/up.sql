@@ -0,0 +1,12 @@
+CREATE TABLE api_token (
+    id UUID NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    name VARCHAR NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    scopes VARCHAR NOT NULL, --space separated
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP,
+    last_used_at TIMESTAMP
+);
+
+CREATE INDEX api_token_user_id_idx ON api_token (user_id);
@@ -0,0 +1,4 @@
+ALTER TABLE session DROP COLUMN client_id;
+ALTER TABLE session DROP COLUMN scope;
+DROP TABLE oauth_code;
+DROP TABLE oauth_client;
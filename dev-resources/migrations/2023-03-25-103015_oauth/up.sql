@@ -0,0 +1,23 @@
+CREATE TABLE oauth_client (
+    id VARCHAR NOT NULL PRIMARY KEY,
+    owner_id UUID NOT NULL,
+    name VARCHAR NOT NULL,
+    redirect_uris VARCHAR NOT NULL, --space separated
+    secret_hash VARCHAR, --NULL for public clients
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX oauth_client_owner_id_idx ON oauth_client (owner_id);
+
+CREATE TABLE oauth_code (
+    code_hash VARCHAR NOT NULL PRIMARY KEY,
+    client_id VARCHAR NOT NULL,
+    user_id UUID NOT NULL,
+    redirect_uri VARCHAR NOT NULL,
+    scope VARCHAR NOT NULL,
+    code_challenge VARCHAR NOT NULL,
+    expires_at TIMESTAMP NOT NULL
+);
+
+ALTER TABLE session ADD COLUMN scope VARCHAR;
+ALTER TABLE session ADD COLUMN client_id VARCHAR;
@@ -0,0 +1,2 @@
+-- Fails while Argon2 hashes longer than 64 characters are stored
+ALTER TABLE auth_user ALTER COLUMN password TYPE VARCHAR(64);
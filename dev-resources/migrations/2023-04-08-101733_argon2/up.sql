@@ -0,0 +1,2 @@
+-- PHC strings such as $argon2id$v=19$m=19456,t=2,p=1$salt$hash, bcrypt hashes stay valid
+ALTER TABLE auth_user ALTER COLUMN password TYPE VARCHAR(255);
@@ -9,10 +9,11 @@ extern crate diesel;
 mod schema;
 
 use todo_server::{
-    todo_api::db::helpers::create_table,
-    todo_api_web::{
-        middleware::authentication_middleware, model::http::Clients, routes::app_routes,
+    todo_api::{
+        core::keys::{init_jwt_keys, init_totp_key},
+        db::helpers::create_table,
     },
+    todo_api_web::{model::http::Clients, routes::app_routes},
 };
 
 use actix_web::{
@@ -20,7 +21,6 @@ use actix_web::{
     web::Data,
     App, HttpServer,
 };
-use actix_web_lab::middleware::from_fn;
 
 use env_logger;
 use uuid::Uuid;
@@ -29,6 +29,10 @@ use uuid::Uuid;
 async fn main() -> Result<(), std::io::Error> {
     std::env::set_var("RUST_LOG", "actix_web=debug");
     env_logger::init();
+    init_jwt_keys()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    init_totp_key()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
 
     let client = Clients::new().await;
     create_table(&client.clone()).await;
@@ -38,7 +42,6 @@ async fn main() -> Result<(), std::io::Error> {
             .app_data(Data::new(client.clone()))
             .wrap(DefaultHeaders::new().add(("x-request-id", Uuid::new_v4().to_string())))
             .wrap(Logger::new("IP:%a DATETIME:%t REQUEST:\"%r\" STATUS: %s DURATION:%D X-REQUEST-ID:%{x-request-id}o"))
-            .wrap(from_fn(authentication_middleware))
             .configure(app_routes)
     })
     .workers(num_cpus::get() - 2)
@@ -0,0 +1 @@
+DROP TABLE card_history;
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE refresh_token (
+    id UUID NOT NULL PRIMARY KEY,
+    family UUID NOT NULL,
+    user_id UUID NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    issued_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    revoked BOOLEAN NOT NULL DEFAULT 'f'
+);
+
+CREATE INDEX refresh_token_family_idx ON refresh_token (family);
+CREATE INDEX refresh_token_user_id_idx ON refresh_token (user_id);
@@ -0,0 +1,4 @@
+ALTER TABLE refresh_token
+  DROP session_id;
+
+DROP TABLE session;
@@ -0,0 +1,4 @@
+DROP TABLE email_verification;
+
+ALTER TABLE auth_user
+  DROP COLUMN email_verified;
@@ -0,0 +1,16 @@
+ALTER TABLE auth_user
+  ADD email_verified BOOLEAN NOT NULL DEFAULT 'f';
+
+-- Accounts created before verification existed keep working
+UPDATE auth_user SET email_verified = 't';
+
+CREATE TABLE email_verification (
+    id UUID NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP
+);
+
+CREATE INDEX email_verification_user_id_idx ON email_verification (user_id, created_at);
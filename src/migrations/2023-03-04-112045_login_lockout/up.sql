@@ -0,0 +1,18 @@
+-- One row per `account:<email>` or `ip:<address>` key
+CREATE TABLE login_throttle (
+    key VARCHAR NOT NULL PRIMARY KEY,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP,
+    unlock_token_hash VARCHAR UNIQUE
+);
+
+CREATE TABLE audit_event (
+    id UUID NOT NULL PRIMARY KEY,
+    action VARCHAR NOT NULL,
+    subject VARCHAR NOT NULL,
+    actor UUID,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX audit_event_subject_idx ON audit_event (subject, created_at);
@@ -0,0 +1,17 @@
+CREATE TABLE two_factor (
+    user_id UUID NOT NULL PRIMARY KEY,
+    secret VARCHAR NOT NULL, --base32 TOTP secret sealed with TOTP_ENCRYPTION_KEY
+    enabled BOOLEAN NOT NULL DEFAULT 'f',
+    created_at TIMESTAMP NOT NULL,
+    enabled_at TIMESTAMP,
+    last_used_step BIGINT
+);
+
+CREATE TABLE recovery_code (
+    id UUID NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    code_hash VARCHAR NOT NULL,
+    used_at TIMESTAMP
+);
+
+CREATE INDEX recovery_code_user_id_idx ON recovery_code (user_id);
@@ -0,0 +1,18 @@
+CREATE TABLE external_identity (
+    issuer VARCHAR NOT NULL,
+    subject VARCHAR NOT NULL,
+    user_id UUID NOT NULL,
+    email VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    last_login_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (issuer, subject)
+);
+
+CREATE INDEX external_identity_user_id_idx ON external_identity (user_id);
+
+CREATE TABLE oidc_login (
+    state_hash VARCHAR NOT NULL PRIMARY KEY,
+    nonce VARCHAR NOT NULL,
+    code_verifier VARCHAR NOT NULL,
+    expires_at TIMESTAMP NOT NULL
+);
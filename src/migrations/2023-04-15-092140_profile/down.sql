@@ -0,0 +1,2 @@
+ALTER TABLE email_verification DROP COLUMN new_email;
+ALTER TABLE auth_user DROP COLUMN timezone, DROP COLUMN display_name;
@@ -0,0 +1,7 @@
+ALTER TABLE auth_user
+  ADD display_name VARCHAR(100),
+  ADD timezone VARCHAR(64) NOT NULL DEFAULT 'UTC';
+
+-- Set when the token confirms a change of address rather than the signup email
+ALTER TABLE email_verification
+  ADD new_email VARCHAR;
@@ -1,5 +1,28 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_token (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Varchar,
+        token_hash -> Varchar,
+        scopes -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    audit_event (id) {
+        id -> Uuid,
+        action -> Varchar,
+        subject -> Varchar,
+        actor -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     auth_user (email) {
         email -> Varchar,
@@ -7,5 +30,173 @@ diesel::table! {
         password -> Varchar,
         expires_at -> Timestamp,
         is_active -> Bool,
+        email_verified -> Bool,
+        display_name -> Nullable<Varchar>,
+        timezone -> Varchar,
+    }
+}
+
+diesel::table! {
+    card_history (id) {
+        id -> Uuid,
+        card_id -> Uuid,
+        actor -> Uuid,
+        action -> Varchar,
+        changed_at -> Timestamp,
+        diff -> Jsonb,
+        snapshot -> Nullable<Jsonb>,
+        undone -> Bool,
+    }
+}
+
+diesel::table! {
+    email_verification (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        new_email -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    external_identity (issuer, subject) {
+        issuer -> Varchar,
+        subject -> Varchar,
+        user_id -> Uuid,
+        email -> Varchar,
+        created_at -> Timestamp,
+        last_login_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    login_throttle (key) {
+        key -> Varchar,
+        failures -> Int4,
+        last_failure_at -> Timestamp,
+        locked_until -> Nullable<Timestamp>,
+        unlock_token_hash -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    oauth_client (id) {
+        id -> Varchar,
+        owner_id -> Uuid,
+        name -> Varchar,
+        redirect_uris -> Varchar,
+        secret_hash -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    oauth_code (code_hash) {
+        code_hash -> Varchar,
+        client_id -> Varchar,
+        user_id -> Uuid,
+        redirect_uri -> Varchar,
+        scope -> Varchar,
+        code_challenge -> Varchar,
+        expires_at -> Timestamp,
     }
 }
+
+diesel::table! {
+    oidc_login (state_hash) {
+        state_hash -> Varchar,
+        nonce -> Varchar,
+        code_verifier -> Varchar,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_reset (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    recovery_code (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Varchar,
+        used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    refresh_token (id) {
+        id -> Uuid,
+        family -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Varchar,
+        issued_at -> Timestamp,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        revoked -> Bool,
+        session_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    revoked_token (jti) {
+        jti -> Uuid,
+        user_id -> Uuid,
+        revoked_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    session (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        device -> Varchar,
+        created_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked -> Bool,
+        scope -> Nullable<Varchar>,
+        client_id -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    two_factor (user_id) {
+        user_id -> Uuid,
+        secret -> Varchar,
+        enabled -> Bool,
+        created_at -> Timestamp,
+        enabled_at -> Nullable<Timestamp>,
+        last_used_step -> Nullable<Int8>,
+    }
+}
+
+diesel::allow_tables_to_appear_in_same_query!(
+    api_token,
+    audit_event,
+    auth_user,
+    card_history,
+    email_verification,
+    external_identity,
+    login_throttle,
+    oauth_client,
+    oauth_code,
+    oidc_login,
+    password_reset,
+    recovery_code,
+    refresh_token,
+    revoked_token,
+    session,
+    two_factor,
+);
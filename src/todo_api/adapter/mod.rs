@@ -1,11 +1,14 @@
 pub mod auth;
+pub mod template;
 
-use actix_web::web;
-use aws_sdk_dynamodb::output::ScanOutput;
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::{model::AttributeValue, output::ScanOutput};
+use chrono::{NaiveDateTime, Utc};
 use uuid::Uuid;
 
-use super::model::{StateDb, TaskDb, TodoCardDb};
-use crate::todo_api_web::model::todo::{State, Task, TodoCard};
+use super::model::{CollaboratorDb, RoleDb, StateDb, TaskDb, TodoCardDb, TransferDb};
+use crate::todo_api_web::model::todo::{Collaborator, Role, State, Task, TodoCard, Transfer};
 
 #[macro_export]
 macro_rules! val {
@@ -23,18 +26,42 @@ macro_rules! val {
     }};
 }
 
-pub fn todo_json_to_db(card: web::Json<TodoCard>, id: Uuid) -> TodoCardDb {
+/// Timestamps are set by the server, whatever the client sent.
+pub fn new_todo_card(mut card: TodoCard, id: Uuid) -> TodoCard {
+    let now = Utc::now().naive_utc();
+    let state = card.state.clone();
+
+    card.id = Some(id);
+    card.transfers = Vec::new();
+    card.collaborators = Vec::new();
+    card.created_at = Some(now);
+    card.doing_at = None;
+    card.done_at = None;
+    card.transition(state, now);
+    card
+}
+
+/// A copy hasn't moved through the board itself, even when it keeps the
+/// progress of the original it carries no state timestamps.
+pub fn duplicated_todo_card(card: TodoCard, id: Uuid) -> TodoCard {
+    let mut card = new_todo_card(card, id);
+    card.doing_at = None;
+    card.done_at = None;
+    card
+}
+
+pub fn todo_card_to_db(card: TodoCard, id: Uuid) -> TodoCardDb {
     TodoCardDb {
         id,
-        title: card.title.clone(),
-        description: card.description.clone(),
+        title: card.title,
+        description: card.description,
         owner: card.owner,
         tasks: card
             .tasks
-            .iter()
+            .into_iter()
             .map(|t| TaskDb {
                 is_done: t.is_done,
-                title: t.title.clone(),
+                title: t.title,
             })
             .collect(),
         state: match card.state {
@@ -42,6 +69,29 @@ pub fn todo_json_to_db(card: web::Json<TodoCard>, id: Uuid) -> TodoCardDb {
             State::Doing => StateDb::Doing,
             State::Done => StateDb::Done,
         },
+        created_at: card.created_at.unwrap_or_else(|| Utc::now().naive_utc()),
+        doing_at: card.doing_at,
+        done_at: card.done_at,
+        transfers: card
+            .transfers
+            .into_iter()
+            .map(|t| TransferDb {
+                from: t.from,
+                to: t.to,
+                at: t.at,
+            })
+            .collect(),
+        collaborators: card
+            .collaborators
+            .into_iter()
+            .map(|c| CollaboratorDb {
+                user: c.user,
+                role: match c.role {
+                    Role::Viewer => RoleDb::Viewer,
+                    Role::Editor => RoleDb::Editor,
+                },
+            })
+            .collect(),
     }
 }
 
@@ -49,37 +99,89 @@ pub fn scanoutput_to_todocards(output: ScanOutput) -> Option<Vec<TodoCard>> {
     Some(
         output
             .items()?
-            .into_iter()
-            .filter_map(|item| {
-                let id = item.get("id")?.as_s().ok();
-                let owner = item.get("owner")?.as_s().ok();
-                let title = item.get("title")?.as_s().ok();
-                let description = item.get("description")?.as_s().ok();
-                let state = item.get("state")?.as_s().ok();
-                let tasks = item.get("tasks")?.as_l().ok();
-
-                Some(TodoCard {
-                    id: uuid::Uuid::parse_str(id?).ok(),
-                    owner: uuid::Uuid::parse_str(owner?).ok()?,
-                    title: title?.to_string(),
-                    description: description?.to_string(),
-                    state: State::from(state?),
-                    tasks: tasks?
-                        .iter()
-                        .filter_map(|t| {
-                            let is_done = *t.as_m().ok()?.get("is_done")?.as_bool().ok()?;
-                            Some(Task {
-                                title: t.as_m().ok()?.get("title")?.as_s().ok()?.to_string(),
-                                is_done,
-                            })
-                        })
-                        .collect::<Vec<Task>>(),
-                })
-            })
+            .iter()
+            .filter_map(item_to_todocard)
             .collect(),
     )
 }
 
+pub fn item_to_todocard(item: &HashMap<String, AttributeValue>) -> Option<TodoCard> {
+    let id = item.get("id")?.as_s().ok();
+    let owner = item.get("owner")?.as_s().ok();
+    let title = item.get("title")?.as_s().ok();
+    let description = item.get("description")?.as_s().ok();
+    let state = item.get("state")?.as_s().ok();
+    let tasks = item.get("tasks")?.as_l().ok();
+
+    Some(TodoCard {
+        id: uuid::Uuid::parse_str(id?).ok(),
+        owner: uuid::Uuid::parse_str(owner?).ok()?,
+        title: title?.to_string(),
+        description: description?.to_string(),
+        state: State::from(state?),
+        tasks: item_tasks(tasks?),
+        created_at: item_date(item, "created_at"),
+        doing_at: item_date(item, "doing_at"),
+        done_at: item_date(item, "done_at"),
+        transfers: item_transfers(item),
+        collaborators: item_collaborators(item),
+    })
+}
+
+pub fn item_tasks(tasks: &[AttributeValue]) -> Vec<Task> {
+    tasks
+        .iter()
+        .filter_map(|t| {
+            let is_done = *t.as_m().ok()?.get("is_done")?.as_bool().ok()?;
+            Some(Task {
+                title: t.as_m().ok()?.get("title")?.as_s().ok()?.to_string(),
+                is_done,
+            })
+        })
+        .collect::<Vec<Task>>()
+}
+
+fn item_transfers(item: &HashMap<String, AttributeValue>) -> Vec<Transfer> {
+    let transfers = match item.get("transfers").map(|t| t.as_l()) {
+        Some(Ok(transfers)) => transfers,
+        _ => return Vec::new(),
+    };
+
+    transfers
+        .iter()
+        .filter_map(|t| {
+            let transfer = t.as_m().ok()?;
+            Some(Transfer {
+                from: uuid::Uuid::parse_str(transfer.get("from")?.as_s().ok()?).ok()?,
+                to: uuid::Uuid::parse_str(transfer.get("to")?.as_s().ok()?).ok()?,
+                at: item_date(transfer, "at")?,
+            })
+        })
+        .collect()
+}
+
+fn item_collaborators(item: &HashMap<String, AttributeValue>) -> Vec<Collaborator> {
+    let collaborators = match item.get("collaborators").map(|c| c.as_l()) {
+        Some(Ok(collaborators)) => collaborators,
+        _ => return Vec::new(),
+    };
+
+    collaborators
+        .iter()
+        .filter_map(|c| {
+            let collaborator = c.as_m().ok()?;
+            Some(Collaborator {
+                user: uuid::Uuid::parse_str(collaborator.get("user")?.as_s().ok()?).ok()?,
+                role: Role::from(collaborator.get("role")?.as_s().ok()?),
+            })
+        })
+        .collect()
+}
+
+fn item_date(item: &HashMap<String, AttributeValue>, key: &str) -> Option<NaiveDateTime> {
+    item.get(key)?.as_s().ok()?.parse::<NaiveDateTime>().ok()
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -105,7 +207,13 @@ mod test {
                 is_done: true,
                 title: "title".to_string(),
             }],
+            created_at: None,
+            doing_at: None,
+            done_at: None,
+            transfers: vec![],
+            collaborators: vec![],
         });
+        let actual = todo_card_to_db(new_todo_card(json.into_inner(), id), id);
         let expected = TodoCardDb {
             id: id,
             title: "title".to_string(),
@@ -116,8 +224,126 @@ mod test {
                 is_done: true,
                 title: "title".to_string(),
             }],
+            created_at: actual.created_at,
+            doing_at: None,
+            done_at: Some(actual.created_at),
+            transfers: vec![],
+            collaborators: vec![],
         };
-        assert_eq!(todo_json_to_db(json, id), expected);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn new_cards_ignore_client_timestamps() {
+        let past = chrono::NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let card = new_todo_card(
+            TodoCard {
+                id: None,
+                title: "title".to_string(),
+                description: "description".to_string(),
+                owner: uuid::Uuid::new_v4(),
+                state: State::Done,
+                tasks: vec![],
+                created_at: Some(past),
+                doing_at: Some(past),
+                done_at: Some(past),
+                transfers: vec![],
+                collaborators: vec![],
+            },
+            uuid::Uuid::new_v4(),
+        );
+
+        assert_eq!(card.doing_at, None);
+        assert_eq!(card.done_at, card.created_at);
+        assert_ne!(card.created_at, Some(past));
+    }
+
+    #[test]
+    fn duplicates_carry_no_state_timestamps() {
+        let past = chrono::NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let card = duplicated_todo_card(
+            TodoCard {
+                id: None,
+                title: "title".to_string(),
+                description: "description".to_string(),
+                owner: uuid::Uuid::new_v4(),
+                state: State::Done,
+                tasks: vec![],
+                created_at: Some(past),
+                doing_at: Some(past),
+                done_at: Some(past),
+                transfers: vec![],
+                collaborators: vec![],
+            },
+            uuid::Uuid::new_v4(),
+        );
+
+        assert_eq!(card.state, State::Done);
+        assert_eq!(card.doing_at, None);
+        assert_eq!(card.done_at, None);
+    }
+
+    #[test]
+    fn transfers_roundtrip_through_dynamo_item() {
+        let id = uuid::Uuid::new_v4();
+        let owner = uuid::Uuid::new_v4();
+        let at = "2023-01-10T10:00:00".parse::<NaiveDateTime>().unwrap();
+        let mut card = new_todo_card(
+            TodoCard {
+                id: None,
+                title: "title".to_string(),
+                description: "description".to_string(),
+                owner,
+                state: State::Todo,
+                tasks: vec![],
+                created_at: None,
+                doing_at: None,
+                done_at: None,
+                transfers: vec![],
+                collaborators: vec![],
+            },
+            id,
+        );
+        card.transfer(uuid::Uuid::new_v4(), at);
+        let item: HashMap<String, AttributeValue> = todo_card_to_db(card.clone(), id).into();
+
+        assert_eq!(card.transfers[0].from, owner);
+        assert_eq!(item_to_todocard(&item).unwrap().transfers, card.transfers);
+    }
+
+    #[test]
+    fn collaborators_roundtrip_through_dynamo_item() {
+        let id = uuid::Uuid::new_v4();
+        let mut card = new_todo_card(
+            TodoCard {
+                id: None,
+                title: "title".to_string(),
+                description: "description".to_string(),
+                owner: uuid::Uuid::new_v4(),
+                state: State::Todo,
+                tasks: vec![],
+                created_at: None,
+                doing_at: None,
+                done_at: None,
+                transfers: vec![],
+                collaborators: vec![],
+            },
+            id,
+        );
+        card.share(uuid::Uuid::new_v4(), Role::Viewer);
+        card.share(uuid::Uuid::new_v4(), Role::Editor);
+        let item: HashMap<String, AttributeValue> = todo_card_to_db(card.clone(), id).into();
+
+        assert_eq!(
+            item_to_todocard(&item).unwrap().collaborators,
+            card.collaborators
+        );
     }
 
     #[test]
@@ -137,6 +363,7 @@ mod test {
     #[test]
     fn todo_card_db_to_db_val() {
         let id = uuid::Uuid::new_v4();
+        let created_at = "2023-01-10T10:00:00".parse::<NaiveDateTime>().unwrap();
         let actual: HashMap<String, aws_sdk_dynamodb::model::AttributeValue> = TodoCardDb {
             id: id,
             title: "title".to_string(),
@@ -147,6 +374,11 @@ mod test {
                 is_done: true,
                 title: "title".to_string(),
             }],
+            created_at,
+            doing_at: None,
+            done_at: Some(created_at),
+            transfers: vec![],
+            collaborators: vec![],
         }
         .into();
         let mut expected = HashMap::new();
@@ -162,6 +394,14 @@ mod test {
             "tasks".to_string(),
             val!(L => vec![TaskDb {is_done: true, title: "title".to_string()}.to_db_val()]),
         );
+        expected.insert(
+            "created_at".to_string(),
+            val!(S => "2023-01-10T10:00:00".to_string()),
+        );
+        expected.insert(
+            "done_at".to_string(),
+            val!(S => "2023-01-10T10:00:00".to_string()),
+        );
         assert_eq!(actual, expected);
     }
 }
@@ -245,6 +485,11 @@ mod scan_to_cards {
                 is_done: true,
                 title: "blob".to_string(),
             }],
+            created_at: None,
+            doing_at: None,
+            done_at: None,
+            transfers: vec![],
+            collaborators: vec![],
         }];
 
         assert_eq!(scanoutput_to_todocards(scan).unwrap(), todos)
@@ -263,6 +508,11 @@ mod scan_to_cards {
                 is_done: true,
                 title: "blob".to_string(),
             }],
+            created_at: None,
+            doing_at: None,
+            done_at: None,
+            transfers: vec![],
+            collaborators: vec![],
         };
         let todos = vec![todo.clone(), todo];
 
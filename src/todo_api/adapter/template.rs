@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::model::AttributeValue;
+use uuid::Uuid;
+
+use super::item_tasks;
+use crate::todo_api::model::{TaskDb, TodoTemplateDb};
+use crate::todo_api_web::model::template::TodoTemplate;
+
+pub fn template_to_db(template: TodoTemplate, id: Uuid, owner: Uuid) -> TodoTemplateDb {
+    TodoTemplateDb {
+        id,
+        name: template.name,
+        title: template.title,
+        description: template.description,
+        owner,
+        tasks: template
+            .tasks
+            .into_iter()
+            .map(|t| TaskDb {
+                is_done: t.is_done,
+                title: t.title,
+            })
+            .collect(),
+    }
+}
+
+pub fn item_to_template(item: &HashMap<String, AttributeValue>) -> Option<TodoTemplate> {
+    let id = item.get("id")?.as_s().ok()?;
+    let owner = item.get("owner")?.as_s().ok()?;
+
+    Some(TodoTemplate {
+        id: Uuid::parse_str(id).ok(),
+        name: item.get("name")?.as_s().ok()?.to_string(),
+        title: item.get("title")?.as_s().ok()?.to_string(),
+        description: item.get("description")?.as_s().ok()?.to_string(),
+        owner: Uuid::parse_str(owner).ok(),
+        tasks: item_tasks(item.get("tasks")?.as_l().ok()?),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todo_api_web::model::todo::Task;
+
+    #[test]
+    fn template_roundtrips_through_dynamo_item() {
+        let id = Uuid::new_v4();
+        let owner = Uuid::new_v4();
+        let template = TodoTemplate {
+            id: Some(id),
+            name: "onboarding".to_string(),
+            title: "Onboard {{name}}".to_string(),
+            description: "Welcome {{name}}".to_string(),
+            owner: Some(owner),
+            tasks: vec![Task {
+                is_done: false,
+                title: "Create accounts".to_string(),
+            }],
+        };
+        let item: HashMap<String, AttributeValue> =
+            template_to_db(template.clone(), id, owner).into();
+
+        assert_eq!(item_to_template(&item), Some(template));
+    }
+}
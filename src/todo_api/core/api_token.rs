@@ -0,0 +1,95 @@
+use chrono::{Duration, NaiveDateTime};
+
+use crate::todo_api::core::{hashing::generate_token, scope::is_known_scope};
+
+/// Marks opaque API tokens so they are never decoded as JWTs.
+pub static API_TOKEN_PREFIX: &str = "tdp_";
+pub static MAX_TOKEN_NAME_LEN: usize = 64;
+pub static MAX_API_TOKEN_DAYS: i64 = 365;
+
+pub fn generate_api_token() -> String {
+    format!("{}{}", API_TOKEN_PREFIX, generate_token())
+}
+
+pub fn is_api_token(token: &str) -> bool {
+    token.starts_with(API_TOKEN_PREFIX)
+}
+
+pub fn is_token_name_valid(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty() && name.chars().count() <= MAX_TOKEN_NAME_LEN
+}
+
+/// Sorted, deduplicated and space separated, `None` when a scope is unknown
+/// or none is given.
+pub fn normalize_scopes(scopes: &[String]) -> Option<String> {
+    let mut scopes = scopes
+        .iter()
+        .map(|scope| scope.trim())
+        .collect::<Vec<&str>>();
+    if scopes.is_empty() || !scopes.iter().all(|scope| is_known_scope(scope)) {
+        return None;
+    }
+
+    scopes.sort_unstable();
+    scopes.dedup();
+    Some(scopes.join(" "))
+}
+
+/// Tokens without `days` never expire, others last `1..=MAX_API_TOKEN_DAYS`.
+pub fn is_expiry_valid(days: Option<i64>) -> bool {
+    days.is_none_or(|days| (1..=MAX_API_TOKEN_DAYS).contains(&days))
+}
+
+pub fn token_expiry(days: Option<i64>, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    days.map(|days| now + Duration::days(days))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn api_tokens_are_prefixed() {
+        let token = generate_api_token();
+
+        assert!(is_api_token(&token));
+        assert!(!is_api_token("eyJhbGciOiJFUzI1NiJ9.e30.sig"));
+    }
+
+    #[test]
+    fn scopes_are_normalized() {
+        let scopes = vec![
+            "todo:write".to_string(),
+            " todo:read".to_string(),
+            "todo:write".to_string(),
+        ];
+
+        assert_eq!(
+            normalize_scopes(&scopes),
+            Some("todo:read todo:write".to_string())
+        );
+        assert_eq!(normalize_scopes(&[]), None);
+        assert_eq!(normalize_scopes(&["todo read".to_string()]), None);
+        assert_eq!(normalize_scopes(&["todo:delete".to_string()]), None);
+    }
+
+    #[test]
+    fn expiry_is_bounded() {
+        let now = Utc::now().naive_utc();
+
+        assert_eq!(token_expiry(None, now), None);
+        assert_eq!(token_expiry(Some(30), now), Some(now + Duration::days(30)));
+        assert!(is_expiry_valid(None));
+        assert!(!is_expiry_valid(Some(0)));
+        assert!(!is_expiry_valid(Some(MAX_API_TOKEN_DAYS + 1)));
+    }
+
+    #[test]
+    fn token_names_are_bounded() {
+        assert!(is_token_name_valid("ci deploy"));
+        assert!(!is_token_name_valid("  "));
+        assert!(!is_token_name_valid(&"a".repeat(MAX_TOKEN_NAME_LEN + 1)));
+    }
+}
@@ -0,0 +1,169 @@
+use std::{env, sync::OnceLock};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use log::warn;
+use ring::{
+    digest::{digest, SHA256},
+    rand::{SecureRandom, SystemRandom},
+};
+
+// OWASP minimum recommendation for Argon2id.
+pub static DEFAULT_ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+pub static DEFAULT_ARGON2_ITERATIONS: u32 = 2;
+pub static DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+
+/// Reads `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and `ARGON2_PARALLELISM`,
+/// invalid combinations fall back to the defaults.
+pub fn argon2_params() -> &'static Params {
+    static PARAMS: OnceLock<Params> = OnceLock::new();
+
+    PARAMS.get_or_init(|| {
+        let var = |name: &str, default: u32| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u32>().ok())
+                .unwrap_or(default)
+        };
+        Params::new(
+            var("ARGON2_MEMORY_KIB", DEFAULT_ARGON2_MEMORY_KIB),
+            var("ARGON2_ITERATIONS", DEFAULT_ARGON2_ITERATIONS),
+            var("ARGON2_PARALLELISM", DEFAULT_ARGON2_PARALLELISM),
+            None,
+        )
+        .unwrap_or_else(|e| {
+            warn!("Invalid Argon2 parameters, using the defaults: {}", e);
+            Params::new(
+                DEFAULT_ARGON2_MEMORY_KIB,
+                DEFAULT_ARGON2_ITERATIONS,
+                DEFAULT_ARGON2_PARALLELISM,
+                None,
+            )
+            .expect("Default Argon2 parameters are valid")
+        })
+    })
+}
+
+/// Argon2id hash in PHC format, e.g. `$argon2id$v=19$m=19456,t=2,p=1$...`.
+pub fn hash_password(password: &str) -> String {
+    hash_with(password, argon2_params().clone())
+}
+
+fn hash_with(password: &str, params: Params) -> String {
+    let mut salt = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .expect("Failed to generate salt");
+    let salt = SaltString::b64_encode(&salt).expect("Failed to encode salt");
+
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Failed to hash password")
+        .to_string()
+}
+
+/// Accepts Argon2 PHC strings, verified with the parameters they carry, and
+/// legacy bcrypt hashes.
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    if hash.starts_with("$argon2") {
+        PasswordHash::new(hash).is_ok_and(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+    } else if hash.starts_with("$2") {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    } else {
+        false
+    }
+}
+
+/// Whether the hash should be replaced on the next successful login, being
+/// bcrypt or Argon2 with other parameters than the configured ones.
+pub fn needs_rehash(hash: &str) -> bool {
+    let current = argon2_params();
+    let parsed = match PasswordHash::new(hash) {
+        Ok(parsed) if parsed.algorithm == Algorithm::Argon2id.ident() => parsed,
+        _ => return true,
+    };
+
+    match Params::try_from(&parsed) {
+        Ok(params) => {
+            params.m_cost() != current.m_cost()
+                || params.t_cost() != current.t_cost()
+                || params.p_cost() != current.p_cost()
+        }
+        Err(_) => true,
+    }
+}
+
+/// Random opaque token handed to the client, only its hash is stored.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to generate token");
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Tokens are random and long, a fast unsalted digest is enough to store them.
+pub fn hash_token(token: &str) -> String {
+    base64::encode_config(
+        digest(&SHA256, token.as_bytes()).as_ref(),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn argon2_hash_verifies() {
+        let hash = hash_password("My cr4azy p@ssw0rd");
+
+        assert!(hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+        assert!(verify_password("My cr4azy p@ssw0rd", &hash));
+        assert!(!verify_password("other password", &hash));
+        assert!(!needs_rehash(&hash));
+    }
+
+    #[test]
+    fn bcrypt_hash_verifies_and_needs_rehash() {
+        let hash = bcrypt::hash("My cr4azy p@ssw0rd", 4).unwrap();
+
+        assert!(verify_password("My cr4azy p@ssw0rd", &hash));
+        assert!(!verify_password("other password", &hash));
+        assert!(needs_rehash(&hash));
+    }
+
+    #[test]
+    fn outdated_parameters_need_rehash() {
+        let hash = hash_with("My cr4azy p@ssw0rd", Params::new(1024, 1, 1, None).unwrap());
+
+        assert!(verify_password("My cr4azy p@ssw0rd", &hash));
+        assert!(needs_rehash(&hash));
+    }
+
+    #[test]
+    fn unknown_hashes_never_verify() {
+        assert!(!verify_password("password", "password"));
+        assert!(!verify_password("password", "$argon2id$broken"));
+        assert!(needs_rehash("password"));
+    }
+
+    #[test]
+    fn tokens_are_unique() {
+        assert_ne!(generate_token(), generate_token());
+    }
+
+    #[test]
+    fn token_hash_is_stable_and_hides_token() {
+        let token = generate_token();
+
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeSet;
+
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::todo_api::model::history::{CardHistoryEntry, FieldChange};
+use crate::todo_api_web::model::todo::TodoCard;
+
+pub fn card_diff(before: Option<&TodoCard>, after: Option<&TodoCard>) -> Vec<FieldChange> {
+    let before = card_fields(before);
+    let after = card_fields(after);
+    let fields = before
+        .keys()
+        .chain(after.keys())
+        .collect::<BTreeSet<&String>>();
+
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let from = before.get(field).cloned().unwrap_or(Value::Null);
+            let to = after.get(field).cloned().unwrap_or(Value::Null);
+            if from == to {
+                None
+            } else {
+                Some(FieldChange {
+                    field: field.to_string(),
+                    from,
+                    to,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Finds a later change by someone else to the same card that would be
+/// overwritten by restoring the snapshot. Changes that are part of the same
+/// undo and later changes of `actor` do not conflict, undoing an older change
+/// takes back their own later ones too.
+pub fn conflicting_edit<'a>(
+    later: &'a [CardHistoryEntry],
+    undoing: &[Uuid],
+    actor: Uuid,
+) -> Option<&'a CardHistoryEntry> {
+    later
+        .iter()
+        .find(|entry| entry.actor != actor && !undoing.contains(&entry.id))
+}
+
+/// Whether `actor` may still put the card back to `restored`: deleting or
+/// recreating a card takes its owner, anything else edit rights on the card
+/// as it is now or having owned it before the change.
+pub fn can_revert(actor: Uuid, current: Option<&TodoCard>, restored: Option<&TodoCard>) -> bool {
+    match (current, restored) {
+        (None, None) => true,
+        (Some(current), None) => current.owner == actor,
+        (None, Some(restored)) => restored.owner == actor,
+        (Some(current), Some(restored)) => current.can_edit(actor) || restored.owner == actor,
+    }
+}
+
+fn card_fields(card: Option<&TodoCard>) -> Map<String, Value> {
+    match card.map(serde_json::to_value) {
+        Some(Ok(Value::Object(fields))) => fields,
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todo_api::model::history::HistoryAction;
+    use crate::todo_api_web::model::todo::{Role, State, Task};
+    use serde_json::json;
+
+    fn card() -> TodoCard {
+        TodoCard {
+            id: Some(uuid::Uuid::parse_str("646b670c-bb50-45a4-ba08-3ab684bc4e95").unwrap()),
+            title: "title".to_string(),
+            description: "description".to_string(),
+            owner: uuid::Uuid::parse_str("90e700b0-2b9b-4c74-9285-f5fc94764995").unwrap(),
+            tasks: vec![Task {
+                is_done: false,
+                title: "task".to_string(),
+            }],
+            state: State::Todo,
+            created_at: None,
+            doing_at: None,
+            done_at: None,
+            transfers: vec![],
+            collaborators: vec![],
+        }
+    }
+
+    #[test]
+    fn unchanged_card_has_empty_diff() {
+        assert!(card_diff(Some(&card()), Some(&card())).is_empty());
+    }
+
+    #[test]
+    fn diff_only_lists_changed_fields() {
+        let mut after = card();
+        after.title = "new title".to_string();
+        after.tasks[0].is_done = true;
+
+        assert_eq!(
+            card_diff(Some(&card()), Some(&after)),
+            vec![
+                FieldChange {
+                    field: "tasks".to_string(),
+                    from: json!([{"is_done": false, "title": "task"}]),
+                    to: json!([{"is_done": true, "title": "task"}]),
+                },
+                FieldChange {
+                    field: "title".to_string(),
+                    from: json!("title"),
+                    to: json!("new title"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn later_edit_outside_undo_conflicts() {
+        let mut after = card();
+        after.title = "new title".to_string();
+        let own = CardHistoryEntry::new(
+            after.id.unwrap(),
+            uuid::Uuid::new_v4(),
+            HistoryAction::Update,
+            Some(&card()),
+            Some(&after),
+        );
+        let other = CardHistoryEntry::new(
+            after.id.unwrap(),
+            uuid::Uuid::new_v4(),
+            HistoryAction::Update,
+            Some(&after),
+            Some(&card()),
+        );
+        let later = vec![own.clone(), other.clone()];
+
+        assert_eq!(
+            conflicting_edit(&later, &[own.id, other.id], own.actor),
+            None
+        );
+        assert_eq!(conflicting_edit(&later, &[own.id], own.actor), Some(&other));
+    }
+
+    #[test]
+    fn later_edits_of_the_actor_do_not_conflict() {
+        let mut after = card();
+        after.title = "new title".to_string();
+        let actor = uuid::Uuid::new_v4();
+        let later = vec![CardHistoryEntry::new(
+            after.id.unwrap(),
+            actor,
+            HistoryAction::Update,
+            Some(&card()),
+            Some(&after),
+        )];
+
+        assert_eq!(conflicting_edit(&later, &[], actor), None);
+        assert_eq!(
+            conflicting_edit(&later, &[], uuid::Uuid::new_v4()),
+            later.first()
+        );
+    }
+
+    #[test]
+    fn revoked_collaborators_cannot_revert() {
+        let owner = card().owner;
+        let editor = uuid::Uuid::new_v4();
+        let mut shared = card();
+        shared.share(editor, Role::Editor);
+
+        assert!(can_revert(editor, Some(&shared), Some(&card())));
+        assert!(!can_revert(editor, Some(&card()), Some(&card())));
+        assert!(!can_revert(editor, Some(&shared), None));
+        assert!(can_revert(owner, None, Some(&card())));
+    }
+
+    #[test]
+    fn deleted_card_diffs_to_null() {
+        let diff = card_diff(Some(&card()), None);
+
+        assert!(diff.iter().all(|change| change.to == Value::Null));
+        assert!(diff.iter().any(|change| change.field == "description"));
+    }
+}
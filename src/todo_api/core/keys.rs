@@ -0,0 +1,564 @@
+use std::{env, fs, sync::OnceLock};
+
+use jsonwebtokens::{error::Error, Algorithm, AlgorithmID};
+use log::warn;
+use ring::signature::{self, EcdsaKeyPair, KeyPair, RsaKeyPair};
+
+use crate::todo_api::model::jwks::Jwk;
+
+pub static DEFAULT_JWT_KID: &str = "default";
+static DEFAULT_JWT_SECRET: &str = "secret";
+static DEFAULT_TOTP_KEY: &[u8; 32] = b"todo-server-development-totp-key";
+// DER prefix of a P-256 SubjectPublicKeyInfo, followed by the uncompressed point.
+static P256_SPKI_PREFIX: &[u8] = &[
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyMaterial {
+    Hmac(String),
+    Rsa {
+        pem: Vec<u8>,
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    Ecdsa {
+        pem: Vec<u8>,
+        point: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtKey {
+    pub kid: String,
+    pub alg: AlgorithmID,
+    pub material: KeyMaterial,
+}
+
+impl JwtKey {
+    pub fn hmac(kid: &str, secret: &str) -> Self {
+        Self {
+            kid: kid.to_string(),
+            alg: AlgorithmID::HS256,
+            material: KeyMaterial::Hmac(secret.to_string()),
+        }
+    }
+
+    /// Builds an RS256 or ES256 key from a PKCS#8 (or PKCS#1 for RSA) private
+    /// key PEM, deriving the public part used for verification and the JWKS.
+    pub fn from_pem(kid: &str, alg: AlgorithmID, pem: &[u8]) -> Option<Self> {
+        let der = pem::parse(pem).ok()?;
+        let material = match alg {
+            AlgorithmID::RS256 => {
+                let key_pair = match der.tag.as_str() {
+                    "RSA PRIVATE KEY" => RsaKeyPair::from_der(&der.contents),
+                    _ => RsaKeyPair::from_pkcs8(&der.contents),
+                }
+                .ok()?;
+                let public = key_pair.public_key();
+                KeyMaterial::Rsa {
+                    pem: pem.to_vec(),
+                    n: public.modulus().big_endian_without_leading_zero().to_vec(),
+                    e: public.exponent().big_endian_without_leading_zero().to_vec(),
+                }
+            }
+            AlgorithmID::ES256 => {
+                let key_pair = EcdsaKeyPair::from_pkcs8(
+                    &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+                    &der.contents,
+                )
+                .ok()?;
+                KeyMaterial::Ecdsa {
+                    pem: pem.to_vec(),
+                    point: key_pair.public_key().as_ref().to_vec(),
+                }
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            kid: kid.to_string(),
+            alg,
+            material,
+        })
+    }
+
+    pub fn signer(&self) -> Result<Algorithm, Error> {
+        let mut alg = match &self.material {
+            KeyMaterial::Hmac(secret) => Algorithm::new_hmac(self.alg, secret.as_str()),
+            KeyMaterial::Rsa { pem, .. } => Algorithm::new_rsa_pem_signer(self.alg, pem),
+            KeyMaterial::Ecdsa { pem, .. } => Algorithm::new_ecdsa_pem_signer(self.alg, pem),
+        }?;
+        alg.set_kid(&self.kid);
+        Ok(alg)
+    }
+
+    pub fn verifier(&self) -> Result<Algorithm, Error> {
+        match &self.material {
+            KeyMaterial::Hmac(secret) => Algorithm::new_hmac(self.alg, secret.as_str()),
+            KeyMaterial::Rsa { n, e, .. } => {
+                Algorithm::new_rsa_n_e_b64_verifier(self.alg, &b64(n), &b64(e))
+            }
+            KeyMaterial::Ecdsa { point, .. } => {
+                let spki = pem::Pem {
+                    tag: String::from("PUBLIC KEY"),
+                    contents: [P256_SPKI_PREFIX, point].concat(),
+                };
+                Algorithm::new_ecdsa_pem_verifier(self.alg, pem::encode(&spki).as_bytes())
+            }
+        }
+    }
+
+    /// Public key in JWK form, secrets are never published.
+    pub fn jwk(&self) -> Option<Jwk> {
+        let alg = format!("{:?}", self.alg);
+        match &self.material {
+            KeyMaterial::Hmac(_) => None,
+            KeyMaterial::Rsa { n, e, .. } => Some(Jwk::rsa(&self.kid, alg, b64(n), b64(e))),
+            KeyMaterial::Ecdsa { point, .. } => Some(Jwk::ec(
+                &self.kid,
+                alg,
+                b64(&point[1..33]),
+                b64(&point[33..]),
+            )),
+        }
+    }
+
+    /// Verification-only key from a JWK published by another issuer, such as
+    /// an OpenID Connect provider. `signer` fails for these keys.
+    pub fn from_jwk(jwk: &Jwk) -> Option<Self> {
+        let decode = |value: &Option<String>| {
+            base64::decode_config(value.as_deref()?, base64::URL_SAFE_NO_PAD).ok()
+        };
+        let (alg, material) = match (jwk.kty.as_str(), jwk.alg.as_str()) {
+            ("RSA", "" | "RS256") => (
+                AlgorithmID::RS256,
+                KeyMaterial::Rsa {
+                    pem: Vec::new(),
+                    n: decode(&jwk.n)?,
+                    e: decode(&jwk.e)?,
+                },
+            ),
+            ("EC", "" | "ES256") if jwk.crv.as_deref() == Some("P-256") => {
+                let (x, y) = (decode(&jwk.x)?, decode(&jwk.y)?);
+                if x.len() != 32 || y.len() != 32 {
+                    return None;
+                }
+                (
+                    AlgorithmID::ES256,
+                    KeyMaterial::Ecdsa {
+                        pem: Vec::new(),
+                        point: [&[0x04], x.as_slice(), y.as_slice()].concat(),
+                    },
+                )
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            kid: jwk.kid.clone(),
+            alg,
+            material,
+        })
+    }
+}
+
+/// Keys used to sign and verify tokens. Only the current key signs new tokens,
+/// every configured key that is not retired is still accepted when verifying.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtKeys {
+    keys: Vec<JwtKey>,
+    signing: JwtKey,
+    retired: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyError {
+    NoActiveKey,
+    NoTotpKey,
+}
+
+impl std::fmt::Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::NoActiveKey => write!(
+                f,
+                "No active JWT key configured, set JWT_KEYS or JWT_ALLOW_DEV_KEY=true for development"
+            ),
+            KeyError::NoTotpKey => write!(
+                f,
+                "No TOTP encryption key configured, set TOTP_ENCRYPTION_KEY to 32 base64 encoded bytes or JWT_ALLOW_DEV_KEY=true for development"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl JwtKeys {
+    /// Reads `JWT_KEYS` as comma separated `kid:secret` or `kid:ALG:pem_path`
+    /// entries (`ALG` being `HS256`, `RS256` or `ES256`), `JWT_SIGNING_KID` as
+    /// the key that signs new tokens and `JWT_RETIRED_KIDS` as comma separated
+    /// keys that are no longer accepted. The public development key is only
+    /// used without active keys when `JWT_ALLOW_DEV_KEY` is `true`.
+    pub fn from_env() -> Result<Self, KeyError> {
+        Self::parse(
+            &env::var("JWT_KEYS").unwrap_or_default(),
+            env::var("JWT_SIGNING_KID").ok().as_deref(),
+            &env::var("JWT_RETIRED_KIDS").unwrap_or_default(),
+            dev_keys_allowed(),
+        )
+    }
+
+    pub fn parse(
+        keys: &str,
+        current: Option<&str>,
+        retired: &str,
+        allow_dev_key: bool,
+    ) -> Result<Self, KeyError> {
+        let retired = retired
+            .split(',')
+            .map(str::trim)
+            .filter(|kid| !kid.is_empty())
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let keys = keys
+            .split(',')
+            .filter(|key| !key.trim().is_empty())
+            .filter_map(|key| {
+                let parsed = parse_key(key.trim());
+                if parsed.is_none() {
+                    warn!("Ignoring invalid JWT key configuration {}", key.trim());
+                }
+                parsed
+            })
+            .collect::<Vec<JwtKey>>();
+
+        let active = keys
+            .iter()
+            .filter(|key| !retired.contains(&key.kid))
+            .collect::<Vec<&JwtKey>>();
+        let signing = match current.and_then(|kid| active.iter().find(|key| key.kid == kid)) {
+            Some(key) => (*key).clone(),
+            None => match active.last() {
+                None if allow_dev_key => {
+                    warn!("No active JWT keys configured, using the development key");
+                    return Ok(Self::default());
+                }
+                None => return Err(KeyError::NoActiveKey),
+                Some(key) => {
+                    warn!(
+                        "JWT_SIGNING_KID is not an active key, signing with {}",
+                        key.kid
+                    );
+                    (*key).clone()
+                }
+            },
+        };
+
+        Ok(Self {
+            keys,
+            signing,
+            retired,
+        })
+    }
+
+    pub fn signing_key(&self) -> &JwtKey {
+        &self.signing
+    }
+
+    pub fn verification_key(&self, kid: &str) -> Option<&JwtKey> {
+        if self.retired.iter().any(|retired| retired == kid) {
+            return None;
+        }
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    pub fn public_keys(&self) -> Vec<Jwk> {
+        self.keys
+            .iter()
+            .filter(|key| !self.retired.contains(&key.kid))
+            .filter_map(JwtKey::jwk)
+            .collect()
+    }
+}
+
+impl Default for JwtKeys {
+    fn default() -> Self {
+        let key = JwtKey::hmac(DEFAULT_JWT_KID, DEFAULT_JWT_SECRET);
+        Self {
+            keys: vec![key.clone()],
+            signing: key,
+            retired: Vec::new(),
+        }
+    }
+}
+
+static KEYS: OnceLock<JwtKeys> = OnceLock::new();
+static TOTP_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// Development keys are public, tests and `JWT_ALLOW_DEV_KEY=true` only.
+fn dev_keys_allowed() -> bool {
+    cfg!(any(test, feature = "db-test"))
+        || env::var("JWT_ALLOW_DEV_KEY").is_ok_and(|allow| allow == "true")
+}
+
+/// Loads the keys, `main` calls it before serving so a missing configuration
+/// stops the server instead of failing on the first token.
+pub fn init_jwt_keys() -> Result<&'static JwtKeys, KeyError> {
+    if let Some(keys) = KEYS.get() {
+        return Ok(keys);
+    }
+    let keys = JwtKeys::from_env()?;
+    Ok(KEYS.get_or_init(|| keys))
+}
+
+pub fn jwt_keys() -> &'static JwtKeys {
+    init_jwt_keys().expect("JWT keys are loaded at startup")
+}
+
+/// Reads `TOTP_ENCRYPTION_KEY`, the base64 encoded AES-256 key sealing TOTP
+/// secrets in the database.
+pub fn init_totp_key() -> Result<&'static [u8; 32], KeyError> {
+    if let Some(key) = TOTP_KEY.get() {
+        return Ok(key);
+    }
+    let key = parse_totp_key(
+        &env::var("TOTP_ENCRYPTION_KEY").unwrap_or_default(),
+        dev_keys_allowed(),
+    )?;
+    Ok(TOTP_KEY.get_or_init(|| key))
+}
+
+pub fn totp_key() -> &'static [u8; 32] {
+    init_totp_key().expect("TOTP key is loaded at startup")
+}
+
+fn parse_totp_key(encoded: &str, allow_dev_key: bool) -> Result<[u8; 32], KeyError> {
+    match base64::decode(encoded.trim()).map(<[u8; 32]>::try_from) {
+        Ok(Ok(key)) => Ok(key),
+        _ if allow_dev_key => {
+            warn!("No valid TOTP encryption key configured, using the development key");
+            Ok(*DEFAULT_TOTP_KEY)
+        }
+        _ => Err(KeyError::NoTotpKey),
+    }
+}
+
+/// The `alg` names of the signing algorithms keys can be configured with.
+pub fn algorithm_id(name: &str) -> Option<AlgorithmID> {
+    match name {
+        "HS256" => Some(AlgorithmID::HS256),
+        "RS256" => Some(AlgorithmID::RS256),
+        "ES256" => Some(AlgorithmID::ES256),
+        _ => None,
+    }
+}
+
+fn parse_key(key: &str) -> Option<JwtKey> {
+    let (kid, rest) = key.split_once(':')?;
+    let (alg, material) = match rest
+        .split_once(':')
+        .and_then(|(alg, material)| Some((algorithm_id(alg)?, material)))
+    {
+        Some(key) => key,
+        None => (AlgorithmID::HS256, rest),
+    };
+    if kid.is_empty() || material.is_empty() {
+        return None;
+    }
+
+    match alg {
+        AlgorithmID::HS256 => Some(JwtKey::hmac(kid, material)),
+        _ => JwtKey::from_pem(kid, alg, &fs::read(material).ok()?),
+    }
+}
+
+fn b64(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Throwaway asymmetric keys for tests, generated once per test run so that no
+/// private key has to live in the repository.
+#[cfg(test)]
+pub(crate) mod test_keys {
+    use std::{path::PathBuf, process::Command, sync::OnceLock};
+
+    use ring::{rand::SystemRandom, signature};
+
+    static KEY_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+    fn key_dir() -> &'static PathBuf {
+        KEY_DIR.get_or_init(|| {
+            let dir = std::env::temp_dir().join(format!("todo-server-keys-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let ec = signature::EcdsaKeyPair::generate_pkcs8(
+                &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+                &SystemRandom::new(),
+            )
+            .unwrap();
+            let ec_pem = pem::encode(&pem::Pem {
+                tag: String::from("PRIVATE KEY"),
+                contents: ec.as_ref().to_vec(),
+            });
+            std::fs::write(dir.join("es256.pem"), ec_pem).unwrap();
+
+            // ring can't generate RSA keys, openssl ships wherever the tests run.
+            let rsa = Command::new("openssl")
+                .args([
+                    "genpkey",
+                    "-algorithm",
+                    "RSA",
+                    "-pkeyopt",
+                    "rsa_keygen_bits:2048",
+                ])
+                .arg("-out")
+                .arg(dir.join("rs256.pem"))
+                .status()
+                .unwrap();
+            assert!(rsa.success(), "openssl could not generate an RSA key");
+
+            dir
+        })
+    }
+
+    pub(crate) fn rs256_pem() -> String {
+        key_dir().join("rs256.pem").display().to_string()
+    }
+
+    pub(crate) fn es256_pem() -> String {
+        key_dir().join("es256.pem").display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::test_keys::{es256_pem, rs256_pem};
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn signs_with_configured_key() {
+        let keys = JwtKeys::parse("old:one, new:two", Some("new"), "", false).unwrap();
+
+        assert_eq!(keys.signing_key().material, KeyMaterial::Hmac("two".into()));
+        assert_eq!(
+            keys.verification_key("old").unwrap().material,
+            KeyMaterial::Hmac("one".into())
+        );
+    }
+
+    #[test]
+    fn retired_keys_are_not_accepted() {
+        let keys = JwtKeys::parse("old:one,new:two", Some("old"), "old", false).unwrap();
+
+        assert_eq!(keys.signing_key().kid, "new");
+        assert_eq!(keys.verification_key("old"), None);
+        assert_eq!(keys.verification_key("unknown"), None);
+    }
+
+    #[test]
+    fn falls_back_to_default_key_only_when_allowed() {
+        assert_eq!(JwtKeys::parse("", None, "", true), Ok(JwtKeys::default()));
+        assert_eq!(
+            JwtKeys::parse("only:one", None, "only", true),
+            Ok(JwtKeys::default())
+        );
+        assert_eq!(
+            JwtKeys::parse("", None, "", false),
+            Err(KeyError::NoActiveKey)
+        );
+        assert_eq!(
+            JwtKeys::parse("only:one", None, "only", false),
+            Err(KeyError::NoActiveKey)
+        );
+    }
+
+    #[test]
+    fn totp_key_needs_32_bytes_outside_development() {
+        let key = base64::encode([7u8; 32]);
+
+        assert_eq!(parse_totp_key(&key, false), Ok([7u8; 32]));
+        assert_eq!(
+            parse_totp_key(&base64::encode([7u8; 16]), false),
+            Err(KeyError::NoTotpKey)
+        );
+        assert_eq!(parse_totp_key("", false), Err(KeyError::NoTotpKey));
+        assert_eq!(parse_totp_key("", true), Ok(*DEFAULT_TOTP_KEY));
+    }
+
+    #[test]
+    fn maps_only_supported_algorithm_names() {
+        assert_eq!(algorithm_id("RS256"), Some(AlgorithmID::RS256));
+        assert_eq!(algorithm_id("ES256"), Some(AlgorithmID::ES256));
+        assert_eq!(algorithm_id("rs256"), None);
+        assert_eq!(algorithm_id("none"), None);
+        assert_eq!(algorithm_id("RS512"), None);
+    }
+
+    #[test]
+    fn asymmetric_keys_sign_and_verify() {
+        let config = format!("rsa:RS256:{},ec:ES256:{}", rs256_pem(), es256_pem());
+        let keys = JwtKeys::parse(&config, Some("rsa"), "", false).unwrap();
+        let claims = json!({ "id": "id" });
+
+        for kid in ["rsa", "ec"] {
+            let key = keys.verification_key(kid).unwrap();
+            let header = json!({ "alg": key.signer().unwrap().name(), "kid": kid });
+            let token = jsonwebtokens::encode(&header, &claims, &key.signer().unwrap()).unwrap();
+            let verified = jsonwebtokens::Verifier::create()
+                .build()
+                .unwrap()
+                .verify(&token, &key.verifier().unwrap());
+
+            assert_eq!(verified.unwrap(), claims);
+        }
+    }
+
+    #[test]
+    fn publishes_only_public_keys() {
+        let config = format!(
+            "hmac:secret,rsa:RS256:{},ec:ES256:{}",
+            rs256_pem(),
+            es256_pem()
+        );
+        let jwks = JwtKeys::parse(&config, Some("hmac"), "", false)
+            .unwrap()
+            .public_keys();
+
+        assert_eq!(
+            jwks.iter().map(|k| k.kid.as_str()).collect::<Vec<&str>>(),
+            vec!["rsa", "ec"]
+        );
+        assert_eq!(jwks[0].kty, "RSA");
+        assert_eq!(jwks[1].crv.as_deref(), Some("P-256"));
+    }
+
+    #[test]
+    fn published_keys_verify_again() {
+        let config = format!("rsa:RS256:{},ec:ES256:{}", rs256_pem(), es256_pem());
+        let keys = JwtKeys::parse(&config, Some("rsa"), "", false).unwrap();
+
+        for key in ["rsa", "ec"].map(|kid| keys.verification_key(kid).unwrap()) {
+            let public = JwtKey::from_jwk(&key.jwk().unwrap()).unwrap();
+            let header = json!({ "alg": key.signer().unwrap().name(), "kid": key.kid });
+            let token = jsonwebtokens::encode(&header, &json!({}), &key.signer().unwrap()).unwrap();
+
+            assert!(public.signer().is_err());
+            assert!(jsonwebtokens::Verifier::create()
+                .build()
+                .unwrap()
+                .verify(&token, &public.verifier().unwrap())
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn missing_pem_is_ignored() {
+        let keys = JwtKeys::parse("rsa:RS256:missing.pem", None, "", true);
+
+        assert_eq!(keys, Ok(JwtKeys::default()));
+    }
+}
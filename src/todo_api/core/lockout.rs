@@ -0,0 +1,236 @@
+use chrono::{Duration, NaiveDateTime};
+
+use crate::todo_api::mailer::Email;
+use crate::todo_api::model::lockout::LoginThrottle;
+
+pub static DEFAULT_ACCOUNT_LOCKOUT_THRESHOLD: i32 = 10;
+pub static DEFAULT_IP_LOCKOUT_THRESHOLD: i32 = 50;
+pub static DEFAULT_RESET_THRESHOLD: i32 = 5;
+pub static DEFAULT_LOCKOUT_SECS: i64 = 15 * 60;
+pub static DEFAULT_UNLOCK_URL: &str = "http://localhost:4000/unlock-account";
+// Failures before the threshold wait 1s, 2s, 4s... capped at this delay.
+static MAX_BACKOFF_SECS: i64 = 5 * 60;
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+pub fn account_key(email: &str) -> String {
+    format!("account:{}", email.trim().to_lowercase())
+}
+
+pub fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+/// Password reset requests are throttled apart from logins, so asking for
+/// resets never locks anyone out of their account.
+pub fn reset_key(throttle_key: &str) -> String {
+    format!("reset:{}", throttle_key)
+}
+
+/// Exponential backoff between failed logins and a temporary lockout once
+/// `threshold` consecutive failures are reached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockoutPolicy {
+    pub threshold: i32,
+    pub lockout_secs: i64,
+}
+
+impl LockoutPolicy {
+    pub fn account() -> Self {
+        Self {
+            threshold: env_or(
+                "ACCOUNT_LOCKOUT_THRESHOLD",
+                DEFAULT_ACCOUNT_LOCKOUT_THRESHOLD,
+            ),
+            lockout_secs: env_or("LOCKOUT_SECS", DEFAULT_LOCKOUT_SECS),
+        }
+    }
+
+    pub fn ip() -> Self {
+        Self {
+            threshold: env_or("IP_LOCKOUT_THRESHOLD", DEFAULT_IP_LOCKOUT_THRESHOLD),
+            lockout_secs: env_or("LOCKOUT_SECS", DEFAULT_LOCKOUT_SECS),
+        }
+    }
+
+    pub fn password_reset() -> Self {
+        Self {
+            threshold: env_or("RESET_THRESHOLD", DEFAULT_RESET_THRESHOLD),
+            lockout_secs: env_or("LOCKOUT_SECS", DEFAULT_LOCKOUT_SECS),
+        }
+    }
+
+    pub fn backoff(&self, failures: i32) -> Duration {
+        if failures <= 0 {
+            return Duration::zero();
+        }
+        let exponent = (failures - 1).min(30) as u32;
+        Duration::seconds(2i64.saturating_pow(exponent).min(MAX_BACKOFF_SECS))
+    }
+
+    /// When the next attempt is allowed, `None` if it is allowed now.
+    pub fn retry_at(&self, throttle: &LoginThrottle, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let retry_at = match throttle.locked_until {
+            Some(locked_until) => locked_until,
+            None => throttle.last_failure_at + self.backoff(throttle.failures),
+        };
+        (retry_at > now).then_some(retry_at)
+    }
+
+    /// Counts a failure, returns true when it locks the key. Failures after an
+    /// expired lockout start counting from scratch.
+    pub fn record_failure(&self, throttle: &mut LoginThrottle, now: NaiveDateTime) -> bool {
+        if throttle.locked_until.is_some_and(|until| until <= now) {
+            throttle.failures = 0;
+            throttle.locked_until = None;
+        }
+        throttle.failures += 1;
+        throttle.last_failure_at = now;
+
+        if throttle.failures >= self.threshold && throttle.locked_until.is_none() {
+            throttle.locked_until = Some(now + Duration::seconds(self.lockout_secs));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Takes back an attempt counted before the credentials were checked once
+    /// it turned out not to be a failure, along with the lockout it caused.
+    pub fn release_attempt(&self, throttle: &mut LoginThrottle) {
+        throttle.failures = (throttle.failures - 1).max(0);
+        if throttle.failures < self.threshold {
+            throttle.locked_until = None;
+        }
+    }
+}
+
+/// Throttles without a failure since this time and no running lockout can be
+/// dropped, their backoff is over and failures that far apart no longer count
+/// towards a lockout.
+pub fn throttle_expiry(now: NaiveDateTime) -> NaiveDateTime {
+    let lockout_secs = env_or("LOCKOUT_SECS", DEFAULT_LOCKOUT_SECS);
+    now - Duration::seconds(lockout_secs.max(MAX_BACKOFF_SECS))
+}
+
+/// Emails allowed to lift lockouts through the admin endpoint.
+pub fn admin_emails() -> Vec<String> {
+    std::env::var("ADMIN_EMAILS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty())
+        .collect()
+}
+
+pub fn unlock_email(to: &str, token: &str, policy: &LockoutPolicy) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Your account was locked".to_string(),
+        body: format!(
+            "We locked your account for {} minutes after too many failed logins.\n\n\
+             If it was you, follow {}?token={} to unlock it now. If it wasn't, \
+             consider resetting your password.",
+            policy.lockout_secs / 60,
+            std::env::var("UNLOCK_URL").unwrap_or_else(|_| DEFAULT_UNLOCK_URL.to_string()),
+            token
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Utc;
+
+    fn policy() -> LockoutPolicy {
+        LockoutPolicy {
+            threshold: 3,
+            lockout_secs: 600,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_cap() {
+        let policy = policy();
+
+        assert_eq!(policy.backoff(0), Duration::zero());
+        assert_eq!(policy.backoff(1), Duration::seconds(1));
+        assert_eq!(policy.backoff(4), Duration::seconds(8));
+        assert_eq!(policy.backoff(40), Duration::seconds(MAX_BACKOFF_SECS));
+    }
+
+    #[test]
+    fn locks_after_threshold() {
+        let policy = policy();
+        let now = Utc::now().naive_utc();
+        let mut throttle = LoginThrottle::new(account_key("My@Email.com"), now);
+
+        assert!(!policy.record_failure(&mut throttle, now));
+        assert!(!policy.record_failure(&mut throttle, now));
+        assert_eq!(
+            policy.retry_at(&throttle, now),
+            Some(now + Duration::seconds(2))
+        );
+        assert!(policy.record_failure(&mut throttle, now));
+        assert_eq!(
+            policy.retry_at(&throttle, now),
+            Some(now + Duration::seconds(600))
+        );
+        assert_eq!(throttle.key, "account:my@email.com");
+    }
+
+    #[test]
+    fn reset_requests_do_not_share_login_keys() {
+        assert_eq!(
+            reset_key(&account_key("My@Email.com")),
+            "reset:account:my@email.com"
+        );
+        assert_ne!(reset_key(&ip_key("10.0.0.1")), ip_key("10.0.0.1"));
+    }
+
+    #[test]
+    fn throttles_expire_after_their_backoff() {
+        let now = Utc::now().naive_utc();
+        let mut throttle = LoginThrottle::new(account_key("my@email.com"), throttle_expiry(now));
+        throttle.failures = 40;
+
+        assert!(throttle_expiry(now) <= now - Duration::seconds(DEFAULT_LOCKOUT_SECS));
+        assert_eq!(policy().retry_at(&throttle, now), None);
+    }
+
+    #[test]
+    fn expired_lockout_starts_over() {
+        let policy = policy();
+        let now = Utc::now().naive_utc();
+        let mut throttle = LoginThrottle::new(ip_key("10.0.0.1"), now);
+        throttle.failures = 3;
+        throttle.locked_until = Some(now - Duration::seconds(1));
+
+        assert_eq!(policy.retry_at(&throttle, now), None);
+        assert!(!policy.record_failure(&mut throttle, now));
+        assert_eq!(throttle.failures, 1);
+    }
+
+    #[test]
+    fn released_attempt_lifts_its_lockout() {
+        let policy = policy();
+        let now = Utc::now().naive_utc();
+        let mut throttle = LoginThrottle::new(ip_key("10.0.0.1"), now);
+        throttle.failures = 2;
+
+        assert!(policy.record_failure(&mut throttle, now));
+        policy.release_attempt(&mut throttle);
+        assert_eq!(throttle.failures, 2);
+        assert_eq!(throttle.locked_until, None);
+        assert_eq!(
+            policy.retry_at(&throttle, now),
+            Some(now + Duration::seconds(2))
+        );
+    }
+}
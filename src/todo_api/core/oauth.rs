@@ -0,0 +1,217 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use ring::digest::{digest, SHA256};
+
+use crate::todo_api::core::{hashing::generate_token, scope::TODO_READ};
+
+pub static OAUTH_CODE_SECS: i64 = 60;
+pub static CLIENT_ID_PREFIX: &str = "tdc_";
+pub static MAX_REDIRECT_URIS: usize = 10;
+pub static MAX_CLIENT_NAME_LEN: usize = 64;
+/// Scope granted when the authorization request names none.
+pub static DEFAULT_OAUTH_SCOPE: &str = TODO_READ;
+pub static PKCE_METHOD: &str = "S256";
+
+pub fn generate_client_id() -> String {
+    format!("{}{}", CLIENT_ID_PREFIX, &generate_token()[..22])
+}
+
+pub fn is_client_valid(name: &str, redirect_uris: &[String]) -> bool {
+    let name = name.trim();
+    !name.is_empty()
+        && name.chars().count() <= MAX_CLIENT_NAME_LEN
+        && (1..=MAX_REDIRECT_URIS).contains(&redirect_uris.len())
+        && redirect_uris.iter().all(|uri| is_redirect_uri_valid(uri))
+}
+
+static REDIRECT_URI: OnceLock<Regex> = OnceLock::new();
+static CODE_VERIFIER: OnceLock<Regex> = OnceLock::new();
+
+/// HTTPS anywhere, plain HTTP only for loopback redirects of native apps.
+pub fn is_redirect_uri_valid(uri: &str) -> bool {
+    let redirect_regex = REDIRECT_URI.get_or_init(|| {
+        Regex::new(r"^(https://[^\s/?#]+|http://(localhost|127\.0\.0\.1)(:\d{1,5})?)(/[^\s#]*)?$")
+            .unwrap()
+    });
+    redirect_regex.is_match(uri)
+}
+
+/// RFC 7636 verifiers are 43 to 128 unreserved characters.
+pub fn is_code_verifier_valid(verifier: &str) -> bool {
+    let verifier_regex =
+        CODE_VERIFIER.get_or_init(|| Regex::new(r"^[A-Za-z0-9\-._~]{43,128}$").unwrap());
+    verifier_regex.is_match(verifier)
+}
+
+pub fn pkce_challenge(verifier: &str) -> String {
+    base64::encode_config(
+        digest(&SHA256, verifier.as_bytes()).as_ref(),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
+
+pub fn verify_pkce(verifier: &str, challenge: &str) -> bool {
+    is_code_verifier_valid(verifier) && pkce_challenge(verifier) == challenge
+}
+
+/// Appends the parameters to a registered redirect URI.
+pub fn redirect_with(uri: &str, params: &[(&str, &str)]) -> String {
+    let query = serde_urlencoded::to_string(params).unwrap_or_default();
+    let separator = if uri.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", uri, separator, query)
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#39;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Login and consent form for an authorization request. `fields` are carried
+/// as hidden inputs so the form posts the original request back.
+pub fn consent_page(
+    client_name: &str,
+    scope: &str,
+    fields: &[(&str, &str)],
+    error: Option<&str>,
+) -> String {
+    let hidden = fields
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "<input type=\"hidden\" name=\"{}\" value=\"{}\">",
+                name,
+                escape_html(value)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n      ");
+    let scopes = scope
+        .split_whitespace()
+        .map(|scope| format!("<li>{}</li>", escape_html(scope)))
+        .collect::<String>();
+    let error = error
+        .map(|error| format!("<p class=\"error\">{}</p>", escape_html(error)))
+        .unwrap_or_default();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head><meta charset="utf-8"><title>Authorize {client}</title></head>
+  <body>
+    <h1>{client} wants to access your account</h1>
+    <p>It will be allowed to:</p>
+    <ul>{scopes}</ul>
+    {error}
+    <form method="post" action="/oauth/authorize">
+      {hidden}
+      <label>Email <input type="email" name="email" required></label>
+      <label>Password <input type="password" name="password" required></label>
+      <label>Two-factor code <input type="text" name="otp" autocomplete="one-time-code"></label>
+      <button type="submit" name="decision" value="approve">Allow</button>
+      <button type="submit" name="decision" value="deny" formnovalidate>Deny</button>
+    </form>
+  </body>
+</html>"#,
+        client = escape_html(client_name),
+        scopes = scopes,
+        error = error,
+        hidden = hidden,
+    )
+}
+
+/// Shown instead of redirecting when the client or redirect URI is invalid.
+pub fn error_page(description: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n  <head><meta charset=\"utf-8\"><title>Authorization error</title></head>\n  <body><h1>Authorization error</h1><p>{}</p></body>\n</html>",
+        escape_html(description)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pkce_matches_rfc_7636_example() {
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+        assert_eq!(
+            pkce_challenge(verifier),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert!(verify_pkce(
+            verifier,
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        ));
+        assert!(!verify_pkce("short", &pkce_challenge("short")));
+    }
+
+    #[test]
+    fn redirect_uris_need_https_or_loopback() {
+        assert!(is_redirect_uri_valid("https://app.example.com/callback"));
+        assert!(is_redirect_uri_valid("http://127.0.0.1:8123/cb"));
+        assert!(is_redirect_uri_valid("http://localhost"));
+        assert!(!is_redirect_uri_valid("http://app.example.com/callback"));
+        assert!(!is_redirect_uri_valid(
+            "https://app.example.com/cb#fragment"
+        ));
+        assert!(!is_redirect_uri_valid("javascript:alert(1)"));
+    }
+
+    #[test]
+    fn clients_need_a_name_and_redirect_uris() {
+        let uris = vec!["https://app.example.com/cb".to_string()];
+
+        assert!(is_client_valid("app", &uris));
+        assert!(!is_client_valid(" ", &uris));
+        assert!(!is_client_valid("app", &[]));
+        assert!(!is_client_valid("app", &["ftp://app".to_string()]));
+    }
+
+    #[test]
+    fn redirect_appends_encoded_params() {
+        assert_eq!(
+            redirect_with(
+                "https://app.example.com/cb",
+                &[("code", "a b"), ("state", "x&y")]
+            ),
+            "https://app.example.com/cb?code=a+b&state=x%26y"
+        );
+        assert_eq!(
+            redirect_with("https://app.example.com/cb?tab=1", &[("code", "c")]),
+            "https://app.example.com/cb?tab=1&code=c"
+        );
+    }
+
+    #[test]
+    fn consent_page_escapes_client_input() {
+        let page = consent_page(
+            "<script>",
+            "todo:read",
+            &[("state", "\"><img src=x>")],
+            None,
+        );
+
+        assert!(!page.contains("<script>"));
+        assert!(page.contains("&lt;script&gt;"));
+        assert!(page.contains("value=\"&quot;&gt;&lt;img src=x&gt;\""));
+        assert!(page.contains("<li>todo:read</li>"));
+    }
+
+    #[test]
+    fn client_ids_are_prefixed() {
+        let id = generate_client_id();
+
+        assert!(id.starts_with(CLIENT_ID_PREFIX));
+        assert_eq!(id.len(), CLIENT_ID_PREFIX.len() + 22);
+    }
+}
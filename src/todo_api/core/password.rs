@@ -0,0 +1,45 @@
+use crate::todo_api::mailer::Email;
+
+pub static DEFAULT_PASSWORD_RESET_SECS: i64 = 60 * 60;
+pub static DEFAULT_PASSWORD_RESET_URL: &str = "http://localhost:4000/reset-password";
+
+pub fn password_reset_secs() -> i64 {
+    std::env::var("PASSWORD_RESET_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_PASSWORD_RESET_SECS)
+}
+
+pub fn password_reset_url() -> String {
+    std::env::var("PASSWORD_RESET_URL").unwrap_or_else(|_| DEFAULT_PASSWORD_RESET_URL.to_string())
+}
+
+pub fn reset_email(to: &str, token: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Someone asked to reset the password of your account.\n\n\
+             Follow {}?token={} within {} minutes to choose a new one.\n\n\
+             If it wasn't you, ignore this email and your password stays the same.",
+            password_reset_url(),
+            token,
+            password_reset_secs() / 60
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reset_email_links_token() {
+        let email = reset_email("my@email.com", "abc");
+
+        assert_eq!(email.to, "my@email.com");
+        assert!(email
+            .body
+            .contains("http://localhost:4000/reset-password?token=abc"));
+    }
+}
@@ -0,0 +1,16 @@
+pub static DEFAULT_ACCESS_TOKEN_SECS: i64 = 900;
+pub static DEFAULT_REFRESH_TOKEN_SECS: i64 = 30 * 24 * 60 * 60;
+
+pub fn access_token_secs() -> i64 {
+    std::env::var("ACCESS_TOKEN_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_ACCESS_TOKEN_SECS)
+}
+
+pub fn refresh_token_secs() -> i64 {
+    std::env::var("REFRESH_TOKEN_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_REFRESH_TOKEN_SECS)
+}
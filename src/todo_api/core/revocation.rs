@@ -0,0 +1,154 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use log::error;
+use uuid::Uuid;
+
+use crate::todo_api::model::revocation::{RevokeToken, RevokedToken, SyncRevokedTokens};
+use crate::todo_api_web::model::http::Clients;
+
+pub static DEFAULT_REVOCATION_SYNC_SECS: i64 = 30;
+// Revocations committed while a sync was running are read again next time.
+static SYNC_OVERLAP_SECS: i64 = 5;
+
+pub fn revocation_sync_secs() -> i64 {
+    std::env::var("REVOCATION_SYNC_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_REVOCATION_SYNC_SECS)
+}
+
+/// In-process copy of the revoked `jti`s, kept until the token would have
+/// expired anyway and refreshed from Postgres every `REVOCATION_SYNC_SECS`.
+#[derive(Debug, Default)]
+pub struct RevocationCache {
+    revoked: RwLock<HashMap<Uuid, NaiveDateTime>>,
+    synced_at: RwLock<Option<NaiveDateTime>>,
+}
+
+impl RevocationCache {
+    pub fn is_revoked(&self, jti: Uuid, now: NaiveDateTime) -> bool {
+        match self.revoked.read() {
+            Ok(revoked) => revoked.get(&jti).map(|exp| *exp > now).unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    pub fn insert(&self, jti: Uuid, expires_at: NaiveDateTime) {
+        if let Ok(mut revoked) = self.revoked.write() {
+            revoked.insert(jti, expires_at);
+        }
+    }
+
+    pub fn merge(&self, tokens: Vec<RevokedToken>, now: NaiveDateTime) {
+        if let Ok(mut revoked) = self.revoked.write() {
+            revoked.extend(tokens.into_iter().map(|t| (t.jti, t.expires_at)));
+            revoked.retain(|_, exp| *exp > now);
+        }
+        if let Ok(mut synced_at) = self.synced_at.write() {
+            *synced_at = Some(now);
+        }
+    }
+
+    pub fn sync_since(&self) -> Option<NaiveDateTime> {
+        let synced_at = *self.synced_at.read().ok()?;
+        synced_at.map(|at| at - Duration::seconds(SYNC_OVERLAP_SECS))
+    }
+
+    pub fn needs_sync(&self, now: NaiveDateTime) -> bool {
+        match self.synced_at.read().map(|at| *at) {
+            Ok(Some(at)) => now - at >= Duration::seconds(revocation_sync_secs()),
+            _ => true,
+        }
+    }
+}
+
+pub async fn is_token_revoked(state: &Clients, jti: Uuid) -> bool {
+    let now = Utc::now().naive_utc();
+    if state.revoked.needs_sync(now) {
+        let sync = SyncRevokedTokens {
+            since: state.revoked.sync_since(),
+        };
+        match state.postgres.send(sync).await {
+            Ok(Ok(tokens)) => state.revoked.merge(tokens, now),
+            e => error!("Failed to sync revoked tokens {:?}", e),
+        }
+    }
+    state.revoked.is_revoked(jti, now)
+}
+
+pub async fn revoke_token(
+    state: &Clients,
+    jti: Uuid,
+    user_id: Uuid,
+    expires_at: NaiveDateTime,
+) -> bool {
+    let revoked = RevokedToken {
+        jti,
+        user_id,
+        revoked_at: Utc::now().naive_utc(),
+        expires_at,
+    };
+
+    match state.postgres.send(RevokeToken(revoked)).await {
+        Ok(Ok(_)) => {
+            state.revoked.insert(jti, expires_at);
+            true
+        }
+        e => {
+            error!("Failed to revoke token {} {:?}", jti, e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn revoked(jti: Uuid, expires_at: NaiveDateTime) -> RevokedToken {
+        RevokedToken {
+            jti,
+            user_id: Uuid::new_v4(),
+            revoked_at: expires_at - Duration::minutes(10),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn revoked_until_expiry() {
+        let cache = RevocationCache::default();
+        let jti = Uuid::new_v4();
+        let now = Utc::now().naive_utc();
+        cache.insert(jti, now + Duration::minutes(1));
+
+        assert!(cache.is_revoked(jti, now));
+        assert!(!cache.is_revoked(jti, now + Duration::minutes(1)));
+        assert!(!cache.is_revoked(Uuid::new_v4(), now));
+    }
+
+    #[test]
+    fn merge_drops_expired_and_marks_synced() {
+        let cache = RevocationCache::default();
+        let now = Utc::now().naive_utc();
+        let live = Uuid::new_v4();
+        let expired = Uuid::new_v4();
+
+        assert!(cache.needs_sync(now));
+        cache.merge(
+            vec![
+                revoked(live, now + Duration::minutes(5)),
+                revoked(expired, now - Duration::minutes(5)),
+            ],
+            now,
+        );
+
+        assert!(cache.is_revoked(live, now));
+        assert!(!cache.is_revoked(expired, now));
+        assert!(!cache.needs_sync(now));
+        assert_eq!(
+            cache.sync_since(),
+            Some(now - Duration::seconds(SYNC_OVERLAP_SECS))
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::todo_api::core::lockout::admin_emails;
+
+pub static TODO_READ: &str = "todo:read";
+pub static TODO_WRITE: &str = "todo:write";
+/// Sessions, two-factor and API token management.
+pub static ACCOUNT: &str = "account";
+pub static ADMIN: &str = "admin";
+pub static KNOWN_SCOPES: [&str; 4] = ["todo:read", "todo:write", "account", "admin"];
+
+pub fn is_known_scope(scope: &str) -> bool {
+    KNOWN_SCOPES.contains(&scope)
+}
+
+/// Scopes granted to an interactive login, `admin` only for `ADMIN_EMAILS`.
+pub fn user_scopes(email: &str) -> String {
+    let mut scopes = vec![ACCOUNT, TODO_READ, TODO_WRITE];
+    if admin_emails().contains(&email.to_lowercase()) {
+        scopes.insert(0, ADMIN);
+    }
+    scopes.join(" ")
+}
+
+/// `granted` is a space separated scope list as found in tokens.
+pub fn has_scope(granted: &str, required: &str) -> bool {
+    granted.split_whitespace().any(|scope| scope == required)
+}
+
+/// First scope of `requested` that `granted` does not include.
+pub fn missing_scope<'a>(granted: &str, requested: &'a str) -> Option<&'a str> {
+    requested
+        .split_whitespace()
+        .find(|scope| !has_scope(granted, scope))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn users_get_todo_and_account_scopes() {
+        let scopes = user_scopes("my@email.com");
+
+        assert!(has_scope(&scopes, TODO_READ));
+        assert!(has_scope(&scopes, TODO_WRITE));
+        assert!(has_scope(&scopes, ACCOUNT));
+        assert!(!has_scope(&scopes, ADMIN));
+    }
+
+    #[test]
+    fn scopes_match_whole_words() {
+        assert!(!has_scope("todo:read", "todo"));
+        assert!(!has_scope("todo:read", "todo:write"));
+        assert!(has_scope("account todo:read", "todo:read"));
+    }
+
+    #[test]
+    fn missing_scope_names_first_absent_scope() {
+        assert_eq!(missing_scope("todo:read", "todo:read admin"), Some("admin"));
+        assert_eq!(missing_scope("todo:read admin", "admin"), None);
+    }
+
+    #[test]
+    fn only_known_scopes_are_accepted() {
+        assert!(KNOWN_SCOPES.iter().all(|scope| is_known_scope(scope)));
+        assert!(!is_known_scope("todo:delete"));
+    }
+}
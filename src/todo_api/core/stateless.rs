@@ -0,0 +1,141 @@
+use std::{env, sync::OnceLock};
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::todo_api::core::{refresh::access_token_secs, validate_jwt_date};
+use crate::todo_api::model::core::JwtValue;
+
+pub static DEFAULT_SENSITIVE_ROUTES: &str =
+    "/api/sessions,/api/2fa,/api/tokens,/api/oauth,/auth/me";
+// Tolerates the delay between signing a token and computing its expiry.
+static LIFETIME_LEEWAY_SECS: i64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValidationMode {
+    Database,
+    Stateless,
+}
+
+impl From<&str> for ValidationMode {
+    fn from(mode: &str) -> Self {
+        match mode.trim().to_lowercase().as_str() {
+            "stateless" => ValidationMode::Stateless,
+            _ => ValidationMode::Database,
+        }
+    }
+}
+
+/// How the authentication middleware checks `/api/` tokens. In stateless mode
+/// a short-lived token is trusted on its signature and claims alone, while
+/// long-lived tokens and requests to `sensitive_routes` still go through the
+/// database check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenValidation {
+    pub mode: ValidationMode,
+    pub sensitive_routes: Vec<String>,
+}
+
+impl TokenValidation {
+    pub fn from_env() -> Self {
+        Self::parse(
+            env::var("JWT_VALIDATION").ok().as_deref(),
+            env::var("JWT_SENSITIVE_ROUTES").ok().as_deref(),
+        )
+    }
+
+    pub fn parse(mode: Option<&str>, sensitive_routes: Option<&str>) -> Self {
+        Self {
+            mode: mode
+                .map(ValidationMode::from)
+                .unwrap_or(ValidationMode::Database),
+            sensitive_routes: sensitive_routes
+                .unwrap_or(DEFAULT_SENSITIVE_ROUTES)
+                .split(',')
+                .map(str::trim)
+                .filter(|route| !route.is_empty())
+                .map(String::from)
+                .collect(),
+        }
+    }
+
+    pub fn is_sensitive(&self, path: &str) -> bool {
+        self.sensitive_routes
+            .iter()
+            .any(|route| path.starts_with(route.as_str()))
+    }
+
+    pub fn requires_database(&self, path: &str, jwt: &JwtValue, now: NaiveDateTime) -> bool {
+        match self.mode {
+            ValidationMode::Database => true,
+            ValidationMode::Stateless => {
+                let max_expiry =
+                    now + Duration::seconds(access_token_secs() + LIFETIME_LEEWAY_SECS);
+                self.is_sensitive(path)
+                    || !validate_jwt_date(jwt.expires_at)
+                    || jwt.expires_at > max_expiry
+            }
+        }
+    }
+}
+
+pub fn token_validation() -> &'static TokenValidation {
+    static VALIDATION: OnceLock<TokenValidation> = OnceLock::new();
+    VALIDATION.get_or_init(TokenValidation::from_env)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Utc;
+
+    fn jwt(expires_at: NaiveDateTime) -> JwtValue {
+        JwtValue {
+            id: "bc45a88e-8bb9-4308-a206-6cc6eec9e6a1".to_string(),
+            email: "my@email.com".to_string(),
+            expires_at,
+            sid: None,
+            jti: None,
+            scope: None,
+        }
+    }
+
+    #[test]
+    fn defaults_to_database_mode() {
+        let validation = TokenValidation::parse(None, None);
+        let now = Utc::now().naive_utc();
+
+        assert_eq!(validation.mode, ValidationMode::Database);
+        assert_eq!(
+            validation.sensitive_routes,
+            vec![
+                "/api/sessions",
+                "/api/2fa",
+                "/api/tokens",
+                "/api/oauth",
+                "/auth/me"
+            ]
+        );
+        assert!(validation.requires_database("/api/index", &jwt(now), now));
+    }
+
+    #[test]
+    fn stateless_accepts_short_lived_tokens() {
+        let validation = TokenValidation::parse(Some("stateless"), None);
+        let now = Utc::now().naive_utc();
+        let short = jwt(now + Duration::seconds(60));
+
+        assert!(!validation.requires_database("/api/index", &short, now));
+        assert!(validation.requires_database("/api/sessions/abc", &short, now));
+    }
+
+    #[test]
+    fn stateless_checks_long_lived_tokens_in_database() {
+        let validation = TokenValidation::parse(Some("stateless"), Some("/api/share, /api/undo"));
+        let now = Utc::now().naive_utc();
+        let long = jwt(now + Duration::days(30));
+
+        assert!(validation.requires_database("/api/index", &long, now));
+        assert!(validation.is_sensitive("/api/undo"));
+        assert!(!validation.is_sensitive("/api/sessions"));
+    }
+}
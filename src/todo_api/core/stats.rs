@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate};
+use uuid::Uuid;
+
+use crate::todo_api_web::model::{
+    stats::{DailyStats, StatsResponse, UserStats},
+    todo::{State, TodoCard},
+};
+
+pub fn card_stats(cards: &[TodoCard], days: i64, today: NaiveDate) -> StatsResponse {
+    let mut by_owner: BTreeMap<Uuid, Vec<&TodoCard>> = BTreeMap::new();
+    for card in cards {
+        by_owner.entry(card.owner).or_default().push(card);
+    }
+
+    let users = by_owner
+        .into_iter()
+        .map(|(owner, cards)| user_stats(owner, &cards))
+        .collect();
+
+    StatsResponse {
+        users,
+        daily: daily_stats(cards, days, today),
+        avg_cycle_time_secs: avg_cycle_time(cards.iter()),
+    }
+}
+
+fn user_stats(owner: Uuid, cards: &[&TodoCard]) -> UserStats {
+    let count = |state: State| cards.iter().filter(|c| c.state == state).count();
+    let tasks_total = cards.iter().map(|c| c.tasks.len()).sum();
+    let tasks_done = cards
+        .iter()
+        .flat_map(|c| c.tasks.iter())
+        .filter(|t| t.is_done)
+        .count();
+
+    UserStats {
+        owner,
+        todo: count(State::Todo),
+        doing: count(State::Doing),
+        done: count(State::Done),
+        tasks_total,
+        tasks_done,
+        completion_ratio: if tasks_total == 0 {
+            0f64
+        } else {
+            tasks_done as f64 / tasks_total as f64
+        },
+        avg_cycle_time_secs: avg_cycle_time(cards.iter().copied()),
+    }
+}
+
+fn daily_stats(cards: &[TodoCard], days: i64, today: NaiveDate) -> Vec<DailyStats> {
+    (0..days)
+        .rev()
+        .map(|offset| {
+            let date = today - Duration::days(offset);
+            DailyStats {
+                date,
+                created: cards
+                    .iter()
+                    .filter(|c| c.created_at.map(|d| d.date()) == Some(date))
+                    .count(),
+                completed: cards
+                    .iter()
+                    .filter(|c| c.state == State::Done && c.done_at.map(|d| d.date()) == Some(date))
+                    .count(),
+            }
+        })
+        .collect()
+}
+
+fn avg_cycle_time<'a>(cards: impl Iterator<Item = &'a TodoCard>) -> Option<f64> {
+    let cycles = cards
+        .filter(|c| c.state == State::Done)
+        .filter_map(|c| Some((c.done_at? - c.doing_at?).num_seconds()))
+        .collect::<Vec<i64>>();
+
+    if cycles.is_empty() {
+        None
+    } else {
+        Some(cycles.iter().sum::<i64>() as f64 / cycles.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todo_api_web::model::todo::Task;
+    use chrono::NaiveDateTime;
+
+    fn date(s: &str) -> NaiveDateTime {
+        s.parse::<NaiveDateTime>().unwrap()
+    }
+
+    fn card(owner: Uuid, state: State, tasks: Vec<bool>) -> TodoCard {
+        TodoCard {
+            id: Some(Uuid::new_v4()),
+            title: "title".to_string(),
+            description: "description".to_string(),
+            owner,
+            tasks: tasks
+                .into_iter()
+                .map(|is_done| Task {
+                    is_done,
+                    title: "task".to_string(),
+                })
+                .collect(),
+            state,
+            created_at: Some(date("2023-01-09T08:00:00")),
+            doing_at: None,
+            done_at: None,
+            transfers: vec![],
+            collaborators: vec![],
+        }
+    }
+
+    #[test]
+    fn counts_states_and_tasks_per_owner() {
+        let owner = Uuid::new_v4();
+        let cards = vec![
+            card(owner, State::Todo, vec![false, false]),
+            card(owner, State::Doing, vec![true, false]),
+            card(Uuid::new_v4(), State::Done, vec![true]),
+        ];
+        let today = date("2023-01-10T00:00:00").date();
+        let stats = card_stats(&cards, 7, today);
+        let user = stats.users.iter().find(|u| u.owner == owner).unwrap();
+
+        assert_eq!(stats.users.len(), 2);
+        assert_eq!((user.todo, user.doing, user.done), (1, 1, 0));
+        assert_eq!((user.tasks_total, user.tasks_done), (4, 1));
+        assert_eq!(user.completion_ratio, 0.25);
+    }
+
+    #[test]
+    fn daily_window_counts_created_and_completed() {
+        let owner = Uuid::new_v4();
+        let mut done = card(owner, State::Doing, vec![]);
+        done.transition(State::Done, date("2023-01-10T09:00:00"));
+        let cards = vec![card(owner, State::Todo, vec![]), done];
+        let today = date("2023-01-10T00:00:00").date();
+        let stats = card_stats(&cards, 3, today);
+
+        assert_eq!(
+            stats.daily,
+            vec![
+                DailyStats {
+                    date: date("2023-01-08T00:00:00").date(),
+                    created: 0,
+                    completed: 0
+                },
+                DailyStats {
+                    date: date("2023-01-09T00:00:00").date(),
+                    created: 2,
+                    completed: 0
+                },
+                DailyStats {
+                    date: today,
+                    created: 0,
+                    completed: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cycle_time_goes_from_doing_to_done() {
+        let owner = Uuid::new_v4();
+        let mut fast = card(owner, State::Todo, vec![]);
+        fast.transition(State::Doing, date("2023-01-10T09:00:00"));
+        fast.transition(State::Done, date("2023-01-10T10:00:00"));
+        let mut slow = card(owner, State::Todo, vec![]);
+        slow.transition(State::Doing, date("2023-01-10T09:00:00"));
+        slow.transition(State::Done, date("2023-01-10T12:00:00"));
+        let skipped = card(owner, State::Done, vec![]);
+
+        let stats = card_stats(&[fast, slow, skipped], 1, date("2023-01-10T00:00:00").date());
+
+        assert_eq!(stats.avg_cycle_time_secs, Some(7200f64));
+        assert_eq!(stats.users[0].avg_cycle_time_secs, Some(7200f64));
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::OnceLock,
+};
+
+use regex::{Captures, Regex};
+use uuid::Uuid;
+
+use crate::todo_api_web::model::{
+    template::TodoTemplate,
+    todo::{State, Task, TodoCard},
+};
+
+static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+
+fn placeholder() -> &'static Regex {
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap())
+}
+
+pub fn template_variables(template: &TodoTemplate) -> BTreeSet<String> {
+    let placeholder = placeholder();
+    let texts = [&template.title, &template.description]
+        .into_iter()
+        .chain(template.tasks.iter().map(|t| &t.title));
+
+    texts
+        .flat_map(|text| {
+            placeholder
+                .captures_iter(text)
+                .map(|c| c[1].to_string())
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+/// Builds a new `Todo` card from the template, with every placeholder
+/// substituted and every task reset. Fails with the names of the
+/// variables that were not provided.
+pub fn instantiate(
+    template: &TodoTemplate,
+    owner: Uuid,
+    variables: &HashMap<String, String>,
+) -> Result<TodoCard, Vec<String>> {
+    let missing = template_variables(template)
+        .into_iter()
+        .filter(|v| !variables.contains_key(v))
+        .collect::<Vec<String>>();
+    if !missing.is_empty() {
+        return Err(missing);
+    }
+
+    let placeholder = placeholder();
+    let substitute = |text: &str| {
+        placeholder
+            .replace_all(text, |c: &Captures| variables[&c[1]].clone())
+            .to_string()
+    };
+
+    Ok(TodoCard {
+        id: None,
+        title: substitute(&template.title),
+        description: substitute(&template.description),
+        owner,
+        tasks: template
+            .tasks
+            .iter()
+            .map(|t| Task {
+                is_done: false,
+                title: substitute(&t.title),
+            })
+            .collect(),
+        state: State::Todo,
+        created_at: None,
+        doing_at: None,
+        done_at: None,
+        transfers: vec![],
+        collaborators: vec![],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn template() -> TodoTemplate {
+        TodoTemplate {
+            id: Some(Uuid::new_v4()),
+            name: "release".to_string(),
+            title: "Release {{version}}".to_string(),
+            description: "Ship {{ version }} to {{env}}".to_string(),
+            owner: None,
+            tasks: vec![Task {
+                is_done: true,
+                title: "Tag {{version}}".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn lists_template_variables() {
+        let variables = template_variables(&template());
+
+        assert_eq!(
+            variables.into_iter().collect::<Vec<String>>(),
+            vec!["env".to_string(), "version".to_string()]
+        );
+    }
+
+    #[test]
+    fn instantiate_substitutes_and_resets_tasks() {
+        let owner = Uuid::new_v4();
+        let variables = HashMap::from([
+            ("version".to_string(), "1.2.0".to_string()),
+            ("env".to_string(), "prod".to_string()),
+        ]);
+        let card = instantiate(&template(), owner, &variables).unwrap();
+
+        assert_eq!(card.title, "Release 1.2.0");
+        assert_eq!(card.description, "Ship 1.2.0 to prod");
+        assert_eq!(card.owner, owner);
+        assert_eq!(card.state, State::Todo);
+        assert_eq!(
+            card.tasks,
+            vec![Task {
+                is_done: false,
+                title: "Tag 1.2.0".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn instantiate_reports_missing_variables() {
+        let variables = HashMap::from([("version".to_string(), "1.2.0".to_string())]);
+
+        assert_eq!(
+            instantiate(&template(), Uuid::new_v4(), &variables),
+            Err(vec!["env".to_string()])
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+
+use crate::todo_api::core::JWT_ISSUER;
+
+pub static TOTP_STEP_SECS: i64 = 30;
+pub static TOTP_DIGITS: u32 = 6;
+pub static RECOVERY_CODE_COUNT: usize = 10;
+// Codes from the previous and next step are accepted to absorb clock drift.
+static TOTP_SKEW_STEPS: i64 = 1;
+static BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to generate random bytes");
+    bytes
+}
+
+/// RFC 4648 base32 without padding, as authenticator apps expect.
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+pub fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in encoded.trim_end_matches('=').chars() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// 160 bit secret, the size RFC 4226 recommends for HMAC-SHA1.
+pub fn generate_secret() -> String {
+    base32_encode(&random_bytes(20))
+}
+
+/// Encrypts a secret for storage with AES-256-GCM, base64 of the nonce
+/// followed by the ciphertext.
+pub fn seal_secret(key: &[u8; 32], secret: &str) -> String {
+    let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("AES-256 key length"));
+    let nonce = random_bytes(NONCE_LEN);
+    let mut sealed = secret.as_bytes().to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::try_assume_unique_for_key(&nonce).expect("AES-GCM nonce length"),
+        Aad::empty(),
+        &mut sealed,
+    )
+    .expect("Failed to seal TOTP secret");
+    base64::encode([nonce, sealed].concat())
+}
+
+pub fn open_secret(key: &[u8; 32], sealed: &str) -> Option<String> {
+    let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).ok()?);
+    let sealed = base64::decode(sealed).ok()?;
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let mut ciphertext = ciphertext.to_vec();
+    let secret = key
+        .open_in_place(
+            Nonce::try_assume_unique_for_key(nonce).ok()?,
+            Aad::empty(),
+            &mut ciphertext,
+        )
+        .ok()?;
+    String::from_utf8(secret.to_vec()).ok()
+}
+
+pub fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let tag = hmac::sign(&key, &counter.to_be_bytes());
+    let digest = tag.as_ref();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+pub fn totp_step(timestamp: i64) -> i64 {
+    timestamp / TOTP_STEP_SECS
+}
+
+/// Returns the time step the code matches, only steps after `last_used_step`
+/// count so a code cannot be replayed.
+pub fn verify_code(
+    secret: &str,
+    code: &str,
+    timestamp: i64,
+    last_used_step: Option<i64>,
+) -> Option<i64> {
+    let secret = base32_decode(secret)?;
+    let code = code.trim().parse::<u32>().ok()?;
+    let current = totp_step(timestamp);
+
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .filter(|step| *step >= 0 && Some(*step) > last_used_step)
+        .find(|step| hotp(&secret, *step as u64) == code)
+}
+
+pub fn otpauth_uri(secret: &str, email: &str) -> String {
+    let encode = |value: &str| {
+        value
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect::<String>()
+    };
+
+    format!(
+        "otpauth://totp/{issuer}:{label}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = encode(JWT_ISSUER),
+        label = encode(email),
+        secret = secret,
+        digits = TOTP_DIGITS,
+        period = TOTP_STEP_SECS
+    )
+}
+
+/// Single-use codes shown once at enrollment, formatted `xxxxx-xxxxx`.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = base32_encode(&random_bytes(7)).to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..10])
+        })
+        .collect()
+}
+
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // RFC 6238 appendix B, SHA1 secret "12345678901234567890"
+    static RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn base32_round_trips() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("MZXW6YTBOI").unwrap(), b"foobar");
+        assert_eq!(base32_decode("mzxw6ytboi======").unwrap(), b"foobar");
+        assert!(base32_decode("not base32!").is_none());
+    }
+
+    #[test]
+    fn matches_rfc_6238_vectors() {
+        assert_eq!(hotp(RFC_SECRET, totp_step(59) as u64), 287082);
+        assert_eq!(hotp(RFC_SECRET, totp_step(1111111109) as u64), 81804);
+        assert_eq!(hotp(RFC_SECRET, totp_step(1234567890) as u64), 5924);
+    }
+
+    #[test]
+    fn verifies_within_skew_once() {
+        let secret = base32_encode(RFC_SECRET);
+        let step = totp_step(1111111109);
+
+        assert_eq!(verify_code(&secret, "081804", 1111111109, None), Some(step));
+        assert_eq!(
+            verify_code(&secret, "081804", 1111111109 + 30, None),
+            Some(step)
+        );
+        assert_eq!(verify_code(&secret, "081804", 1111111109, Some(step)), None);
+        assert_eq!(verify_code(&secret, "000000", 1111111109, None), None);
+    }
+
+    #[test]
+    fn sealed_secrets_open_with_their_key_only() {
+        let secret = generate_secret();
+        let sealed = seal_secret(&[1u8; 32], &secret);
+
+        assert!(!sealed.contains(&secret));
+        assert_ne!(sealed, seal_secret(&[1u8; 32], &secret));
+        assert_eq!(open_secret(&[1u8; 32], &sealed), Some(secret));
+        assert_eq!(open_secret(&[2u8; 32], &sealed), None);
+        assert_eq!(open_secret(&[1u8; 32], "c2hvcnQ="), None);
+    }
+
+    #[test]
+    fn otpauth_uri_encodes_label() {
+        let uri = otpauth_uri("MZXW6YTBOI", "my@email.com");
+
+        assert_eq!(
+            uri,
+            "otpauth://totp/todo-server:my%40email.com?secret=MZXW6YTBOI&issuer=todo-server&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn recovery_codes_are_unique() {
+        let codes = generate_recovery_codes();
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(unique.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(codes[0].len(), 11);
+    }
+}
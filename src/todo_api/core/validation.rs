@@ -0,0 +1,520 @@
+use std::{collections::HashSet, env, fs, sync::OnceLock};
+
+use log::warn;
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+use serde::{Deserialize, Serialize};
+
+pub static DEFAULT_PASSWORD_MIN_LENGTH: usize = 32;
+pub static DEFAULT_PASSWORD_MAX_LENGTH: usize = 128;
+static MAX_EMAIL_LENGTH: usize = 254;
+static MAX_LOCAL_PART_LENGTH: usize = 64;
+static MAX_LABEL_LENGTH: usize = 63;
+static MIN_SIMILARITY_LENGTH: usize = 3;
+pub static DEFAULT_TIMEZONE: &str = "UTC";
+static MAX_DISPLAY_NAME_LENGTH: usize = 100;
+
+/// Why a field was rejected, `reason` is stable for clients to match on.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct FieldError {
+    pub field: String,
+    pub reason: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, reason: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            reason: reason.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+impl CharClass {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "lower" => Some(CharClass::Lowercase),
+            "upper" => Some(CharClass::Uppercase),
+            "digit" => Some(CharClass::Digit),
+            "symbol" => Some(CharClass::Symbol),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharClass::Lowercase => c.is_lowercase(),
+            CharClass::Uppercase => c.is_uppercase(),
+            CharClass::Digit => c.is_numeric(),
+            CharClass::Symbol => !c.is_alphanumeric(),
+        }
+    }
+
+    fn error(&self) -> FieldError {
+        let (reason, message) = match self {
+            CharClass::Lowercase => ("missing_lowercase", "Password needs a lowercase letter"),
+            CharClass::Uppercase => ("missing_uppercase", "Password needs an uppercase letter"),
+            CharClass::Digit => ("missing_digit", "Password needs a digit"),
+            CharClass::Symbol => ("missing_symbol", "Password needs a symbol or space"),
+        };
+        FieldError::new("password", reason, message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub required_classes: Vec<CharClass>,
+    pub breached: HashSet<String>,
+    pub reject_email_similarity: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: DEFAULT_PASSWORD_MIN_LENGTH,
+            max_length: DEFAULT_PASSWORD_MAX_LENGTH,
+            required_classes: Vec::new(),
+            breached: HashSet::new(),
+            reject_email_similarity: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Reads `PASSWORD_MIN_LENGTH`, `PASSWORD_MAX_LENGTH`,
+    /// `PASSWORD_REQUIRED_CLASSES` (comma separated `lower`, `upper`, `digit`
+    /// and `symbol`), `PASSWORD_BREACHED_LIST` (path of a file with one
+    /// password or SHA-1 hash per line) and `PASSWORD_REJECT_EMAIL_SIMILARITY`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let length = |name: &str, default: usize| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            min_length: length("PASSWORD_MIN_LENGTH", defaults.min_length),
+            max_length: length("PASSWORD_MAX_LENGTH", defaults.max_length),
+            required_classes: env::var("PASSWORD_REQUIRED_CLASSES")
+                .unwrap_or_default()
+                .split(',')
+                .filter(|name| !name.trim().is_empty())
+                .filter_map(|name| {
+                    let class = CharClass::parse(name);
+                    if class.is_none() {
+                        warn!("Unknown password character class {}", name);
+                    }
+                    class
+                })
+                .collect(),
+            breached: env::var("PASSWORD_BREACHED_LIST")
+                .map(|path| breached_list(&path))
+                .unwrap_or_default(),
+            reject_email_similarity: env::var("PASSWORD_REJECT_EMAIL_SIMILARITY")
+                .map(|value| value != "false")
+                .unwrap_or(defaults.reject_email_similarity),
+        }
+    }
+
+    /// Every rule the password breaks. The email similarity check is skipped
+    /// without an email.
+    pub fn check(&self, password: &str, email: Option<&str>) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            errors.push(FieldError::new(
+                "password",
+                "too_short",
+                &format!("Password needs at least {} characters", self.min_length),
+            ));
+        }
+        if length > self.max_length {
+            errors.push(FieldError::new(
+                "password",
+                "too_long",
+                &format!("Password can have at most {} characters", self.max_length),
+            ));
+        }
+        errors.extend(
+            self.required_classes
+                .iter()
+                .filter(|class| !password.chars().any(|c| class.matches(c)))
+                .map(CharClass::error),
+        );
+        if self.is_breached(password) {
+            errors.push(FieldError::new(
+                "password",
+                "breached",
+                "Password appears in a list of breached passwords",
+            ));
+        }
+        if self.reject_email_similarity && email.is_some_and(|email| is_similar(password, email)) {
+            errors.push(FieldError::new(
+                "password",
+                "similar_to_email",
+                "Password must not contain the email",
+            ));
+        }
+        errors
+    }
+
+    fn is_breached(&self, password: &str) -> bool {
+        !self.breached.is_empty()
+            && (self.breached.contains(&password.to_lowercase())
+                || self.breached.contains(&sha1_hex(password)))
+    }
+}
+
+/// Lines are plain passwords, compared case-insensitively, or uppercase SHA-1
+/// hashes with an optional `:count` as in the Have I Been Pwned dumps.
+fn breached_list(path: &str) -> HashSet<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => content
+            .lines()
+            .map(|line| {
+                let line = line.trim();
+                match line.split_once(':') {
+                    Some((hash, count)) if is_sha1_hex(hash) && count.parse::<u64>().is_ok() => {
+                        hash.to_uppercase()
+                    }
+                    _ if is_sha1_hex(line) => line.to_uppercase(),
+                    _ => line.to_lowercase(),
+                }
+            })
+            .filter(|line| !line.is_empty())
+            .collect(),
+        Err(e) => {
+            warn!("Failed to read breached password list {}: {}", path, e);
+            HashSet::new()
+        }
+    }
+}
+
+fn is_sha1_hex(value: &str) -> bool {
+    value.len() == 40 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn sha1_hex(password: &str) -> String {
+    digest(&SHA1_FOR_LEGACY_USE_ONLY, password.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect()
+}
+
+fn is_similar(password: &str, email: &str) -> bool {
+    let password = password.to_lowercase();
+    let email = email.to_lowercase();
+    let local = email
+        .rsplit_once('@')
+        .map_or(email.as_str(), |(local, _)| local);
+
+    password.contains(&email)
+        || (local.chars().count() >= MIN_SIMILARITY_LENGTH && password.contains(local))
+        || (password.chars().count() >= MIN_SIMILARITY_LENGTH && email.contains(&password))
+}
+
+pub fn password_policy() -> &'static PasswordPolicy {
+    static POLICY: OnceLock<PasswordPolicy> = OnceLock::new();
+    POLICY.get_or_init(PasswordPolicy::from_env)
+}
+
+/// Parses an RFC 5322 `addr-spec` with the RFC 6531 UTF-8 extension: a
+/// dot-atom or quoted local part and a domain of at least two hostname labels.
+/// Comments and domain literals are not accepted.
+pub fn is_email_valid(email: &str) -> bool {
+    let (local, domain) = match email.rsplit_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    email.len() <= MAX_EMAIL_LENGTH
+        && local.len() <= MAX_LOCAL_PART_LENGTH
+        && (is_dot_atom(local) || is_quoted_string(local))
+        && is_domain(domain)
+}
+
+fn is_atext(c: char) -> bool {
+    c.is_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c) || (!c.is_ascii() && !c.is_control())
+}
+
+fn is_dot_atom(local: &str) -> bool {
+    local
+        .split('.')
+        .all(|atom| !atom.is_empty() && atom.chars().all(is_atext))
+}
+
+fn is_quoted_string(local: &str) -> bool {
+    let inner = match local
+        .strip_prefix('"')
+        .and_then(|local| local.strip_suffix('"'))
+    {
+        Some(inner) => inner,
+        None => return false,
+    };
+
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) if escaped == ' ' || escaped == '\t' || is_printable(escaped) => (),
+                _ => return false,
+            },
+            '"' => return false,
+            c if c == ' ' || c == '\t' || is_printable(c) => (),
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn is_printable(c: char) -> bool {
+    c.is_ascii_graphic() || (!c.is_ascii() && !c.is_control())
+}
+
+fn is_domain(domain: &str) -> bool {
+    let labels = domain.split('.').collect::<Vec<&str>>();
+    let is_label = |label: &&str| {
+        !label.is_empty()
+            && label.chars().count() <= MAX_LABEL_LENGTH
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+    };
+
+    labels.len() >= 2
+        && labels.iter().all(is_label)
+        && labels
+            .last()
+            .is_some_and(|tld| !tld.chars().all(|c| c.is_ascii_digit()))
+}
+
+pub fn email_errors(email: &str) -> Vec<FieldError> {
+    match is_email_valid(email) {
+        true => Vec::new(),
+        false => vec![FieldError::new(
+            "email",
+            "invalid_format",
+            "Email is not a valid address",
+        )],
+    }
+}
+
+/// Email and password of a new account, checked against the configured policy.
+pub fn credential_errors(email: &str, password: &str) -> Vec<FieldError> {
+    let mut errors = email_errors(email);
+    errors.extend(password_policy().check(password, Some(email)));
+    errors
+}
+
+/// Display names are trimmed before being checked and stored.
+pub fn display_name_errors(display_name: &str) -> Vec<FieldError> {
+    if display_name.trim().chars().count() > MAX_DISPLAY_NAME_LENGTH {
+        vec![FieldError::new(
+            "display_name",
+            "too_long",
+            &format!(
+                "Display name can have at most {} characters",
+                MAX_DISPLAY_NAME_LENGTH
+            ),
+        )]
+    } else if display_name.chars().any(char::is_control) {
+        vec![FieldError::new(
+            "display_name",
+            "invalid_characters",
+            "Display name must not contain control characters",
+        )]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Timezones are IANA names such as `Europe/Paris`.
+pub fn timezone_errors(timezone: &str) -> Vec<FieldError> {
+    match timezone.parse::<chrono_tz::Tz>() {
+        Ok(_) => Vec::new(),
+        Err(_) => vec![FieldError::new(
+            "timezone",
+            "unknown_timezone",
+            "Timezone is not a known IANA timezone",
+        )],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn reasons(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().map(|error| error.reason).collect()
+    }
+
+    #[test]
+    fn valid_emails() {
+        for email in [
+            "my@email.com",
+            "a@b.co.uk.org",
+            "my@email.com.br.us",
+            "first.last+tag@sub.example.io",
+            "\"john doe\"@example.com",
+            "o'brien@example.ie",
+            "用户@例子.广告",
+        ] {
+            assert!(is_email_valid(email), "{}", email);
+        }
+    }
+
+    #[test]
+    fn invalid_emails() {
+        for email in [
+            "my_email.com",
+            "my@email",
+            "my@@email.com",
+            ".my@email.com",
+            "my..name@email.com",
+            "my@-email.com",
+            "my@email..com",
+            "my@email.123",
+            "\"unclosed@email.com",
+            "my name@email.com",
+            "my@[127.0.0.1]",
+        ] {
+            assert!(!is_email_valid(email), "{}", email);
+        }
+        assert!(!is_email_valid(&format!("{}@email.com", "a".repeat(65))));
+    }
+
+    #[test]
+    fn default_policy_only_needs_length() {
+        let policy = PasswordPolicy::default();
+
+        assert!(policy
+            .check("my cr4zy p@ssw0rd my cr4zy p@ssw0rd", Some("my@email.com"))
+            .is_empty());
+        assert_eq!(
+            reasons(policy.check("My cr4zy P@ssw0rd", None)),
+            vec!["too_short"]
+        );
+        assert_eq!(
+            reasons(policy.check(&"a".repeat(129), None)),
+            vec!["too_long"]
+        );
+    }
+
+    #[test]
+    fn required_classes_are_reported() {
+        let policy = PasswordPolicy {
+            required_classes: vec![
+                CharClass::Lowercase,
+                CharClass::Uppercase,
+                CharClass::Digit,
+                CharClass::Symbol,
+            ],
+            ..PasswordPolicy::default()
+        };
+
+        assert!(policy
+            .check("My cr4zy P@ssw0rd My cr4zy P@ssw0rd", None)
+            .is_empty());
+        assert_eq!(
+            reasons(policy.check("mycrazypasswordmycrazypasswordmycrazy", None)),
+            vec!["missing_uppercase", "missing_digit", "missing_symbol"]
+        );
+    }
+
+    #[test]
+    fn breached_passwords_are_rejected() {
+        let path = std::env::temp_dir().join(format!("breached-{}.txt", uuid::Uuid::new_v4()));
+        let hashed = "correct horse battery staple correct horse";
+        std::fs::write(
+            &path,
+            format!(
+                "Password1234Password1234Password1234\n{}:42\n",
+                sha1_hex(hashed)
+            ),
+        )
+        .unwrap();
+        let policy = PasswordPolicy {
+            breached: breached_list(path.to_str().unwrap()),
+            ..PasswordPolicy::default()
+        };
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            reasons(policy.check("password1234password1234password1234", None)),
+            vec!["breached"]
+        );
+        assert_eq!(reasons(policy.check(hashed, None)), vec!["breached"]);
+        assert!(policy
+            .check("My cr4zy P@ssw0rd My cr4zy P@ssw0rd", None)
+            .is_empty());
+    }
+
+    #[test]
+    fn password_similar_to_email_is_rejected() {
+        let policy = PasswordPolicy::default();
+        let errors = policy.check(
+            "john.doe@email.com is my password!!",
+            Some("john.doe@email.com"),
+        );
+
+        assert_eq!(reasons(errors), vec!["similar_to_email"]);
+        assert_eq!(
+            reasons(policy.check(
+                "my password is johndoe and nobody knows",
+                Some("JohnDoe@email.com")
+            )),
+            vec!["similar_to_email"]
+        );
+    }
+
+    #[test]
+    fn credential_errors_name_their_fields() {
+        let errors = credential_errors("my_email.com", "short");
+
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| error.field.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["email", "password"]
+        );
+    }
+
+    #[test]
+    fn display_names_are_bounded() {
+        assert!(display_name_errors("Ada Lovelace").is_empty());
+        assert!(display_name_errors(&format!("  {}  ", "a".repeat(100))).is_empty());
+        assert_eq!(
+            reasons(display_name_errors(&"a".repeat(101))),
+            vec!["too_long"]
+        );
+        assert_eq!(
+            reasons(display_name_errors("Ada\nLovelace")),
+            vec!["invalid_characters"]
+        );
+    }
+
+    #[test]
+    fn timezones_must_be_iana_names() {
+        assert!(timezone_errors(DEFAULT_TIMEZONE).is_empty());
+        assert!(timezone_errors("America/Sao_Paulo").is_empty());
+        assert_eq!(
+            reasons(timezone_errors("Mars/Olympus_Mons")),
+            vec!["unknown_timezone"]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use crate::todo_api::mailer::Email;
+
+pub static DEFAULT_VERIFICATION_SECS: i64 = 24 * 60 * 60;
+pub static DEFAULT_VERIFICATION_RESEND_SECS: i64 = 60;
+pub static MAX_VERIFICATIONS_PER_HOUR: usize = 5;
+pub static DEFAULT_VERIFICATION_URL: &str = "http://localhost:4000/verify-email";
+
+pub fn verification_secs() -> i64 {
+    std::env::var("VERIFICATION_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_VERIFICATION_SECS)
+}
+
+pub fn verification_resend_secs() -> i64 {
+    std::env::var("VERIFICATION_RESEND_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_VERIFICATION_RESEND_SECS)
+}
+
+pub fn verification_url() -> String {
+    std::env::var("VERIFICATION_URL").unwrap_or_else(|_| DEFAULT_VERIFICATION_URL.to_string())
+}
+
+pub fn verification_email(to: &str, token: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Verify your email".to_string(),
+        body: format!(
+            "Welcome! Follow {}?token={} to verify your email and activate your account.\n\n\
+             The link expires in {} hours.",
+            verification_url(),
+            token,
+            verification_secs() / 3600
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verification_email_links_token() {
+        let email = verification_email("my@email.com", "abc");
+
+        assert_eq!(email.to, "my@email.com");
+        assert!(email
+            .body
+            .contains("http://localhost:4000/verify-email?token=abc"));
+    }
+}
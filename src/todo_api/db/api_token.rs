@@ -0,0 +1,126 @@
+use diesel::{prelude::*, PgConnection};
+use uuid::Uuid;
+
+use crate::todo_api::model::{
+    api_token::{ApiToken, RevokeApiToken},
+    error::DbError,
+};
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_api_token(row: ApiToken, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::api_token::dsl::*;
+
+    match diesel::insert_into(api_token).values(&row).execute(conn) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_api_token(_row: ApiToken, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_api_tokens(owner: Uuid, conn: &mut PgConnection) -> Result<Vec<ApiToken>, DbError> {
+    use crate::schema::api_token::dsl::*;
+
+    api_token
+        .filter(user_id.eq(owner))
+        .order(created_at.desc())
+        .load::<ApiToken>(conn)
+        .map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_api_tokens(_owner: Uuid, _conn: &mut PgConnection) -> Result<Vec<ApiToken>, DbError> {
+    Ok(Vec::new())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn delete_api_token(msg: RevokeApiToken, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::api_token::dsl::*;
+
+    match diesel::delete(
+        api_token
+            .filter(id.eq(msg.id))
+            .filter(user_id.eq(msg.user_id)),
+    )
+    .execute(conn)
+    {
+        Ok(0) => Err(DbError::CannotFindApiToken),
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn delete_api_token(_msg: RevokeApiToken, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Finds a valid token with its owner's email and refreshes `last_used_at`.
+/// Tokens only authenticate while their owner's account is active and
+/// verified, like the access tokens of a login.
+#[cfg(not(feature = "db-test"))]
+pub fn touch_api_token(
+    hash: &str,
+    conn: &mut PgConnection,
+) -> Result<Option<(ApiToken, String)>, DbError> {
+    use crate::schema::{api_token, auth_user};
+
+    let now = chrono::Utc::now().naive_utc();
+    let found = api_token::table
+        .inner_join(auth_user::table.on(auth_user::id.eq(api_token::user_id)))
+        .filter(api_token::token_hash.eq(hash))
+        .filter(auth_user::is_active.eq(true))
+        .filter(auth_user::email_verified.eq(true))
+        .select((api_token::all_columns, auth_user::email))
+        .first::<(ApiToken, String)>(conn)
+        .optional()
+        .map_err(|_| DbError::TryAgain)?;
+
+    match found {
+        Some((token, email)) if token.is_valid(now) => {
+            if token.needs_touch(now) {
+                diesel::update(api_token::table.filter(api_token::id.eq(token.id)))
+                    .set(api_token::last_used_at.eq(Some(now)))
+                    .execute(conn)
+                    .map_err(|_| DbError::TryAgain)?;
+            }
+            Ok(Some((token, email)))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn touch_api_token(
+    _hash: &str,
+    _conn: &mut PgConnection,
+) -> Result<Option<(ApiToken, String)>, DbError> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::{api_token, auth_user};
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use diesel::prelude::*;
+
+    #[test]
+    fn tokens_of_inactive_accounts_do_not_authenticate() {
+        let query = api_token::table
+            .inner_join(auth_user::table.on(auth_user::id.eq(api_token::user_id)))
+            .filter(api_token::token_hash.eq("hash"))
+            .filter(auth_user::is_active.eq(true))
+            .filter(auth_user::email_verified.eq(true))
+            .select(api_token::id);
+        let sql = debug_query::<Pg, _>(&query).to_string();
+
+        assert!(sql.contains(
+            "AND (\"auth_user\".\"is_active\" = $2)) AND (\"auth_user\".\"email_verified\" = $3)"
+        ));
+    }
+}
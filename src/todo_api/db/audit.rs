@@ -0,0 +1,30 @@
+use diesel::{prelude::*, PgConnection};
+use log::info;
+
+use crate::todo_api::model::{audit::AuditEvent, error::DbError};
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_audit_event(event: AuditEvent, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::audit_event::dsl::*;
+
+    info!(
+        "Audit {} {} by {:?}",
+        event.action, event.subject, event.actor
+    );
+    match diesel::insert_into(audit_event)
+        .values(&event)
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_audit_event(event: AuditEvent, _conn: &mut PgConnection) -> Result<(), DbError> {
+    info!(
+        "Audit {} {} by {:?}",
+        event.action, event.subject, event.actor
+    );
+    Ok(())
+}
@@ -29,7 +29,7 @@ pub fn insert_new_user(_user: User, _: &mut PgConnection) -> Result<(), DbError>
         String::from("My cr4azy p@ssw0rd My cr4azy p@ssw0rd"),
     );
     let query = diesel::insert_into(auth_user).values(&user);
-    let sql = "INSERT INTO \"auth_user\" (\"email\", \"id\", \"password\", \"expires_at\", \"is_active\") VALUES ($1, $2, $3, $4, $5) \
+    let sql = "INSERT INTO \"auth_user\" (\"email\", \"id\", \"password\", \"expires_at\", \"is_active\", \"email_verified\", \"display_name\", \"timezone\") VALUES ($1, $2, $3, $4, $5, $6, DEFAULT, $7) \
             -- binds: [\"my@email.com\", ";
     assert!(debug_query::<Pg, _>(&query).to_string().contains(sql));
     assert!(debug_query::<Pg, _>(&query)
@@ -60,7 +60,7 @@ pub fn scan_user(user_email: String, _conn: &mut PgConnection) -> Result<User, D
     use diesel::debug_query;
     use diesel::pg::Pg;
     let query = auth_user.filter(email.eq(&user_email));
-    let expected = "SELECT \"auth_user\".\"email\", \"auth_user\".\"id\", \"auth_user\".\"password\", \"auth_user\".\"expires_at\", \"auth_user\".\"is_active\" FROM \"auth_user\" WHERE \"auth_user\".\"email\" = $1 -- binds: [\"my@email.com\"]".to_string();
+    let expected = "SELECT \"auth_user\".\"email\", \"auth_user\".\"id\", \"auth_user\".\"password\", \"auth_user\".\"expires_at\", \"auth_user\".\"is_active\", \"auth_user\".\"email_verified\", \"auth_user\".\"display_name\", \"auth_user\".\"timezone\" FROM \"auth_user\" WHERE \"auth_user\".\"email\" = $1 -- binds: [\"my@email.com\"]".to_string();
 
     assert_eq!(debug_query::<Pg, _>(&query).to_string(), expected);
     Ok(User::from(user_email, "this is a hash".to_string()))
@@ -107,6 +107,35 @@ pub fn update_user_jwt_date(
     Ok(())
 }
 
+#[cfg(not(feature = "db-test"))]
+pub fn update_password_hash(
+    user: uuid::Uuid,
+    old_hash: &str,
+    new_hash: &str,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::auth_user::dsl::*;
+
+    let target = auth_user.filter(id.eq(user)).filter(password.eq(old_hash));
+    match diesel::update(target)
+        .set(password.eq(new_hash))
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn update_password_hash(
+    _user: uuid::Uuid,
+    _old_hash: &str,
+    _new_hash: &str,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
 #[cfg(not(feature = "db-test"))]
 pub fn inactivate_user(msg: Inactivate, conn: &mut PgConnection) -> Result<(), DbError> {
     use crate::schema::auth_user::dsl::*;
@@ -138,8 +167,8 @@ mod test {
 
         let user = User::from(String::from("email@my.com"), String::from("pswd"));
         let query = diesel::insert_into(auth_user).values(&user);
-        let sql = String::from("INSERT INTO \"auth_user\" (\"email\", \"id\", \"password\", \"expires_at\", \"is_active\") VALUES ($1, $2, $3, $4, $5) \
-                -- binds: [\"email@my.com\", ") + &user.id.to_string() + ", \"pswd\", " + &format!("{:?}", user.expires_at) +", false]";
+        let sql = String::from("INSERT INTO \"auth_user\" (\"email\", \"id\", \"password\", \"expires_at\", \"is_active\", \"email_verified\", \"display_name\", \"timezone\") VALUES ($1, $2, $3, $4, $5, $6, DEFAULT, $7) \
+                -- binds: [\"email@my.com\", ") + &user.id.to_string() + ", \"pswd\", " + &format!("{:?}", user.expires_at) +", false, false, \"UTC\"]";
         assert_eq!(&sql, &debug_query::<Pg, _>(&query).to_string());
     }
 }
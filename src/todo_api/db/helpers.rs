@@ -21,10 +21,50 @@ use tokio_stream::StreamExt;
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("src/migrations");
 
 pub static TODO_CARD_TABLE: &str = "TODO_CARDS";
+pub static TODO_TEMPLATE_TABLE: &str = "TODO_TEMPLATES";
 pub static TODO_FILE: &str = "post_todo.json";
 pub static ERROR_SERIALIZE: &str = "Failed to serialize todo cards";
 pub static ERROR_CREATE: &str = "Failed to create todo card";
 pub static ERROR_READ: &str = "Failed to read todo card";
+pub static ERROR_UPDATE: &str = "Failed to update todo card";
+pub static ERROR_DELETE: &str = "Failed to delete todo card";
+pub static ERROR_NOT_FOUND: &str = "Todo card not found";
+pub static ERROR_USER_NOT_FOUND: &str = "Target user not found";
+pub static ERROR_SHARE_OWNER: &str = "Owner cannot be a collaborator";
+pub static ERROR_COLLABORATOR_NOT_FOUND: &str = "Collaborator not found";
+pub static ERROR_SESSION_READ: &str = "Failed to read sessions";
+pub static ERROR_SESSION_NOT_FOUND: &str = "Session not found";
+pub static ERROR_RESET_TOKEN: &str = "Password reset token is invalid or expired";
+pub static ERROR_EMAIL_NOT_VERIFIED: &str = "Email address is not verified";
+pub static ERROR_VERIFICATION_TOKEN: &str = "Email verification token is invalid or expired";
+pub static ERROR_VERIFICATION_THROTTLED: &str = "Verification email was sent recently";
+pub static ERROR_LOGIN_THROTTLED: &str = "Too many failed login attempts, try again later";
+pub static ERROR_UNLOCK_TOKEN: &str = "Unlock token is invalid";
+pub static ERROR_TWO_FACTOR_ENABLED: &str = "Two-factor is already enabled";
+pub static ERROR_TWO_FACTOR_NOT_ENROLLED: &str = "Two-factor enrollment not found";
+pub static ERROR_TWO_FACTOR_CODE: &str = "Invalid two-factor code";
+pub static ERROR_API_TOKEN_REQUEST: &str = "Invalid API token name, scopes or expiry";
+pub static ERROR_API_TOKEN_READ: &str = "Failed to read API tokens";
+pub static ERROR_API_TOKEN_NOT_FOUND: &str = "API token not found";
+pub static ERROR_API_TOKEN_MANAGEMENT: &str = "API tokens cannot manage API tokens";
+pub static ERROR_OAUTH_CLIENT_REQUEST: &str = "Invalid client name or redirect URIs";
+pub static ERROR_OAUTH_CLIENT_READ: &str = "Failed to read OAuth clients";
+pub static ERROR_OAUTH_CLIENT_NOT_FOUND: &str = "OAuth client not found";
+pub static ERROR_INVALID_CREDENTIALS: &str = "Invalid email or password";
+pub static ERROR_OIDC_DISABLED: &str = "Login with an OpenID provider is not configured";
+pub static ERROR_OIDC_STATE: &str = "Invalid or expired login state";
+pub static ERROR_OIDC_PROVIDER: &str = "OpenID provider could not complete the login";
+pub static ERROR_OIDC_EMAIL_NOT_VERIFIED: &str = "Email must be verified by the OpenID provider";
+pub static ERROR_PROFILE_READ: &str = "Failed to read profile";
+pub static ERROR_CURRENT_PASSWORD: &str = "Current password is incorrect";
+pub static ERROR_EMAIL_TAKEN: &str = "Email already belongs to another account";
+pub static ERROR_HISTORY: &str = "Failed to read todo card history";
+pub static ERROR_NOTHING_TO_UNDO: &str = "No recent changes to undo";
+pub static ERROR_TEMPLATE_CREATE: &str = "Failed to create template";
+pub static ERROR_TEMPLATE_READ: &str = "Failed to read templates";
+pub static ERROR_TEMPLATE_UPDATE: &str = "Failed to update template";
+pub static ERROR_TEMPLATE_DELETE: &str = "Failed to delete template";
+pub static ERROR_TEMPLATE_NOT_FOUND: &str = "Template not found";
 
 #[derive(Debug)]
 pub struct DbExecutor(pub Pool<ConnectionManager<PgConnection>>);
@@ -63,27 +103,61 @@ pub async fn get_client() -> Client {
     Client::from_conf(dynamodb_local_config)
 }
 
+/// Every item of `table` matching `filter`, following the scan pages instead of
+/// stopping at the first one.
+#[cfg(not(feature = "dynamo"))]
+pub async fn scan_all(
+    client: &Client,
+    table: &str,
+    filter: &str,
+    names: std::collections::HashMap<String, String>,
+    values: std::collections::HashMap<String, aws_sdk_dynamodb::model::AttributeValue>,
+) -> Option<Vec<std::collections::HashMap<String, aws_sdk_dynamodb::model::AttributeValue>>> {
+    let mut items = Vec::new();
+    let mut start_key = None;
+    loop {
+        let scan_output = client
+            .scan()
+            .table_name(table.to_string())
+            .filter_expression(filter)
+            .set_expression_attribute_names(Some(names.clone()))
+            .set_expression_attribute_values(Some(values.clone()))
+            .set_exclusive_start_key(start_key)
+            .send()
+            .await;
+
+        match scan_output {
+            Ok(output) => {
+                start_key = output.last_evaluated_key().cloned();
+                items.extend(output.items().unwrap_or_default().iter().cloned());
+            }
+            Err(e) => {
+                error!("Could not scan {} due to error {:?}", table, e);
+                return None;
+            }
+        }
+        if start_key.is_none() {
+            debug!("Scanned {} items of {}", items.len(), table);
+            return Some(items);
+        }
+    }
+}
+
 pub async fn create_table(client: &Clients) {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let mut pg_conn = PgConnection::establish(&database_url)
         .expect(&format!("Error connecting to {}", database_url));
 
     run_migrations(&mut pg_conn);
-    match client.dynamo.list_tables().send().await {
-        Ok(list) => {
-            match list.table_names {
-                Some(table_vec) => {
-                    if table_vec.len() > 0 {
-                        println!("Error: {:?}", "Table already exists");
-                    } else {
-                        create_table_input(&client.dynamo).await
-                    }
-                }
-                None => create_table_input(&client.dynamo).await,
-            };
-        }
-        Err(_) => {
-            create_table_input(&client.dynamo).await;
+    let existing = match client.dynamo.list_tables().send().await {
+        Ok(list) => list.table_names.unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    for table_name in [TODO_CARD_TABLE, TODO_TEMPLATE_TABLE] {
+        if existing.iter().any(|t| t == table_name) {
+            debug!("Table {} already exists", table_name);
+        } else {
+            create_table_input(&client.dynamo, table_name).await
         }
     }
 }
@@ -116,8 +190,7 @@ fn build_attribute_definition() -> AttributeDefinition {
         .build()
 }
 
-async fn create_table_input(client: &Client) {
-    let table_name = TODO_CARD_TABLE.to_string();
+async fn create_table_input(client: &Client, table_name: &str) {
     let ad = build_attribute_definition();
     let ks = build_key_schema();
     let pt = build_provisioned_throughput();
@@ -0,0 +1,186 @@
+use diesel::{prelude::*, PgConnection};
+use uuid::Uuid;
+
+use crate::todo_api::model::{
+    error::DbError,
+    history::{CardHistoryEntry, HistoryAction, ReadLaterHistory, ReadUndoable},
+};
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_history(entry: CardHistoryEntry, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::card_history::dsl::*;
+
+    match diesel::insert_into(card_history)
+        .values(&entry)
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::HistoryNotRecorded),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_history(_entry: CardHistoryEntry, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_history(
+    history_card_id: Uuid,
+    conn: &mut PgConnection,
+) -> Result<Vec<CardHistoryEntry>, DbError> {
+    use crate::schema::card_history::dsl::*;
+
+    card_history
+        .filter(card_id.eq(history_card_id))
+        .order(changed_at.asc())
+        .load::<CardHistoryEntry>(conn)
+        .map_err(|_| DbError::CannotReadHistory)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_history(
+    history_card_id: Uuid,
+    _conn: &mut PgConnection,
+) -> Result<Vec<CardHistoryEntry>, DbError> {
+    Ok(vec![CardHistoryEntry::new(
+        history_card_id,
+        Uuid::new_v4(),
+        HistoryAction::Create,
+        None,
+        None,
+    )])
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_undoable(
+    msg: ReadUndoable,
+    conn: &mut PgConnection,
+) -> Result<Vec<CardHistoryEntry>, DbError> {
+    use crate::schema::card_history::dsl::*;
+
+    let mut query = card_history
+        .filter(actor.eq(msg.actor))
+        .filter(undone.eq(false))
+        .filter(action.ne(HistoryAction::Undo.to_string()))
+        .filter(changed_at.ge(msg.since))
+        .order(changed_at.desc())
+        .limit(msg.count)
+        .into_boxed();
+    if let Some(token) = msg.token {
+        query = query.filter(id.eq(token));
+    }
+
+    query
+        .load::<CardHistoryEntry>(conn)
+        .map_err(|_| DbError::CannotReadHistory)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_undoable(
+    _msg: ReadUndoable,
+    _conn: &mut PgConnection,
+) -> Result<Vec<CardHistoryEntry>, DbError> {
+    Ok(Vec::new())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_later_history(
+    msg: ReadLaterHistory,
+    conn: &mut PgConnection,
+) -> Result<Vec<CardHistoryEntry>, DbError> {
+    use crate::schema::card_history::dsl::*;
+
+    card_history
+        .filter(card_id.eq(msg.card_id))
+        .filter(undone.eq(false))
+        .filter(action.ne(HistoryAction::Undo.to_string()))
+        .filter(changed_at.gt(msg.after))
+        .order(changed_at.asc())
+        .load::<CardHistoryEntry>(conn)
+        .map_err(|_| DbError::CannotReadHistory)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_later_history(
+    _msg: ReadLaterHistory,
+    _conn: &mut PgConnection,
+) -> Result<Vec<CardHistoryEntry>, DbError> {
+    Ok(Vec::new())
+}
+
+/// Marks `entry_id` undone and records the undo itself in one transaction,
+/// an entry that is already undone is left alone.
+#[cfg(not(feature = "db-test"))]
+pub fn mark_undone(
+    entry_id: Uuid,
+    undo_entry: CardHistoryEntry,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::card_history::dsl::*;
+
+    let marked = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let marked = diesel::update(
+            card_history
+                .filter(id.eq(entry_id))
+                .filter(undone.eq(false)),
+        )
+        .set(undone.eq(true))
+        .execute(conn)?;
+        if marked == 1 {
+            diesel::insert_into(card_history)
+                .values(&undo_entry)
+                .execute(conn)?;
+        }
+        Ok(marked)
+    });
+
+    match marked {
+        Ok(1) => Ok(()),
+        _ => Err(DbError::HistoryNotRecorded),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn mark_undone(
+    _entry_id: Uuid,
+    _undo_entry: CardHistoryEntry,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::card_history::dsl::*;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use diesel::prelude::*;
+
+    #[test]
+    fn scan_history_orders_by_date() {
+        let history_card_id = uuid::Uuid::new_v4();
+        let query = card_history
+            .filter(card_id.eq(history_card_id))
+            .order(changed_at.asc());
+        let sql = String::from("SELECT \"card_history\".\"id\", \"card_history\".\"card_id\", \"card_history\".\"actor\", \"card_history\".\"action\", \"card_history\".\"changed_at\", \"card_history\".\"diff\", \"card_history\".\"snapshot\", \"card_history\".\"undone\" \
+                FROM \"card_history\" WHERE (\"card_history\".\"card_id\" = $1) ORDER BY \"card_history\".\"changed_at\" ASC  -- binds: [") + &history_card_id.to_string() + "]";
+        assert_eq!(&sql, &debug_query::<Pg, _>(&query).to_string());
+    }
+
+    #[test]
+    fn mark_undone_skips_undone_entries() {
+        let entry_id = uuid::Uuid::new_v4();
+        let query = diesel::update(
+            card_history
+                .filter(id.eq(entry_id))
+                .filter(undone.eq(false)),
+        )
+        .set(undone.eq(true));
+        let sql = format!(
+            "UPDATE \"card_history\" SET \"undone\" = $1 WHERE ((\"card_history\".\"id\" = $2) AND (\"card_history\".\"undone\" = $3)) -- binds: [true, {}, false]",
+            entry_id
+        );
+        assert_eq!(sql, debug_query::<Pg, _>(&query).to_string());
+    }
+}
@@ -0,0 +1,256 @@
+use chrono::NaiveDateTime;
+use diesel::{prelude::*, PgConnection};
+
+use crate::todo_api::core::lockout::LockoutPolicy;
+use crate::todo_api::model::{
+    error::DbError,
+    lockout::{LoginAttempt, LoginThrottle},
+};
+
+/// Counts an attempt against every key under row locks, unless one of them
+/// still has to wait. Concurrent attempts queue on the locks, so each one sees
+/// the failures counted before it.
+#[cfg(not(feature = "db-test"))]
+pub fn count_attempt(
+    keys: &[(String, LockoutPolicy)],
+    now: NaiveDateTime,
+    conn: &mut PgConnection,
+) -> Result<LoginAttempt, DbError> {
+    use crate::schema::login_throttle::dsl::*;
+
+    let mut keys = keys.to_vec();
+    keys.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let attempt = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(login_throttle)
+            .values(
+                &keys
+                    .iter()
+                    .map(|(throttle_key, _)| LoginThrottle::new(throttle_key.clone(), now))
+                    .collect::<Vec<LoginThrottle>>(),
+            )
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        let mut throttles = login_throttle
+            .filter(key.eq_any(keys.iter().map(|(throttle_key, _)| throttle_key)))
+            .order(key)
+            .for_update()
+            .load::<LoginThrottle>(conn)?;
+        let policy_of = |throttle: &LoginThrottle| {
+            keys.iter()
+                .find(|(throttle_key, _)| *throttle_key == throttle.key)
+                .map(|(_, policy)| *policy)
+        };
+
+        let retry_at = throttles
+            .iter()
+            .filter_map(|throttle| policy_of(throttle)?.retry_at(throttle, now))
+            .max();
+        if let Some(retry_at) = retry_at {
+            return Ok(LoginAttempt::Throttled(retry_at));
+        }
+
+        let mut locked = Vec::new();
+        for throttle in throttles.iter_mut() {
+            let policy = match policy_of(throttle) {
+                None => continue,
+                Some(policy) => policy,
+            };
+            if policy.record_failure(throttle, now) {
+                locked.push(throttle.key.clone());
+            }
+            diesel::update(login_throttle.filter(key.eq(&throttle.key)))
+                .set((
+                    failures.eq(throttle.failures),
+                    last_failure_at.eq(throttle.last_failure_at),
+                    locked_until.eq(throttle.locked_until),
+                ))
+                .execute(conn)?;
+        }
+        Ok(LoginAttempt::Counted(locked))
+    });
+
+    attempt.map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn count_attempt(
+    _keys: &[(String, LockoutPolicy)],
+    _now: NaiveDateTime,
+    _conn: &mut PgConnection,
+) -> Result<LoginAttempt, DbError> {
+    Ok(LoginAttempt::Counted(Vec::new()))
+}
+
+/// Takes back an attempt `count_attempt` made for the key.
+#[cfg(not(feature = "db-test"))]
+pub fn release_attempt(
+    throttle_key: &str,
+    policy: &LockoutPolicy,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::login_throttle::dsl::*;
+
+    let released = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let throttle = login_throttle
+            .filter(key.eq(throttle_key))
+            .for_update()
+            .first::<LoginThrottle>(conn)
+            .optional()?;
+        if let Some(mut throttle) = throttle {
+            policy.release_attempt(&mut throttle);
+            diesel::update(login_throttle.filter(key.eq(throttle_key)))
+                .set((
+                    failures.eq(throttle.failures),
+                    locked_until.eq(throttle.locked_until),
+                ))
+                .execute(conn)?;
+        }
+        Ok(())
+    });
+
+    released.map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn release_attempt(
+    _throttle_key: &str,
+    _policy: &LockoutPolicy,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn set_unlock_token(
+    throttle_key: &str,
+    hash: &str,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::login_throttle::dsl::*;
+
+    match diesel::update(login_throttle.filter(key.eq(throttle_key)))
+        .set(unlock_token_hash.eq(Some(hash)))
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn set_unlock_token(
+    _throttle_key: &str,
+    _hash: &str,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn clear_throttle(throttle_key: &str, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::login_throttle::dsl::*;
+
+    match diesel::delete(login_throttle.filter(key.eq(throttle_key))).execute(conn) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn clear_throttle(_throttle_key: &str, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Deletes throttles whose last failure is older than `stale_before` unless
+/// they are still locked.
+#[cfg(not(feature = "db-test"))]
+pub fn purge_throttles(
+    stale_before: NaiveDateTime,
+    now: NaiveDateTime,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::login_throttle::dsl::*;
+
+    match diesel::delete(
+        login_throttle
+            .filter(last_failure_at.lt(stale_before))
+            .filter(locked_until.is_null().or(locked_until.le(now))),
+    )
+    .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn purge_throttles(
+    _stale_before: NaiveDateTime,
+    _now: NaiveDateTime,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Deletes the throttle holding the unlock token and returns its key.
+#[cfg(not(feature = "db-test"))]
+pub fn unlock_by_token(hash: &str, conn: &mut PgConnection) -> Result<String, DbError> {
+    use crate::schema::login_throttle::dsl::*;
+
+    match diesel::delete(login_throttle.filter(unlock_token_hash.eq(hash)))
+        .returning(key)
+        .get_result::<String>(conn)
+        .optional()
+    {
+        Ok(Some(unlocked)) => Ok(unlocked),
+        Ok(None) => Err(DbError::InvalidUnlockToken),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn unlock_by_token(_hash: &str, _conn: &mut PgConnection) -> Result<String, DbError> {
+    Ok(String::from("account:my@email.com"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::login_throttle::dsl::*;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use diesel::prelude::*;
+
+    #[test]
+    fn unlock_deletes_throttle_by_token() {
+        let query =
+            diesel::delete(login_throttle.filter(unlock_token_hash.eq("hash"))).returning(key);
+        let sql = "DELETE  FROM \"login_throttle\" WHERE (\"login_throttle\".\"unlock_token_hash\" = $1) RETURNING \"login_throttle\".\"key\" -- binds: [\"hash\"]";
+        assert_eq!(sql, debug_query::<Pg, _>(&query).to_string());
+    }
+
+    #[test]
+    fn attempts_lock_their_throttles_in_key_order() {
+        let keys = vec!["account:my@email.com", "ip:10.0.0.1"];
+        let query = login_throttle
+            .filter(key.eq_any(keys))
+            .order(key)
+            .for_update();
+        let sql = "SELECT \"login_throttle\".\"key\", \"login_throttle\".\"failures\", \"login_throttle\".\"last_failure_at\", \"login_throttle\".\"locked_until\", \"login_throttle\".\"unlock_token_hash\" FROM \"login_throttle\" WHERE (\"login_throttle\".\"key\" = ANY($1)) ORDER BY \"login_throttle\".\"key\" FOR UPDATE -- binds: [[\"account:my@email.com\", \"ip:10.0.0.1\"]]";
+        assert_eq!(sql, debug_query::<Pg, _>(&query).to_string());
+    }
+
+    #[test]
+    fn purge_keeps_running_lockouts() {
+        let now = chrono::Utc::now().naive_utc();
+        let query = diesel::delete(
+            login_throttle
+                .filter(last_failure_at.lt(now))
+                .filter(locked_until.is_null().or(locked_until.le(now))),
+        );
+        let sql = format!(
+            "DELETE  FROM \"login_throttle\" WHERE ((\"login_throttle\".\"last_failure_at\" < $1) AND ((\"login_throttle\".\"locked_until\" IS NULL) OR (\"login_throttle\".\"locked_until\" <= $2))) -- binds: [{:?}, {:?}]",
+            now, now
+        );
+        assert_eq!(sql, debug_query::<Pg, _>(&query).to_string());
+    }
+}
@@ -1,3 +1,17 @@
+pub mod api_token;
+pub mod audit;
 pub mod auth;
 pub mod helpers;
+pub mod history;
+pub mod lockout;
+pub mod oauth;
+pub mod oidc;
+pub mod password;
+pub mod profile;
+pub mod refresh;
+pub mod revocation;
+pub mod session;
+pub mod template;
 pub mod todo;
+pub mod two_factor;
+pub mod verification;
@@ -0,0 +1,146 @@
+use diesel::{prelude::*, PgConnection};
+use uuid::Uuid;
+
+use crate::todo_api::model::{
+    auth::User,
+    error::DbError,
+    oauth::{DeleteClient, OAuthClient, OAuthCode},
+};
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_client(client: OAuthClient, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::oauth_client::dsl::*;
+
+    match diesel::insert_into(oauth_client)
+        .values(&client)
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_client(_client: OAuthClient, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_clients(owner: Uuid, conn: &mut PgConnection) -> Result<Vec<OAuthClient>, DbError> {
+    use crate::schema::oauth_client::dsl::*;
+
+    oauth_client
+        .filter(owner_id.eq(owner))
+        .order(created_at.desc())
+        .load::<OAuthClient>(conn)
+        .map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_clients(_owner: Uuid, _conn: &mut PgConnection) -> Result<Vec<OAuthClient>, DbError> {
+    Ok(Vec::new())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_client(client: &str, conn: &mut PgConnection) -> Result<Option<OAuthClient>, DbError> {
+    use crate::schema::oauth_client::dsl::*;
+
+    oauth_client
+        .filter(id.eq(client))
+        .first::<OAuthClient>(conn)
+        .optional()
+        .map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_client(
+    _client: &str,
+    _conn: &mut PgConnection,
+) -> Result<Option<OAuthClient>, DbError> {
+    Ok(None)
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn delete_client(msg: DeleteClient, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::{oauth_client, oauth_code, refresh_token, session};
+
+    let deleted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let clients = diesel::delete(
+            oauth_client::table
+                .filter(oauth_client::id.eq(&msg.id))
+                .filter(oauth_client::owner_id.eq(msg.owner_id)),
+        )
+        .execute(conn)?;
+        if clients == 0 {
+            return Ok(0);
+        }
+
+        diesel::delete(oauth_code::table.filter(oauth_code::client_id.eq(&msg.id)))
+            .execute(conn)?;
+        let sessions = diesel::update(session::table.filter(session::client_id.eq(&msg.id)))
+            .set(session::revoked.eq(true))
+            .returning(session::id)
+            .get_results::<Uuid>(conn)?;
+        diesel::update(refresh_token::table.filter(refresh_token::session_id.eq_any(sessions)))
+            .set(refresh_token::revoked.eq(true))
+            .execute(conn)?;
+        Ok(clients)
+    });
+
+    match deleted {
+        Ok(0) => Err(DbError::CannotFindClient),
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn delete_client(_msg: DeleteClient, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_code(row: OAuthCode, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::oauth_code::dsl::*;
+
+    match diesel::insert_into(oauth_code).values(&row).execute(conn) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_code(_row: OAuthCode, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Deletes the code so it can only be presented once, and loads its user.
+#[cfg(not(feature = "db-test"))]
+pub fn take_code(hash: &str, conn: &mut PgConnection) -> Result<(OAuthCode, User), DbError> {
+    use crate::schema::{auth_user, oauth_code};
+
+    let taken = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let code = match diesel::delete(oauth_code::table.filter(oauth_code::code_hash.eq(hash)))
+            .get_result::<OAuthCode>(conn)
+            .optional()?
+        {
+            None => return Ok(None),
+            Some(code) => code,
+        };
+        let user = auth_user::table
+            .filter(auth_user::id.eq(code.user_id))
+            .first::<User>(conn)?;
+        Ok(Some((code, user)))
+    });
+
+    match taken {
+        Ok(Some(taken)) => Ok(taken),
+        Ok(None) => Err(DbError::InvalidGrant),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn take_code(_hash: &str, _conn: &mut PgConnection) -> Result<(OAuthCode, User), DbError> {
+    Err(DbError::InvalidGrant)
+}
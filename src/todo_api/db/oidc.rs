@@ -0,0 +1,133 @@
+use chrono::Utc;
+use diesel::{prelude::*, PgConnection};
+
+use crate::todo_api::{
+    core::hashing::{generate_token, hash_password},
+    model::{
+        auth::User,
+        error::DbError,
+        oidc::{link_target, ExternalIdentity, LinkExternalIdentity, LinkTarget, OidcLogin},
+    },
+};
+
+/// Stores a pending login and drops the expired ones.
+#[cfg(not(feature = "db-test"))]
+pub fn insert_oidc_login(login: OidcLogin, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::oidc_login::dsl::*;
+
+    diesel::delete(oidc_login.filter(expires_at.lt(Utc::now().naive_utc())))
+        .execute(conn)
+        .map_err(|_| DbError::TryAgain)?;
+    diesel::insert_into(oidc_login)
+        .values(&login)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_oidc_login(_login: OidcLogin, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn take_oidc_login(hash: &str, conn: &mut PgConnection) -> Result<OidcLogin, DbError> {
+    use crate::schema::oidc_login::dsl::*;
+
+    match diesel::delete(oidc_login.filter(state_hash.eq(hash)))
+        .get_result::<OidcLogin>(conn)
+        .optional()
+    {
+        Ok(Some(login)) => Ok(login),
+        Ok(None) => Err(DbError::InvalidOidcState),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn take_oidc_login(_hash: &str, _conn: &mut PgConnection) -> Result<OidcLogin, DbError> {
+    Err(DbError::InvalidOidcState)
+}
+
+/// Returns the user of the identity and whether the identity was linked now.
+#[cfg(not(feature = "db-test"))]
+pub fn link_external_identity(
+    msg: &LinkExternalIdentity,
+    conn: &mut PgConnection,
+) -> Result<(User, bool), DbError> {
+    use crate::schema::{api_token, auth_user, external_identity, refresh_token, session};
+
+    let linked = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let known = external_identity::table
+            .find((&msg.issuer, &msg.subject))
+            .for_update()
+            .first::<ExternalIdentity>(conn)
+            .optional()?;
+        if let Some(identity) = known {
+            diesel::update(external_identity::table.find((&msg.issuer, &msg.subject)))
+                .set((
+                    external_identity::email.eq(&msg.email),
+                    external_identity::last_login_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+            let user = auth_user::table
+                .filter(auth_user::id.eq(identity.user_id))
+                .first::<User>(conn)?;
+            return Ok(Ok((user, false)));
+        }
+        if !msg.email_verified {
+            return Ok(Err(DbError::ExternalEmailNotVerified));
+        }
+
+        let existing = auth_user::table
+            .filter(auth_user::email.eq(&msg.email))
+            .for_update()
+            .first::<User>(conn)
+            .optional()?;
+        let user = match link_target(existing, &msg.email) {
+            LinkTarget::Existing(user) => user,
+            LinkTarget::Reclaimed(user) => {
+                let user = diesel::update(auth_user::table.filter(auth_user::id.eq(user.id)))
+                    .set((
+                        auth_user::password.eq(hash_password(&generate_token())),
+                        auth_user::email_verified.eq(true),
+                    ))
+                    .get_result::<User>(conn)?;
+                diesel::update(session::table.filter(session::user_id.eq(user.id)))
+                    .set(session::revoked.eq(true))
+                    .execute(conn)?;
+                diesel::update(refresh_token::table.filter(refresh_token::user_id.eq(user.id)))
+                    .set(refresh_token::revoked.eq(true))
+                    .execute(conn)?;
+                diesel::delete(api_token::table.filter(api_token::user_id.eq(user.id)))
+                    .execute(conn)?;
+                user
+            }
+            LinkTarget::Created(user) => {
+                diesel::insert_into(auth_user::table)
+                    .values(&user)
+                    .execute(conn)?;
+                user
+            }
+        };
+        diesel::insert_into(external_identity::table)
+            .values(&ExternalIdentity::link(msg, user.id))
+            .execute(conn)?;
+        Ok(Ok((user, true)))
+    });
+
+    linked.map_err(|_| DbError::TryAgain)?
+}
+
+#[cfg(feature = "db-test")]
+pub fn link_external_identity(
+    msg: &LinkExternalIdentity,
+    _conn: &mut PgConnection,
+) -> Result<(User, bool), DbError> {
+    use crate::todo_api::model::oidc::external_user;
+
+    match msg.email_verified {
+        true => Ok((external_user(&msg.email), true)),
+        false => Err(DbError::ExternalEmailNotVerified),
+    }
+}
@@ -0,0 +1,119 @@
+use diesel::{prelude::*, PgConnection};
+
+use crate::todo_api::model::{error::DbError, password::PasswordReset};
+
+/// Stores a new reset token, invalidating the user's pending ones.
+#[cfg(not(feature = "db-test"))]
+pub fn replace_password_reset(row: PasswordReset, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::password_reset::dsl::*;
+
+    let replaced = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::update(
+            password_reset
+                .filter(user_id.eq(row.user_id))
+                .filter(used_at.is_null()),
+        )
+        .set(used_at.eq(Some(row.created_at)))
+        .execute(conn)?;
+        diesel::insert_into(password_reset)
+            .values(&row)
+            .execute(conn)
+    });
+
+    match replaced {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn replace_password_reset(
+    _row: PasswordReset,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Marks the reset token as used and replaces the password, revoking every
+/// session, refresh token and API token of the user in the same transaction.
+#[cfg(not(feature = "db-test"))]
+pub fn reset_password(
+    hash: &str,
+    new_password: String,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::password_reset::dsl::*;
+    use crate::schema::{api_token, auth_user, refresh_token, session};
+
+    let now = chrono::Utc::now().naive_utc();
+    let reset = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let stored = match password_reset
+            .filter(token_hash.eq(hash))
+            .for_update()
+            .first::<PasswordReset>(conn)
+            .optional()?
+        {
+            None => return Ok(Err(DbError::InvalidResetToken)),
+            Some(stored) => stored,
+        };
+        if let Err(e) = stored.check(now) {
+            return Ok(Err(e));
+        }
+
+        diesel::update(password_reset.filter(id.eq(stored.id)))
+            .set(used_at.eq(Some(now)))
+            .execute(conn)?;
+        diesel::update(auth_user::table.filter(auth_user::id.eq(stored.user_id)))
+            .set(auth_user::password.eq(new_password))
+            .execute(conn)?;
+        diesel::update(session::table.filter(session::user_id.eq(stored.user_id)))
+            .set(session::revoked.eq(true))
+            .execute(conn)?;
+        diesel::update(refresh_token::table.filter(refresh_token::user_id.eq(stored.user_id)))
+            .set(refresh_token::revoked.eq(true))
+            .execute(conn)?;
+        diesel::delete(api_token::table.filter(api_token::user_id.eq(stored.user_id)))
+            .execute(conn)?;
+
+        Ok(Ok(()))
+    });
+
+    match reset {
+        Ok(reset) => reset,
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn reset_password(
+    _hash: &str,
+    _new_password: String,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::password_reset::dsl::*;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use diesel::prelude::*;
+
+    #[test]
+    fn new_reset_invalidates_pending_ones() {
+        let owner = uuid::Uuid::new_v4();
+        let now = chrono::Utc::now().naive_utc();
+        let query = diesel::update(
+            password_reset
+                .filter(user_id.eq(owner))
+                .filter(used_at.is_null()),
+        )
+        .set(used_at.eq(Some(now)));
+        let sql = format!(
+            "UPDATE \"password_reset\" SET \"used_at\" = $1 WHERE ((\"password_reset\".\"user_id\" = $2) AND (\"password_reset\".\"used_at\" IS NULL)) -- binds: [Some({:?}), {}]",
+            now, owner
+        );
+        assert_eq!(sql, debug_query::<Pg, _>(&query).to_string());
+    }
+}
@@ -0,0 +1,107 @@
+use diesel::{prelude::*, PgConnection};
+use uuid::Uuid;
+
+use crate::todo_api::model::{auth::User, error::DbError, profile::UpdateProfile};
+
+#[cfg(not(feature = "db-test"))]
+pub fn update_profile(msg: UpdateProfile, conn: &mut PgConnection) -> Result<User, DbError> {
+    use crate::schema::auth_user::dsl::*;
+
+    diesel::update(auth_user.filter(id.eq(msg.user_id)))
+        .set((display_name.eq(msg.display_name), timezone.eq(msg.timezone)))
+        .get_result::<User>(conn)
+        .optional()
+        .map_err(|_| DbError::TryAgain)?
+        .ok_or(DbError::CannotFindUser)
+}
+
+#[cfg(feature = "db-test")]
+pub fn update_profile(msg: UpdateProfile, _conn: &mut PgConnection) -> Result<User, DbError> {
+    let mut user = User::from(String::from("my@email.com"), String::from("this is a hash"));
+    user.id = msg.user_id;
+    user.display_name = msg.display_name;
+    user.timezone = msg.timezone;
+    Ok(user)
+}
+
+/// Replaces the password hash and revokes every other session of the user
+/// with their refresh tokens, `keep_session` being the one asking for it.
+/// API tokens are revoked too, they may have been created by whoever knew
+/// the old password.
+#[cfg(not(feature = "db-test"))]
+pub fn change_password(
+    owner: Uuid,
+    new_hash: &str,
+    keep_session: Option<Uuid>,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::{api_token, auth_user, refresh_token, session};
+
+    let kept = keep_session.into_iter().collect::<Vec<Uuid>>();
+    let changed = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let users = diesel::update(auth_user::table.filter(auth_user::id.eq(owner)))
+            .set(auth_user::password.eq(new_hash))
+            .execute(conn)?;
+        diesel::update(
+            session::table
+                .filter(session::user_id.eq(owner))
+                .filter(session::id.ne_all(&kept)),
+        )
+        .set(session::revoked.eq(true))
+        .execute(conn)?;
+        diesel::update(
+            refresh_token::table
+                .filter(refresh_token::user_id.eq(owner))
+                .filter(
+                    refresh_token::session_id
+                        .is_null()
+                        .or(refresh_token::session_id.ne_all(&kept)),
+                ),
+        )
+        .set(refresh_token::revoked.eq(true))
+        .execute(conn)?;
+        diesel::delete(api_token::table.filter(api_token::user_id.eq(owner))).execute(conn)?;
+        Ok(users)
+    });
+
+    match changed {
+        Ok(0) => Err(DbError::CannotFindUser),
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn change_password(
+    _owner: Uuid,
+    _new_hash: &str,
+    _keep_session: Option<Uuid>,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::session::dsl::*;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use diesel::prelude::*;
+
+    #[test]
+    fn password_change_keeps_current_session() {
+        let owner = uuid::Uuid::new_v4();
+        let current = uuid::Uuid::new_v4();
+        let query = diesel::update(
+            session
+                .filter(user_id.eq(owner))
+                .filter(id.ne_all(vec![current])),
+        )
+        .set(revoked.eq(true));
+        let sql = format!(
+            "UPDATE \"session\" SET \"revoked\" = $1 WHERE ((\"session\".\"user_id\" = $2) AND (\"session\".\"id\" != ALL($3))) -- binds: [true, {}, [{}]]",
+            owner, current
+        );
+        assert_eq!(sql, debug_query::<Pg, _>(&query).to_string());
+    }
+}
@@ -0,0 +1,155 @@
+use diesel::{prelude::*, PgConnection};
+use uuid::Uuid;
+
+use crate::todo_api::model::{
+    auth::User,
+    error::DbError,
+    refresh::{RefreshToken, RotatedRefreshToken},
+};
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_refresh_token(row: RefreshToken, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::refresh_token::dsl::*;
+
+    match diesel::insert_into(refresh_token)
+        .values(&row)
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_refresh_token(_row: RefreshToken, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Marks the presented token as used and issues its successor in the same
+/// family, extending the session it belongs to. Presenting an already used
+/// token revokes the whole family.
+#[cfg(not(feature = "db-test"))]
+pub fn rotate_refresh_token(
+    hash: &str,
+    conn: &mut PgConnection,
+) -> Result<RotatedRefreshToken, DbError> {
+    use crate::schema::auth_user::dsl::{auth_user, id as user_id_column};
+    use crate::schema::refresh_token::dsl::*;
+    use crate::schema::session;
+
+    let now = chrono::Utc::now().naive_utc();
+    let rotated = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let stored = match refresh_token
+            .filter(token_hash.eq(hash))
+            .for_update()
+            .first::<RefreshToken>(conn)
+            .optional()?
+        {
+            None => return Ok(Err(DbError::InvalidRefreshToken)),
+            Some(stored) => stored,
+        };
+
+        match stored.check(now) {
+            Err(DbError::RefreshTokenReused) => {
+                diesel::update(refresh_token.filter(family.eq(stored.family)))
+                    .set(revoked.eq(true))
+                    .execute(conn)?;
+                return Ok(Err(DbError::RefreshTokenReused));
+            }
+            Err(e) => return Ok(Err(e)),
+            Ok(_) => (),
+        }
+
+        diesel::update(refresh_token.filter(id.eq(stored.id)))
+            .set(used_at.eq(Some(now)))
+            .execute(conn)?;
+        let user = auth_user
+            .filter(user_id_column.eq(stored.user_id))
+            .first::<User>(conn)?;
+        let (next, token) =
+            RefreshToken::issue(stored.user_id, stored.session_id, Some(stored.family));
+        let mut scope = None;
+        if let Some(current) = stored.session_id {
+            let extended = diesel::update(
+                session::table
+                    .filter(session::id.eq(current))
+                    .filter(session::revoked.eq(false)),
+            )
+            .set((
+                session::expires_at.eq(next.expires_at),
+                session::last_seen_at.eq(now),
+            ))
+            .returning(session::scope)
+            .get_result::<Option<String>>(conn)
+            .optional()?;
+            match extended {
+                None => return Ok(Err(DbError::InvalidRefreshToken)),
+                Some(granted) => scope = granted,
+            }
+        }
+        diesel::insert_into(refresh_token)
+            .values(&next)
+            .execute(conn)?;
+
+        Ok(Ok(RotatedRefreshToken {
+            user,
+            session_id: stored.session_id,
+            token,
+            scope,
+        }))
+    });
+
+    match rotated {
+        Ok(rotated) => rotated,
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn rotate_refresh_token(
+    _hash: &str,
+    _conn: &mut PgConnection,
+) -> Result<RotatedRefreshToken, DbError> {
+    let (_, token) = RefreshToken::issue(Uuid::new_v4(), None, None);
+    Ok(RotatedRefreshToken {
+        user: User::from(String::from("my@email.com"), String::from("this is a hash")),
+        session_id: None,
+        token,
+        scope: None,
+    })
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn revoke_user_refresh_tokens(owner: Uuid, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::refresh_token::dsl::*;
+
+    match diesel::update(refresh_token.filter(user_id.eq(owner)))
+        .set(revoked.eq(true))
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn revoke_user_refresh_tokens(_owner: Uuid, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::refresh_token::dsl::*;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use diesel::prelude::*;
+
+    #[test]
+    fn reuse_revokes_whole_family() {
+        let token_family = uuid::Uuid::new_v4();
+        let query =
+            diesel::update(refresh_token.filter(family.eq(token_family))).set(revoked.eq(true));
+        let sql = String::from("UPDATE \"refresh_token\" SET \"revoked\" = $1 WHERE (\"refresh_token\".\"family\" = $2) -- binds: [true, ") + &token_family.to_string() + "]";
+        assert_eq!(&sql, &debug_query::<Pg, _>(&query).to_string());
+    }
+}
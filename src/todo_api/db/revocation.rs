@@ -0,0 +1,105 @@
+use chrono::NaiveDateTime;
+use diesel::{prelude::*, PgConnection};
+
+use crate::todo_api::model::{error::DbError, revocation::RevokedToken};
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_revoked_token(row: RevokedToken, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::revoked_token::dsl::*;
+
+    match diesel::insert_into(revoked_token)
+        .values(&row)
+        .on_conflict_do_nothing()
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_revoked_token(_row: RevokedToken, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Records a single-use token as spent, `ChallengeUsed` when it already was.
+#[cfg(not(feature = "db-test"))]
+pub fn consume_token(row: RevokedToken, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::revoked_token::dsl::*;
+
+    match diesel::insert_into(revoked_token)
+        .values(&row)
+        .on_conflict_do_nothing()
+        .execute(conn)
+    {
+        Ok(1) => Ok(()),
+        Ok(_) => Err(DbError::ChallengeUsed),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn consume_token(_row: RevokedToken, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn purge_revoked_tokens(conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::revoked_token::dsl::*;
+
+    match diesel::delete(revoked_token.filter(expires_at.le(chrono::Utc::now().naive_utc())))
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn purge_revoked_tokens(_conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_revoked_tokens(
+    since: Option<NaiveDateTime>,
+    conn: &mut PgConnection,
+) -> Result<Vec<RevokedToken>, DbError> {
+    use crate::schema::revoked_token::dsl::*;
+
+    let mut query = revoked_token.into_boxed();
+    if let Some(since) = since {
+        query = query.filter(revoked_at.ge(since));
+    }
+
+    query
+        .load::<RevokedToken>(conn)
+        .map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_revoked_tokens(
+    _since: Option<NaiveDateTime>,
+    _conn: &mut PgConnection,
+) -> Result<Vec<RevokedToken>, DbError> {
+    Ok(Vec::new())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::revoked_token::dsl::*;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use diesel::prelude::*;
+
+    #[test]
+    fn purge_deletes_expired_revocations() {
+        let now = chrono::Utc::now().naive_utc();
+        let query = diesel::delete(revoked_token.filter(expires_at.le(now)));
+        let sql = format!(
+            "DELETE  FROM \"revoked_token\" WHERE (\"revoked_token\".\"expires_at\" <= $1) -- binds: [{:?}]",
+            now
+        );
+        assert_eq!(sql, debug_query::<Pg, _>(&query).to_string());
+    }
+}
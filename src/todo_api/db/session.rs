@@ -0,0 +1,178 @@
+use diesel::{prelude::*, PgConnection};
+use uuid::Uuid;
+
+use crate::todo_api::model::{
+    error::DbError,
+    session::{RevokeSession, Session},
+};
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_session(row: Session, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::session::dsl::*;
+
+    match diesel::insert_into(session).values(&row).execute(conn) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_session(_row: Session, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Sessions of `owner` that are neither revoked nor expired at `now`, most
+/// recently used first.
+#[cfg(any(test, not(feature = "db-test")))]
+fn live_sessions(
+    owner: Uuid,
+    now: chrono::NaiveDateTime,
+) -> crate::schema::session::BoxedQuery<'static, diesel::pg::Pg> {
+    use crate::schema::session::dsl::*;
+
+    session
+        .filter(user_id.eq(owner))
+        .filter(revoked.eq(false))
+        .filter(expires_at.gt(now))
+        .order(last_seen_at.desc())
+        .into_boxed()
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_sessions(owner: Uuid, conn: &mut PgConnection) -> Result<Vec<Session>, DbError> {
+    live_sessions(owner, chrono::Utc::now().naive_utc())
+        .load::<Session>(conn)
+        .map_err(|_| DbError::CannotFindSession)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_sessions(owner: Uuid, _conn: &mut PgConnection) -> Result<Vec<Session>, DbError> {
+    Ok(vec![Session::new(owner, "test")])
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_session(session_id: Uuid, conn: &mut PgConnection) -> Result<Option<Session>, DbError> {
+    use crate::schema::session::dsl::*;
+
+    session
+        .filter(id.eq(session_id))
+        .first::<Session>(conn)
+        .optional()
+        .map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_session(
+    _session_id: Uuid,
+    _conn: &mut PgConnection,
+) -> Result<Option<Session>, DbError> {
+    Ok(None)
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_refresh_session(
+    hash: &str,
+    conn: &mut PgConnection,
+) -> Result<Option<Session>, DbError> {
+    use crate::schema::{refresh_token, session};
+
+    session::table
+        .inner_join(refresh_token::table.on(refresh_token::session_id.eq(session::id.nullable())))
+        .filter(refresh_token::token_hash.eq(hash))
+        .select(session::all_columns)
+        .first::<Session>(conn)
+        .optional()
+        .map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_refresh_session(
+    _hash: &str,
+    _conn: &mut PgConnection,
+) -> Result<Option<Session>, DbError> {
+    Ok(None)
+}
+
+/// Revokes one session of a user together with the refresh tokens issued to it.
+#[cfg(not(feature = "db-test"))]
+pub fn revoke_session(msg: RevokeSession, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::{refresh_token, session};
+
+    let revoked = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let sessions = diesel::update(
+            session::table
+                .filter(session::id.eq(msg.id))
+                .filter(session::user_id.eq(msg.user_id)),
+        )
+        .set(session::revoked.eq(true))
+        .execute(conn)?;
+        diesel::update(refresh_token::table.filter(refresh_token::session_id.eq(msg.id)))
+            .set(refresh_token::revoked.eq(true))
+            .execute(conn)?;
+        Ok(sessions)
+    });
+
+    match revoked {
+        Ok(0) => Err(DbError::CannotFindSession),
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn revoke_session(_msg: RevokeSession, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Validates the session of an access token and refreshes its `last_seen_at`.
+#[cfg(not(feature = "db-test"))]
+pub fn touch_session(
+    session_id: Uuid,
+    owner: Uuid,
+    conn: &mut PgConnection,
+) -> Result<Session, DbError> {
+    use crate::schema::session::dsl::*;
+
+    let now = chrono::Utc::now().naive_utc();
+    let current = session
+        .filter(id.eq(session_id))
+        .first::<Session>(conn)
+        .map_err(|_| DbError::CannotFindSession)?;
+    if !current.is_valid(owner, now) {
+        return Err(DbError::CannotFindSession);
+    }
+
+    if current.needs_touch(now) {
+        diesel::update(session.filter(id.eq(session_id)))
+            .set(last_seen_at.eq(now))
+            .execute(conn)
+            .map_err(|_| DbError::TryAgain)?;
+    }
+    Ok(current)
+}
+
+#[cfg(feature = "db-test")]
+pub fn touch_session(
+    _session_id: Uuid,
+    owner: Uuid,
+    _conn: &mut PgConnection,
+) -> Result<Session, DbError> {
+    Ok(Session::new(owner, "test"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::live_sessions;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+
+    #[test]
+    fn scan_sessions_only_reads_live_sessions_of_user() {
+        let owner = uuid::Uuid::new_v4();
+        let now = chrono::Utc::now().naive_utc();
+        let query = live_sessions(owner, now);
+        let sql = String::from("SELECT \"session\".\"id\", \"session\".\"user_id\", \"session\".\"device\", \"session\".\"created_at\", \"session\".\"last_seen_at\", \"session\".\"expires_at\", \"session\".\"revoked\", \"session\".\"scope\", \"session\".\"client_id\" \
+                FROM \"session\" WHERE (((\"session\".\"user_id\" = $1) AND (\"session\".\"revoked\" = $2)) AND (\"session\".\"expires_at\" > $3)) ORDER BY \"session\".\"last_seen_at\" DESC  -- binds: [") + &owner.to_string() + ", false, " + &format!("{:?}", now) + "]";
+        assert_eq!(&sql, &debug_query::<Pg, _>(&query).to_string());
+    }
+}
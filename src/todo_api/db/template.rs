@@ -0,0 +1,131 @@
+use crate::todo_api::model::TodoTemplateDb;
+use aws_sdk_dynamodb::Client;
+#[cfg(not(feature = "dynamo"))]
+use std::collections::HashMap;
+
+#[cfg(not(feature = "dynamo"))]
+use crate::todo_api::db::helpers::scan_all;
+use crate::{
+    todo_api::db::helpers::TODO_TEMPLATE_TABLE, todo_api_web::model::template::TodoTemplate,
+};
+use log::{debug, error};
+
+#[cfg(not(feature = "dynamo"))]
+pub async fn put_template(client: &Client, template: TodoTemplateDb) -> Option<uuid::Uuid> {
+    match client
+        .put_item()
+        .table_name(TODO_TEMPLATE_TABLE.to_string())
+        .set_item(Some(template.clone().into()))
+        .send()
+        .await
+    {
+        Ok(_) => {
+            debug!("template created with id {:?}", template.id);
+            Some(template.id)
+        }
+        Err(e) => {
+            error!("error when creating template {:?}", e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "dynamo")]
+pub async fn put_template(_client: &Client, template: TodoTemplateDb) -> Option<uuid::Uuid> {
+    Some(template.id)
+}
+
+/// Every template of `owner`.
+#[cfg(not(feature = "dynamo"))]
+pub async fn get_owner_templates(client: &Client, owner: uuid::Uuid) -> Option<Vec<TodoTemplate>> {
+    use crate::todo_api::adapter::template::item_to_template;
+
+    let items = scan_all(
+        client,
+        TODO_TEMPLATE_TABLE,
+        "#owner = :owner",
+        HashMap::from([("#owner".to_string(), "owner".to_string())]),
+        HashMap::from([(":owner".to_string(), val!(S => owner.to_string()))]),
+    )
+    .await?;
+    Some(items.iter().filter_map(item_to_template).collect())
+}
+
+#[cfg(feature = "dynamo")]
+fn fixture_template() -> TodoTemplate {
+    use crate::todo_api_web::model::todo::Task;
+
+    TodoTemplate {
+        id: Some(uuid::Uuid::parse_str("c3c1e1a4-1f0f-4b8e-9c55-6d2f3a0b7e11").unwrap()),
+        name: String::from("release"),
+        title: String::from("Release {{version}}"),
+        description: String::from("Release checklist for {{version}}"),
+        owner: Some(uuid::Uuid::parse_str("ae75c4d8-5241-4f1c-8e85-ff380c041442").unwrap()),
+        tasks: vec![Task {
+            title: String::from("Tag {{version}}"),
+            is_done: false,
+        }],
+    }
+}
+
+#[cfg(feature = "dynamo")]
+pub async fn get_owner_templates(_client: &Client, owner: uuid::Uuid) -> Option<Vec<TodoTemplate>> {
+    Some(
+        vec![fixture_template()]
+            .into_iter()
+            .filter(|t| t.owner == Some(owner))
+            .collect(),
+    )
+}
+
+#[cfg(not(feature = "dynamo"))]
+pub async fn get_template(client: &Client, id: uuid::Uuid) -> Option<TodoTemplate> {
+    use crate::todo_api::adapter::template::item_to_template;
+
+    let item_output = client
+        .get_item()
+        .table_name(TODO_TEMPLATE_TABLE.to_string())
+        .key("id", val!(S => id.to_string()))
+        .send()
+        .await;
+
+    match item_output {
+        Ok(output) => item_to_template(output.item()?),
+        Err(e) => {
+            error!("Could not read template {} due to error {:?}", id, e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "dynamo")]
+pub async fn get_template(client: &Client, id: uuid::Uuid) -> Option<TodoTemplate> {
+    let mut template = fixture_template();
+    template.id = Some(id);
+    Some(template)
+}
+
+#[cfg(not(feature = "dynamo"))]
+pub async fn delete_template(client: &Client, id: uuid::Uuid) -> Option<uuid::Uuid> {
+    match client
+        .delete_item()
+        .table_name(TODO_TEMPLATE_TABLE.to_string())
+        .key("id", val!(S => id.to_string()))
+        .send()
+        .await
+    {
+        Ok(_) => {
+            debug!("template deleted with id {:?}", id);
+            Some(id)
+        }
+        Err(e) => {
+            error!("error when deleting template {:?}", e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "dynamo")]
+pub async fn delete_template(_client: &Client, id: uuid::Uuid) -> Option<uuid::Uuid> {
+    Some(id)
+}
@@ -1,6 +1,10 @@
 use crate::todo_api::model::TodoCardDb;
 use aws_sdk_dynamodb::Client;
+#[cfg(not(feature = "dynamo"))]
+use std::collections::HashMap;
 
+#[cfg(not(feature = "dynamo"))]
+use crate::todo_api::db::helpers::scan_all;
 use crate::{todo_api::db::helpers::TODO_CARD_TABLE, todo_api_web::model::todo::TodoCard};
 use log::{debug, error};
 
@@ -77,5 +81,132 @@ pub async fn get_todos(_client: &Client) -> Option<Vec<TodoCard>> {
             },
         ],
         state: State::Doing,
+        created_at: None,
+        doing_at: None,
+        done_at: None,
+        transfers: vec![],
+        collaborators: vec![],
     }])
 }
+
+/// Every card of `owner`.
+#[cfg(not(feature = "dynamo"))]
+pub async fn get_owner_todos(client: &Client, owner: uuid::Uuid) -> Option<Vec<TodoCard>> {
+    use crate::todo_api::adapter;
+
+    let items = scan_all(
+        client,
+        TODO_CARD_TABLE,
+        "#owner = :owner",
+        HashMap::from([("#owner".to_string(), "owner".to_string())]),
+        HashMap::from([(":owner".to_string(), val!(S => owner.to_string()))]),
+    )
+    .await?;
+    Some(items.iter().filter_map(adapter::item_to_todocard).collect())
+}
+
+#[cfg(feature = "dynamo")]
+pub async fn get_owner_todos(client: &Client, owner: uuid::Uuid) -> Option<Vec<TodoCard>> {
+    Some(
+        get_todos(client)
+            .await?
+            .into_iter()
+            .filter(|c| c.owner == owner)
+            .collect(),
+    )
+}
+
+/// Every card `user` can read, as owner or collaborator. Collaborators are
+/// stored as `{user, role}` maps, so each role is looked up as a whole entry.
+#[cfg(not(feature = "dynamo"))]
+pub async fn get_readable_todos(client: &Client, user: uuid::Uuid) -> Option<Vec<TodoCard>> {
+    use crate::todo_api::{
+        adapter,
+        model::{CollaboratorDb, RoleDb},
+    };
+
+    let collaborator = |role| CollaboratorDb { user, role }.to_db_val();
+    let items = scan_all(
+        client,
+        TODO_CARD_TABLE,
+        "#owner = :user OR contains(#collaborators, :viewer) OR contains(#collaborators, :editor)",
+        HashMap::from([
+            ("#owner".to_string(), "owner".to_string()),
+            ("#collaborators".to_string(), "collaborators".to_string()),
+        ]),
+        HashMap::from([
+            (":user".to_string(), val!(S => user.to_string())),
+            (":viewer".to_string(), collaborator(RoleDb::Viewer)),
+            (":editor".to_string(), collaborator(RoleDb::Editor)),
+        ]),
+    )
+    .await?;
+    Some(items.iter().filter_map(adapter::item_to_todocard).collect())
+}
+
+#[cfg(feature = "dynamo")]
+pub async fn get_readable_todos(client: &Client, user: uuid::Uuid) -> Option<Vec<TodoCard>> {
+    Some(
+        get_todos(client)
+            .await?
+            .into_iter()
+            .filter(|c| c.can_read(user))
+            .collect(),
+    )
+}
+
+#[cfg(not(feature = "dynamo"))]
+pub async fn get_todo(client: &Client, id: uuid::Uuid) -> Option<TodoCard> {
+    use crate::todo_api::adapter;
+
+    let item_output = client
+        .get_item()
+        .table_name(TODO_CARD_TABLE.to_string())
+        .key("id", val!(S => id.to_string()))
+        .send()
+        .await;
+
+    match item_output {
+        Ok(output) => {
+            let card = adapter::item_to_todocard(output.item()?);
+            debug!("Read todo card {:?}", card);
+            card
+        }
+        Err(e) => {
+            error!("Could not read todocard {} due to error {:?}", id, e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "dynamo")]
+pub async fn get_todo(client: &Client, id: uuid::Uuid) -> Option<TodoCard> {
+    let mut card = get_todos(client).await?.pop()?;
+    card.id = Some(id);
+    Some(card)
+}
+
+#[cfg(not(feature = "dynamo"))]
+pub async fn delete_todo(client: &Client, id: uuid::Uuid) -> Option<uuid::Uuid> {
+    match client
+        .delete_item()
+        .table_name(TODO_CARD_TABLE.to_string())
+        .key("id", val!(S => id.to_string()))
+        .send()
+        .await
+    {
+        Ok(_) => {
+            debug!("item deleted with id {:?}", id);
+            Some(id)
+        }
+        Err(e) => {
+            error!("error when deleting item {:?}", e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "dynamo")]
+pub async fn delete_todo(_client: &Client, id: uuid::Uuid) -> Option<uuid::Uuid> {
+    Some(id)
+}
@@ -0,0 +1,206 @@
+use chrono::Utc;
+use diesel::{prelude::*, PgConnection};
+use uuid::Uuid;
+
+use crate::todo_api::{
+    core::totp::verify_code,
+    model::{
+        error::DbError,
+        two_factor::{hash_recovery_code, RecoveryCode, TwoFactor},
+    },
+};
+
+#[cfg(not(feature = "db-test"))]
+pub fn scan_two_factor(user: Uuid, conn: &mut PgConnection) -> Result<Option<TwoFactor>, DbError> {
+    use crate::schema::two_factor::dsl::*;
+
+    two_factor
+        .filter(user_id.eq(user))
+        .first::<TwoFactor>(conn)
+        .optional()
+        .map_err(|_| DbError::TryAgain)
+}
+
+#[cfg(feature = "db-test")]
+pub fn scan_two_factor(
+    _user: Uuid,
+    _conn: &mut PgConnection,
+) -> Result<Option<TwoFactor>, DbError> {
+    Ok(None)
+}
+
+/// Replaces any pending enrollment, an enabled one must be disabled first.
+#[cfg(not(feature = "db-test"))]
+pub fn replace_pending_two_factor(
+    pending: TwoFactor,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::two_factor::dsl::*;
+
+    let replaced = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let current = two_factor
+            .filter(user_id.eq(pending.user_id))
+            .for_update()
+            .first::<TwoFactor>(conn)
+            .optional()?;
+        if current.is_some_and(|current| current.enabled) {
+            return Ok(Err(DbError::TwoFactorAlreadyEnabled));
+        }
+
+        diesel::delete(two_factor.filter(user_id.eq(pending.user_id))).execute(conn)?;
+        diesel::insert_into(two_factor)
+            .values(&pending)
+            .execute(conn)?;
+        Ok(Ok(()))
+    });
+
+    replaced.map_err(|_| DbError::TryAgain)?
+}
+
+#[cfg(feature = "db-test")]
+pub fn replace_pending_two_factor(
+    _pending: TwoFactor,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Enables a pending enrollment when `code` is valid and swaps in a new set
+/// of recovery codes, returned in clear once.
+#[cfg(not(feature = "db-test"))]
+pub fn confirm_two_factor(
+    user: Uuid,
+    code: &str,
+    conn: &mut PgConnection,
+) -> Result<Vec<String>, DbError> {
+    use crate::schema::recovery_code;
+    use crate::schema::two_factor::dsl::*;
+
+    let now = Utc::now();
+    let confirmed = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let pending = match two_factor
+            .filter(user_id.eq(user))
+            .for_update()
+            .first::<TwoFactor>(conn)
+            .optional()?
+        {
+            None => return Ok(Err(DbError::TwoFactorNotEnrolled)),
+            Some(row) if row.enabled => return Ok(Err(DbError::TwoFactorAlreadyEnabled)),
+            Some(row) => row,
+        };
+        let step = match pending
+            .totp_secret()
+            .and_then(|totp_secret| verify_code(&totp_secret, code, now.timestamp(), None))
+        {
+            None => return Ok(Err(DbError::InvalidTwoFactorCode)),
+            Some(step) => step,
+        };
+
+        diesel::update(two_factor.filter(user_id.eq(user)))
+            .set((
+                enabled.eq(true),
+                enabled_at.eq(Some(now.naive_utc())),
+                last_used_step.eq(Some(step)),
+            ))
+            .execute(conn)?;
+        let (rows, codes) = RecoveryCode::issue_all(user);
+        diesel::delete(recovery_code::table.filter(recovery_code::user_id.eq(user)))
+            .execute(conn)?;
+        diesel::insert_into(recovery_code::table)
+            .values(&rows)
+            .execute(conn)?;
+        Ok(Ok(codes))
+    });
+
+    confirmed.map_err(|_| DbError::TryAgain)?
+}
+
+#[cfg(feature = "db-test")]
+pub fn confirm_two_factor(
+    _user: Uuid,
+    _code: &str,
+    _conn: &mut PgConnection,
+) -> Result<Vec<String>, DbError> {
+    Err(DbError::TwoFactorNotEnrolled)
+}
+
+/// Accepts a TOTP code newer than the last one used, otherwise consumes a
+/// matching unused recovery code. Returns true when a recovery code was used.
+#[cfg(not(feature = "db-test"))]
+pub fn verify_second_factor(
+    user: Uuid,
+    code: &str,
+    conn: &mut PgConnection,
+) -> Result<bool, DbError> {
+    use crate::schema::recovery_code;
+    use crate::schema::two_factor::dsl::*;
+
+    let now = Utc::now();
+    let verified = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let enrolled = match two_factor
+            .filter(user_id.eq(user))
+            .for_update()
+            .first::<TwoFactor>(conn)
+            .optional()?
+        {
+            Some(row) if row.enabled => row,
+            _ => return Ok(Err(DbError::TwoFactorNotEnrolled)),
+        };
+
+        if let Some(step) = enrolled.totp_secret().and_then(|totp_secret| {
+            verify_code(&totp_secret, code, now.timestamp(), enrolled.last_used_step)
+        }) {
+            diesel::update(two_factor.filter(user_id.eq(user)))
+                .set(last_used_step.eq(Some(step)))
+                .execute(conn)?;
+            return Ok(Ok(false));
+        }
+
+        let used = diesel::update(
+            recovery_code::table
+                .filter(recovery_code::user_id.eq(user))
+                .filter(recovery_code::code_hash.eq(hash_recovery_code(code)))
+                .filter(recovery_code::used_at.is_null()),
+        )
+        .set(recovery_code::used_at.eq(Some(now.naive_utc())))
+        .execute(conn)?;
+        match used {
+            0 => Ok(Err(DbError::InvalidTwoFactorCode)),
+            _ => Ok(Ok(true)),
+        }
+    });
+
+    verified.map_err(|_| DbError::TryAgain)?
+}
+
+#[cfg(feature = "db-test")]
+pub fn verify_second_factor(
+    _user: Uuid,
+    _code: &str,
+    _conn: &mut PgConnection,
+) -> Result<bool, DbError> {
+    Err(DbError::TwoFactorNotEnrolled)
+}
+
+#[cfg(not(feature = "db-test"))]
+pub fn delete_two_factor(user: Uuid, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::recovery_code;
+    use crate::schema::two_factor::dsl::*;
+
+    let deleted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::delete(recovery_code::table.filter(recovery_code::user_id.eq(user)))
+            .execute(conn)?;
+        diesel::delete(two_factor.filter(user_id.eq(user))).execute(conn)
+    });
+
+    match deleted {
+        Ok(0) => Err(DbError::TwoFactorNotEnrolled),
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn delete_two_factor(_user: Uuid, _conn: &mut PgConnection) -> Result<(), DbError> {
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use chrono::NaiveDateTime;
+use diesel::{prelude::*, PgConnection};
+use uuid::Uuid;
+
+use crate::todo_api::model::{error::DbError, verification::EmailVerification};
+
+#[cfg(not(feature = "db-test"))]
+pub fn insert_verification(row: EmailVerification, conn: &mut PgConnection) -> Result<(), DbError> {
+    use crate::schema::email_verification::dsl::*;
+
+    match diesel::insert_into(email_verification)
+        .values(&row)
+        .execute(conn)
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_verification(
+    _row: EmailVerification,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Stores a new verification unless the ones sent to its user in the last hour
+/// hit the resend limits. The user row stays locked until the insert, so
+/// concurrent requests can't all pass the check.
+#[cfg(not(feature = "db-test"))]
+pub fn insert_resent_verification(
+    row: EmailVerification,
+    now: NaiveDateTime,
+    conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::auth_user;
+    use crate::schema::email_verification::dsl::*;
+    use crate::todo_api::model::verification::check_resend;
+    use chrono::Duration;
+
+    let inserted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        auth_user::table
+            .filter(auth_user::id.eq(row.user_id))
+            .select(auth_user::id)
+            .for_update()
+            .first::<Uuid>(conn)?;
+        let sent = email_verification
+            .filter(user_id.eq(row.user_id))
+            .filter(created_at.gt(now - Duration::hours(1)))
+            .select(created_at)
+            .load::<NaiveDateTime>(conn)?;
+        if let Err(e) = check_resend(&sent, now) {
+            return Ok(Err(e));
+        }
+
+        diesel::insert_into(email_verification)
+            .values(&row)
+            .execute(conn)?;
+        Ok(Ok(()))
+    });
+
+    match inserted {
+        Ok(inserted) => inserted,
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn insert_resent_verification(
+    _row: EmailVerification,
+    _now: NaiveDateTime,
+    _conn: &mut PgConnection,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Consumes the verification token and marks the user's email as verified.
+/// Tokens of an email change also replace the address and revoke every
+/// session, whose tokens still carry the old one.
+#[cfg(not(feature = "db-test"))]
+pub fn verify_email(hash: &str, conn: &mut PgConnection) -> Result<EmailVerification, DbError> {
+    use crate::schema::email_verification::dsl::*;
+    use crate::schema::{auth_user, refresh_token, session};
+
+    let now = chrono::Utc::now().naive_utc();
+    let verified = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let stored = match email_verification
+            .filter(token_hash.eq(hash))
+            .for_update()
+            .first::<EmailVerification>(conn)
+            .optional()?
+        {
+            None => return Ok(Err(DbError::InvalidVerificationToken)),
+            Some(stored) => stored,
+        };
+        if let Err(e) = stored.check(now) {
+            return Ok(Err(e));
+        }
+
+        diesel::update(email_verification.filter(id.eq(stored.id)))
+            .set(used_at.eq(Some(now)))
+            .execute(conn)?;
+        let user = auth_user::table.filter(auth_user::id.eq(stored.user_id));
+        match &stored.new_email {
+            None => {
+                diesel::update(user)
+                    .set(auth_user::email_verified.eq(true))
+                    .execute(conn)?;
+            }
+            Some(address) => {
+                let taken = auth_user::table
+                    .filter(auth_user::email.eq(address))
+                    .count()
+                    .get_result::<i64>(conn)?;
+                if taken > 0 {
+                    return Ok(Err(DbError::EmailTaken));
+                }
+                diesel::update(user)
+                    .set((
+                        auth_user::email.eq(address),
+                        auth_user::email_verified.eq(true),
+                    ))
+                    .execute(conn)?;
+                diesel::update(session::table.filter(session::user_id.eq(stored.user_id)))
+                    .set(session::revoked.eq(true))
+                    .execute(conn)?;
+                diesel::update(
+                    refresh_token::table.filter(refresh_token::user_id.eq(stored.user_id)),
+                )
+                .set(refresh_token::revoked.eq(true))
+                .execute(conn)?;
+            }
+        }
+
+        Ok(Ok(stored))
+    });
+
+    match verified {
+        Ok(verified) => verified,
+        Err(_) => Err(DbError::TryAgain),
+    }
+}
+
+#[cfg(feature = "db-test")]
+pub fn verify_email(_hash: &str, _conn: &mut PgConnection) -> Result<EmailVerification, DbError> {
+    Ok(EmailVerification::issue(Uuid::new_v4()).0)
+}
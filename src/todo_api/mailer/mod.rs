@@ -0,0 +1,104 @@
+pub mod outbox;
+pub mod smtp;
+
+use std::{env, sync::Arc};
+
+use chrono::Utc;
+use uuid::Uuid;
+
+pub static DEFAULT_MAIL_FROM: &str = "no-reply@todo-server.local";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Email {
+    /// Renders the RFC 5322 message, header values are stripped of line
+    /// breaks so user input cannot inject headers.
+    pub fn to_message(&self, from: &str) -> String {
+        let header = |value: &str| value.replace(['\r', '\n'], " ");
+        let body = self.body.replace("\r\n", "\n").replace('\n', "\r\n");
+
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <{}@todo-server>\r\n\
+             MIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Transfer-Encoding: 8bit\r\n\r\n{}\r\n",
+            header(from),
+            header(&self.to),
+            header(&self.subject),
+            Utc::now().to_rfc2822(),
+            Uuid::new_v4(),
+            body
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum MailError {
+    Io(std::io::Error),
+    Rejected(u16, String),
+    InvalidAddress(String),
+}
+
+impl std::fmt::Display for MailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MailError::Io(e) => write!(f, "Email could not be delivered: {}", e),
+            MailError::Rejected(code, reply) => {
+                write!(f, "Email was rejected with {}: {}", code, reply)
+            }
+            MailError::InvalidAddress(address) => {
+                write!(f, "Email address {:?} cannot be used in SMTP", address)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+impl From<std::io::Error> for MailError {
+    fn from(e: std::io::Error) -> Self {
+        MailError::Io(e)
+    }
+}
+
+/// Delivers outgoing emails. Sending blocks, so callers on the async runtime
+/// go through `web::block`.
+pub trait Mailer: std::fmt::Debug + Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+pub fn mail_from() -> String {
+    env::var("MAIL_FROM").unwrap_or_else(|_| DEFAULT_MAIL_FROM.to_string())
+}
+
+/// `MAILER=smtp` delivers through `SMTP_HOST`, anything else writes to the
+/// local outbox directory.
+pub fn mailer_from_env() -> Arc<dyn Mailer> {
+    match env::var("MAILER").as_deref() {
+        Ok("smtp") => Arc::new(smtp::SmtpMailer::from_env()),
+        _ => Arc::new(outbox::OutboxMailer::from_env()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn message_has_headers_and_crlf_body() {
+        let email = Email {
+            to: "my@email.com".to_string(),
+            subject: "Reset\r\nBcc: evil@email.com".to_string(),
+            body: "line one\nline two".to_string(),
+        };
+        let message = email.to_message("from@email.com");
+
+        assert!(message.starts_with("From: from@email.com\r\nTo: my@email.com\r\n"));
+        assert!(message.contains("Subject: Reset  Bcc: evil@email.com\r\n"));
+        assert!(message.ends_with("\r\n\r\nline one\r\nline two\r\n"));
+    }
+}
@@ -0,0 +1,69 @@
+use std::{env, fs, path::PathBuf};
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{mail_from, Email, MailError, Mailer};
+
+pub static DEFAULT_OUTBOX_DIR: &str = "outbox";
+
+/// Writes every email as an `.eml` file instead of sending it, for local
+/// development and tests.
+#[derive(Debug, Clone)]
+pub struct OutboxMailer {
+    pub dir: PathBuf,
+    pub from: String,
+}
+
+impl OutboxMailer {
+    pub fn from_env() -> Self {
+        Self {
+            dir: PathBuf::from(
+                env::var("MAIL_OUTBOX_DIR").unwrap_or_else(|_| DEFAULT_OUTBOX_DIR.to_string()),
+            ),
+            from: mail_from(),
+        }
+    }
+}
+
+impl Mailer for OutboxMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        fs::create_dir_all(&self.dir)?;
+        let name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%d%H%M%S"),
+            Uuid::new_v4()
+        );
+        fs::write(self.dir.join(name), email.to_message(&self.from))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn writes_message_to_outbox() {
+        let mailer = OutboxMailer {
+            dir: env::temp_dir().join(format!("outbox-{}", Uuid::new_v4())),
+            from: "from@email.com".to_string(),
+        };
+        let email = Email {
+            to: "my@email.com".to_string(),
+            subject: "Subject".to_string(),
+            body: "Body".to_string(),
+        };
+
+        mailer.send(&email).unwrap();
+        let files = fs::read_dir(&mailer.dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<PathBuf>>();
+        let message = fs::read_to_string(&files[0]).unwrap();
+        fs::remove_dir_all(&mailer.dir).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert!(message.contains("To: my@email.com\r\n"));
+    }
+}
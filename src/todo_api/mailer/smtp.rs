@@ -0,0 +1,278 @@
+use std::{
+    env,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    sync::Arc,
+    time::Duration,
+};
+
+use rustls::{ClientConfig, ClientSession, StreamOwned};
+
+use super::{mail_from, Email, MailError, Mailer};
+
+pub static DEFAULT_SMTP_HOST: &str = "localhost";
+pub static DEFAULT_SMTP_PORT: u16 = 1025;
+static SMTP_TIMEOUT_SECS: u64 = 10;
+
+/// Minimal SMTP client with optional `AUTH PLAIN`. The session is upgraded
+/// with STARTTLS before authenticating unless `SMTP_STARTTLS` is `false`,
+/// which is meant for a fake SMTP server such as MailHog.
+#[derive(Debug, Clone)]
+pub struct SmtpMailer {
+    pub host: String,
+    pub port: u16,
+    pub credentials: Option<(String, String)>,
+    pub from: String,
+    pub starttls: bool,
+}
+
+impl SmtpMailer {
+    pub fn from_env() -> Self {
+        Self {
+            host: env::var("SMTP_HOST").unwrap_or_else(|_| DEFAULT_SMTP_HOST.to_string()),
+            port: env::var("SMTP_PORT")
+                .ok()
+                .and_then(|port| port.parse::<u16>().ok())
+                .unwrap_or(DEFAULT_SMTP_PORT),
+            credentials: env::var("SMTP_USER")
+                .ok()
+                .zip(env::var("SMTP_PASSWORD").ok()),
+            from: mail_from(),
+            starttls: env::var("SMTP_STARTTLS").map_or(true, |starttls| starttls != "false"),
+        }
+    }
+
+    fn tls(&self, stream: TcpStream) -> Result<StreamOwned<ClientSession, TcpStream>, MailError> {
+        let mut config = ClientConfig::new();
+        config.root_store = rustls_native_certs::load_native_certs().map_err(|(_, e)| e)?;
+        let host = webpki::DNSNameRef::try_from_ascii_str(&self.host)
+            .map_err(|_| MailError::Rejected(0, format!("Invalid SMTP host {}", self.host)))?;
+        let session = ClientSession::new(&Arc::new(config), host);
+        Ok(StreamOwned::new(session, stream))
+    }
+
+    fn deliver<S: Read + Write>(
+        &self,
+        mut session: SmtpSession<S>,
+        email: &Email,
+    ) -> Result<(), MailError> {
+        if let Some((user, password)) = &self.credentials {
+            let plain = base64::encode(format!("\0{}\0{}", user, password));
+            session.command(&format!("AUTH PLAIN {}", plain), &[235])?;
+        }
+        session.command(&format!("MAIL FROM:<{}>", self.from), &[250])?;
+        session.command(&format!("RCPT TO:<{}>", email.to), &[250, 251])?;
+        session.command("DATA", &[354])?;
+        session.write(&dot_stuff(&email.to_message(&self.from)))?;
+        session.command(".", &[250])?;
+        session.command("QUIT", &[221]).map(|_| ())
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        for address in [&self.from, &email.to] {
+            if address.contains(['\r', '\n', '<', '>']) {
+                return Err(MailError::InvalidAddress(address.to_string()));
+            }
+        }
+
+        let stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        stream.set_read_timeout(Some(Duration::from_secs(SMTP_TIMEOUT_SECS)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(SMTP_TIMEOUT_SECS)))?;
+        let mut session = SmtpSession::new(stream);
+
+        session.expect(&[220])?;
+        let extensions = session.command("EHLO todo-server", &[250])?;
+        if !self.starttls {
+            return self.deliver(session, email);
+        }
+
+        if !extensions.lines().any(|line| {
+            line.get(4..)
+                .is_some_and(|extension| extension.trim().eq_ignore_ascii_case("STARTTLS"))
+        }) {
+            return Err(MailError::Rejected(
+                0,
+                String::from("SMTP server does not offer STARTTLS"),
+            ));
+        }
+        session.command("STARTTLS", &[220])?;
+        let mut session = SmtpSession::new(self.tls(session.into_inner())?);
+        session.command("EHLO todo-server", &[250])?;
+        self.deliver(session, email)
+    }
+}
+
+struct SmtpSession<S: Read + Write> {
+    stream: BufReader<S>,
+}
+
+impl<S: Read + Write> SmtpSession<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    fn write(&mut self, data: &str) -> Result<(), MailError> {
+        self.stream.get_mut().write_all(data.as_bytes())?;
+        Ok(())
+    }
+
+    fn command(&mut self, line: &str, accepted: &[u16]) -> Result<String, MailError> {
+        self.write(&format!("{}\r\n", line))?;
+        self.expect(accepted)
+    }
+
+    /// Reads a possibly multiline reply and checks its status code.
+    fn expect(&mut self, accepted: &[u16]) -> Result<String, MailError> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(MailError::Rejected(0, reply));
+            }
+            reply.push_str(&line);
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+        }
+
+        let code = reply
+            .get(..3)
+            .and_then(|code| code.parse::<u16>().ok())
+            .unwrap_or(0);
+        if accepted.contains(&code) {
+            Ok(reply)
+        } else {
+            Err(MailError::Rejected(code, reply.trim_end().to_string()))
+        }
+    }
+}
+
+/// Escapes lines starting with a dot so they don't end the DATA section.
+fn dot_stuff(message: &str) -> String {
+    message
+        .split("\r\n")
+        .map(|line| {
+            if line.starts_with('.') {
+                format!(".{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\r\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn fake_smtp_server(replies: Vec<&'static str>) -> (u16, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut received = Vec::new();
+            writer.write_all(b"220 fake ESMTP\r\n").unwrap();
+            let mut in_data = false;
+            for reply in replies {
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    received.push(line.clone());
+                    if !in_data || line == ".\r\n" {
+                        in_data = line == "DATA\r\n";
+                        break;
+                    }
+                }
+                writer.write_all(reply.as_bytes()).unwrap();
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    fn mailer(port: u16) -> SmtpMailer {
+        SmtpMailer {
+            host: "127.0.0.1".to_string(),
+            port,
+            credentials: None,
+            from: "from@email.com".to_string(),
+            starttls: false,
+        }
+    }
+
+    fn email() -> Email {
+        Email {
+            to: "my@email.com".to_string(),
+            subject: "Subject".to_string(),
+            body: "Hello\n.hidden".to_string(),
+        }
+    }
+
+    #[test]
+    fn delivers_to_fake_server() {
+        let (port, server) = fake_smtp_server(vec![
+            "250-fake\r\n250 8BITMIME\r\n",
+            "250 OK\r\n",
+            "250 OK\r\n",
+            "354 Go ahead\r\n",
+            "250 Queued\r\n",
+            "221 Bye\r\n",
+        ]);
+
+        mailer(port).send(&email()).unwrap();
+        let received = server.join().unwrap();
+
+        assert_eq!(received[0], "EHLO todo-server\r\n");
+        assert_eq!(received[1], "MAIL FROM:<from@email.com>\r\n");
+        assert_eq!(received[2], "RCPT TO:<my@email.com>\r\n");
+        assert!(received.contains(&"..hidden\r\n".to_string()));
+        assert_eq!(received.last().unwrap(), "QUIT\r\n");
+    }
+
+    #[test]
+    fn line_breaks_in_addresses_are_refused() {
+        let mut email = email();
+        email.to = "my@email.com>\r\nRCPT TO:<evil@email.com".to_string();
+
+        match mailer(1).send(&email) {
+            Err(MailError::InvalidAddress(_)) => (),
+            e => panic!("expected invalid address, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn refuses_plaintext_when_starttls_is_missing() {
+        let (port, server) = fake_smtp_server(vec!["250-fake\r\n250 AUTH PLAIN\r\n"]);
+        let mut mailer = mailer(port);
+        mailer.starttls = true;
+        mailer.credentials = Some(("user".to_string(), "password".to_string()));
+
+        assert!(mailer.send(&email()).is_err());
+        assert_eq!(server.join().unwrap(), vec!["EHLO todo-server\r\n"]);
+    }
+
+    #[test]
+    fn rejected_recipient_is_an_error() {
+        let (port, server) =
+            fake_smtp_server(vec!["250 fake\r\n", "250 OK\r\n", "550 No such user\r\n"]);
+
+        match mailer(port).send(&email()) {
+            Err(MailError::Rejected(code, _)) => assert_eq!(code, 550),
+            e => panic!("expected rejection, got {:?}", e),
+        }
+        server.join().unwrap();
+    }
+}
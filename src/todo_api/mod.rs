@@ -2,4 +2,6 @@
 pub mod adapter;
 pub mod core;
 pub mod db;
+pub mod mailer;
 pub mod model;
+pub mod oidc;
@@ -0,0 +1,216 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::{api_token::generate_api_token, hashing::hash_token},
+    db::helpers::DbExecutor,
+    model::{core::JwtValue, error::DbError},
+};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+pub static API_TOKEN_TOUCH_SECS: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = api_token)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    /// Returns the row together with the raw token, which is only shown once.
+    pub fn issue(
+        user_id: Uuid,
+        name: &str,
+        scopes: String,
+        expires_at: Option<NaiveDateTime>,
+    ) -> (Self, String) {
+        let token = generate_api_token();
+        let row = Self {
+            id: Uuid::new_v4(),
+            user_id,
+            name: name.trim().to_string(),
+            token_hash: hash_token(&token),
+            scopes,
+            created_at: Utc::now().naive_utc(),
+            expires_at,
+            last_used_at: None,
+        };
+        (row, token)
+    }
+
+    pub fn is_valid(&self, now: NaiveDateTime) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+
+    /// Avoids writing `last_used_at` on every authenticated request.
+    pub fn needs_touch(&self, now: NaiveDateTime) -> bool {
+        self.last_used_at
+            .is_none_or(|used| now - used >= Duration::seconds(API_TOKEN_TOUCH_SECS))
+    }
+
+    pub fn scope_list(&self) -> Vec<String> {
+        self.scopes.split_whitespace().map(String::from).collect()
+    }
+
+    /// Identity handlers see for requests authenticated with this token.
+    pub fn identity(&self, email: &str) -> JwtValue {
+        JwtValue {
+            id: self.user_id.to_string(),
+            email: email.to_string(),
+            expires_at: self.expires_at.unwrap_or(NaiveDateTime::MAX),
+            sid: None,
+            jti: None,
+            scope: Some(self.scopes.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateApiToken {
+    pub user_id: Uuid,
+    pub name: String,
+    pub scopes: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl Message for CreateApiToken {
+    type Result = Result<(ApiToken, String), DbError>;
+}
+
+impl Handler<CreateApiToken> for DbExecutor {
+    type Result = Result<(ApiToken, String), DbError>;
+
+    fn handle(&mut self, msg: CreateApiToken, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::api_token::insert_api_token;
+
+        let (row, token) = ApiToken::issue(msg.user_id, &msg.name, msg.scopes, msg.expires_at);
+        insert_api_token(
+            row.clone(),
+            &mut self.0.get().expect("Failed to open connection"),
+        )?;
+        Ok((row, token))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadApiTokens {
+    pub user_id: Uuid,
+}
+
+impl Message for ReadApiTokens {
+    type Result = Result<Vec<ApiToken>, DbError>;
+}
+
+impl Handler<ReadApiTokens> for DbExecutor {
+    type Result = Result<Vec<ApiToken>, DbError>;
+
+    fn handle(&mut self, msg: ReadApiTokens, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::api_token::scan_api_tokens;
+
+        scan_api_tokens(
+            msg.user_id,
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RevokeApiToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+}
+
+impl Message for RevokeApiToken {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<RevokeApiToken> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: RevokeApiToken, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::api_token::delete_api_token;
+
+        delete_api_token(msg, &mut self.0.get().expect("Failed to open connection"))
+    }
+}
+
+/// Resolves a raw API token to the identity of its owner, `None` when it is
+/// unknown or expired.
+#[derive(Debug, Clone)]
+pub struct AuthenticateApiToken {
+    pub token: String,
+}
+
+impl Message for AuthenticateApiToken {
+    type Result = Result<Option<JwtValue>, DbError>;
+}
+
+impl Handler<AuthenticateApiToken> for DbExecutor {
+    type Result = Result<Option<JwtValue>, DbError>;
+
+    fn handle(&mut self, msg: AuthenticateApiToken, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::api_token::touch_api_token;
+
+        let found = touch_api_token(
+            &hash_token(&msg.token),
+            &mut self.0.get().expect("Failed to open connection"),
+        )?;
+        Ok(found.map(|(token, email)| token.identity(&email)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn issue(expires_at: Option<NaiveDateTime>) -> ApiToken {
+        ApiToken::issue(Uuid::new_v4(), " ci ", "todo:read".to_string(), expires_at).0
+    }
+
+    #[test]
+    fn only_the_hash_is_stored() {
+        let (row, token) = ApiToken::issue(Uuid::new_v4(), "ci", "todo:read".to_string(), None);
+
+        assert_ne!(row.token_hash, token);
+        assert_eq!(row.token_hash, hash_token(&token));
+    }
+
+    #[test]
+    fn expired_token_is_invalid() {
+        let now = Utc::now().naive_utc();
+
+        assert!(issue(None).is_valid(now));
+        assert!(issue(Some(now + Duration::days(1))).is_valid(now));
+        assert!(!issue(Some(now - Duration::seconds(1))).is_valid(now));
+    }
+
+    #[test]
+    fn last_use_is_throttled() {
+        let now = Utc::now().naive_utc();
+        let mut token = issue(None);
+        assert!(token.needs_touch(now));
+
+        token.last_used_at = Some(now);
+        assert!(!token.needs_touch(now + Duration::seconds(10)));
+        assert!(token.needs_touch(now + Duration::seconds(API_TOKEN_TOUCH_SECS)));
+    }
+
+    #[test]
+    fn identity_belongs_to_owner() {
+        let token = issue(None);
+        let identity = token.identity("my@email.com");
+
+        assert_eq!(identity.id, token.user_id.to_string());
+        assert_eq!(identity.email, "my@email.com");
+        assert_eq!(identity.sid, None);
+        assert_eq!(identity.granted_scopes(), "todo:read");
+        assert_eq!(token.name, "ci");
+    }
+}
@@ -0,0 +1,47 @@
+use crate::schema::*;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditAction {
+    AccountLocked,
+    IpLocked,
+    AccountUnlocked,
+    AdminUnlocked,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    RecoveryCodeUsed,
+    ExternalIdentityLinked,
+    EmailChanged,
+    PasswordChanged,
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Security relevant event, `subject` is the throttle key or user it concerns
+/// and `actor` the user who caused it when known.
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = audit_event)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub action: String,
+    pub subject: String,
+    pub actor: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, subject: &str, actor: Option<Uuid>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            action: action.to_string(),
+            subject: subject.to_string(),
+            actor,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
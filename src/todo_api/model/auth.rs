@@ -1,5 +1,13 @@
 use crate::schema::*;
-use bcrypt::{verify, BcryptResult};
+use crate::todo_api::{
+    core::{
+        hashing::{hash_password, needs_rehash, verify_password},
+        validation::DEFAULT_TIMEZONE,
+    },
+    db::helpers::DbExecutor,
+    model::error::DbError,
+};
+use actix::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable)]
@@ -16,6 +24,9 @@ pub struct User {
     #[cfg(not(test))]
     pub expires_at: chrono::NaiveDateTime,
     pub is_active: bool,
+    pub email_verified: bool,
+    pub display_name: Option<String>,
+    pub timezone: String,
 }
 
 impl User {
@@ -28,6 +39,9 @@ impl User {
             password: password,
             expires_at: utc.naive_utc(),
             is_active: false,
+            email_verified: false,
+            display_name: None,
+            timezone: DEFAULT_TIMEZONE.to_string(),
         }
     }
 
@@ -41,17 +55,25 @@ impl User {
             password: password,
             expires_at: utc.naive_utc(),
             is_active: true,
+            email_verified: true,
+            display_name: None,
+            timezone: DEFAULT_TIMEZONE.to_string(),
         }
     }
 
+    /// Accepts Argon2id and legacy bcrypt hashes.
     #[cfg(not(feature = "db-test"))]
-    pub fn verify(&self, pswd: String) -> BcryptResult<bool> {
-        verify(pswd, &self.password)
+    pub fn verify(&self, pswd: String) -> bool {
+        verify_password(&pswd, &self.password)
     }
 
     #[cfg(feature = "db-test")]
-    pub fn verify(&self, _pswd: String) -> BcryptResult<bool> {
-        BcryptResult::Ok(true)
+    pub fn verify(&self, _pswd: String) -> bool {
+        true
+    }
+
+    pub fn needs_rehash(&self) -> bool {
+        needs_rehash(&self.password)
     }
 
     pub fn get_id(self) -> String {
@@ -61,11 +83,38 @@ impl User {
     #[cfg(test)]
     pub fn is_user_valid(self, email: &str, password: &str) {
         assert_eq!(self.email, String::from(email));
-        assert!(verify(password, &self.password).unwrap());
+        assert!(verify_password(password, &self.password));
         assert!(self.id.to_string().len() == 36);
     }
 }
 
+/// Replaces the hash of a password that was just verified, unless it changed
+/// in the meantime.
+#[derive(Debug, Clone)]
+pub struct RehashPassword {
+    pub user: User,
+    pub password: String,
+}
+
+impl Message for RehashPassword {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<RehashPassword> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: RehashPassword, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::auth::update_password_hash;
+
+        update_password_hash(
+            msg.user.id,
+            &msg.user.password,
+            &hash_password(&msg.password),
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1,15 +1,25 @@
-use crate::todo_api::{core::validate_jwt_date, db::helpers::DbExecutor, model::error::DbError};
+use crate::todo_api::{
+    core::validate_jwt_date,
+    db::helpers::DbExecutor,
+    model::{auth::User, error::DbError},
+};
 use actix::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Jwt {
-    token: String,
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
 }
 
 impl Jwt {
-    pub fn new(jwt: String) -> Self {
-        Self { token: jwt }
+    pub fn new(jwt: String, refresh_token: String, expires_in: i64) -> Self {
+        Self {
+            token: jwt,
+            refresh_token,
+            expires_in,
+        }
     }
 }
 
@@ -34,11 +44,40 @@ impl Handler<UpdateUserStatus> for DbExecutor {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChallengeClaims {
+    pub sub: uuid::Uuid,
+    pub email: String,
+    pub jti: uuid::Uuid,
+    pub exp: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct JwtValue {
     pub id: String,
     pub email: String,
     pub expires_at: chrono::NaiveDateTime,
+    #[serde(default)]
+    pub sid: Option<uuid::Uuid>,
+    #[serde(default)]
+    pub jti: Option<uuid::Uuid>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl JwtValue {
+    /// Tokens issued before scopes existed keep the scopes of a login.
+    pub fn granted_scopes(&self) -> String {
+        self.scope
+            .clone()
+            .unwrap_or_else(|| crate::todo_api::core::scope::user_scopes(&self.email))
+    }
+
+    /// Logged out, deactivated and expired accounts no longer accept their
+    /// tokens, whether or not they belong to a session.
+    pub fn is_accepted_by(&self, user: &User) -> bool {
+        user.is_active && validate_jwt_date(user.expires_at) && user.id.to_string() == self.id
+    }
 }
 
 impl Message for JwtValue {
@@ -48,26 +87,24 @@ impl Message for JwtValue {
 impl Handler<JwtValue> for DbExecutor {
     type Result = bool;
 
+    /// Tokens of a session also need the session to be live, on top of the
+    /// account checks every token goes through.
     #[cfg(not(feature = "dbtest"))]
     fn handle(&mut self, msg: JwtValue, _: &mut Self::Context) -> Self::Result {
-        use crate::todo_api::db::auth::scan_user;
+        use crate::todo_api::db::{auth::scan_user, session::touch_session};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let user = match scan_user(String::from(&msg.email), &mut conn) {
+            Err(_) => return false,
+            Ok(user) => user,
+        };
+        if !msg.is_accepted_by(&user) {
+            return false;
+        }
 
-        let user = scan_user(
-            String::from(&msg.email),
-            &mut self.0.get().expect("Failed to open connection"),
-        );
-        match user {
-            Err(_) => false,
-            Ok(user) => {
-                match (
-                    user.is_active,
-                    validate_jwt_date(user.expires_at),
-                    user.id.to_string() == msg.id,
-                ) {
-                    (true, true, true) => true,
-                    _ => false,
-                }
-            }
+        match msg.sid {
+            None => true,
+            Some(sid) => touch_session(sid, user.id, &mut conn).is_ok(),
         }
     }
 
@@ -82,16 +119,7 @@ impl Handler<JwtValue> for DbExecutor {
         );
         match user {
             Err(_) => false,
-            Ok(user) => {
-                match (
-                    user.is_active,
-                    validate_jwt_date(user.expires_at),
-                    user.id.to_string() == msg.id,
-                ) {
-                    (true, true, true) => true,
-                    _ => false,
-                }
-            }
+            Ok(user) => msg.is_accepted_by(&user),
         }
     }
 }
@@ -124,3 +152,26 @@ impl Handler<Inactivate> for DbExecutor {
         inactivate_user(msg, &mut self.0.get().expect("Failed to open connection"))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn session_tokens_of_inactive_accounts_are_rejected() {
+        let mut user = User::from(String::from("my@email.com"), String::from("hash"));
+        user.is_active = true;
+        let jwt = JwtValue {
+            id: user.id.to_string(),
+            email: user.email.clone(),
+            expires_at: user.expires_at,
+            sid: Some(uuid::Uuid::new_v4()),
+            jti: None,
+            scope: None,
+        };
+
+        assert!(jwt.is_accepted_by(&user));
+        user.is_active = false;
+        assert!(!jwt.is_accepted_by(&user));
+    }
+}
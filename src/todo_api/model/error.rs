@@ -6,6 +6,25 @@ pub enum DbError {
     DatabaseConflit,
     CannotFindUser,
     TryAgain,
+    HistoryNotRecorded,
+    CannotReadHistory,
+    InvalidRefreshToken,
+    RefreshTokenReused,
+    CannotFindSession,
+    InvalidResetToken,
+    InvalidVerificationToken,
+    VerificationThrottled,
+    InvalidUnlockToken,
+    TwoFactorAlreadyEnabled,
+    TwoFactorNotEnrolled,
+    InvalidTwoFactorCode,
+    ChallengeUsed,
+    CannotFindApiToken,
+    CannotFindClient,
+    InvalidGrant,
+    InvalidOidcState,
+    ExternalEmailNotVerified,
+    EmailTaken,
 }
 
 impl std::fmt::Display for DbError {
@@ -15,6 +34,29 @@ impl std::fmt::Display for DbError {
             DbError::DatabaseConflit => write!(f, "There are conflits in database"),
             DbError::CannotFindUser => write!(f, "User could not be found"),
             DbError::TryAgain => write!(f, "Expire date could not be updated"),
+            DbError::HistoryNotRecorded => write!(f, "Card history could not be recorded"),
+            DbError::CannotReadHistory => write!(f, "Card history could not be read"),
+            DbError::InvalidRefreshToken => write!(f, "Refresh token is invalid or expired"),
+            DbError::RefreshTokenReused => write!(f, "Refresh token was already used"),
+            DbError::CannotFindSession => write!(f, "Session could not be found"),
+            DbError::InvalidResetToken => write!(f, "Password reset token is invalid or expired"),
+            DbError::InvalidVerificationToken => {
+                write!(f, "Email verification token is invalid or expired")
+            }
+            DbError::VerificationThrottled => write!(f, "Verification email was sent recently"),
+            DbError::InvalidUnlockToken => write!(f, "Unlock token is invalid"),
+            DbError::TwoFactorAlreadyEnabled => write!(f, "Two-factor is already enabled"),
+            DbError::TwoFactorNotEnrolled => write!(f, "Two-factor is not enrolled"),
+            DbError::InvalidTwoFactorCode => write!(f, "Two-factor code is invalid"),
+            DbError::ChallengeUsed => write!(f, "Two-factor challenge was already used"),
+            DbError::CannotFindApiToken => write!(f, "API token could not be found"),
+            DbError::CannotFindClient => write!(f, "OAuth client could not be found"),
+            DbError::InvalidGrant => write!(f, "Authorization code is invalid or expired"),
+            DbError::InvalidOidcState => write!(f, "OpenID login state is invalid or expired"),
+            DbError::ExternalEmailNotVerified => {
+                write!(f, "External identity has no verified email")
+            }
+            DbError::EmailTaken => write!(f, "Email is used by another account"),
         }
     }
 }
@@ -26,6 +68,31 @@ impl Error for DbError {
             DbError::DatabaseConflit => "There are conflits in database",
             DbError::CannotFindUser => "User could not be found",
             DbError::TryAgain => "Expire date could not be updated",
+            DbError::HistoryNotRecorded => "Card history could not be recorded",
+            DbError::CannotReadHistory => "Card history could not be read",
+            DbError::InvalidRefreshToken => "Refresh token is invalid or expired",
+            DbError::RefreshTokenReused => "Refresh token was already used, its family is revoked",
+            DbError::CannotFindSession => "Session could not be found or is no longer active",
+            DbError::InvalidResetToken => "Password reset token is invalid, used or expired",
+            DbError::InvalidVerificationToken => {
+                "Email verification token is invalid, used or expired"
+            }
+            DbError::VerificationThrottled => "Verification email was sent recently, try later",
+            DbError::InvalidUnlockToken => "Unlock token is invalid or the account is not locked",
+            DbError::TwoFactorAlreadyEnabled => "Two-factor is already enabled, disable it first",
+            DbError::TwoFactorNotEnrolled => "Two-factor enrollment was not started or enabled",
+            DbError::InvalidTwoFactorCode => "Two-factor or recovery code is invalid or was used",
+            DbError::ChallengeUsed => "Two-factor challenge was already exchanged for a token",
+            DbError::CannotFindApiToken => "API token does not exist or belongs to another user",
+            DbError::CannotFindClient => "OAuth client does not exist or belongs to another user",
+            DbError::InvalidGrant => "Authorization code is invalid, expired or was already used",
+            DbError::InvalidOidcState => {
+                "OpenID login state is unknown, expired or was already used"
+            }
+            DbError::ExternalEmailNotVerified => {
+                "The provider did not verify the email of this new external identity"
+            }
+            DbError::EmailTaken => "Email already belongs to another account",
         }
     }
 
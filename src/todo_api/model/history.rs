@@ -0,0 +1,177 @@
+use crate::schema::*;
+use crate::todo_api::{core::history::card_diff, db::helpers::DbExecutor, model::error::DbError};
+use crate::todo_api_web::model::todo::TodoCard;
+use actix::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum HistoryAction {
+    Create,
+    Update,
+    Transition,
+    Delete,
+    Transfer,
+    Share,
+    Revoke,
+    Undo,
+}
+
+impl std::fmt::Display for HistoryAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = card_history)]
+pub struct CardHistoryEntry {
+    pub id: Uuid,
+    pub card_id: Uuid,
+    pub actor: Uuid,
+    pub action: String,
+    pub changed_at: chrono::NaiveDateTime,
+    pub diff: Value,
+    #[serde(default, skip_serializing)]
+    pub snapshot: Option<Value>,
+    pub undone: bool,
+}
+
+impl CardHistoryEntry {
+    pub fn new(
+        card_id: Uuid,
+        actor: Uuid,
+        action: HistoryAction,
+        before: Option<&TodoCard>,
+        after: Option<&TodoCard>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            card_id,
+            actor,
+            action: action.to_string(),
+            changed_at: chrono::Utc::now().naive_utc(),
+            diff: serde_json::to_value(card_diff(before, after)).unwrap_or(Value::Null),
+            snapshot: before.and_then(|card| serde_json::to_value(card).ok()),
+            undone: false,
+        }
+    }
+
+    pub fn snapshot_card(&self) -> Option<TodoCard> {
+        serde_json::from_value(self.snapshot.clone()?).ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordHistory(pub CardHistoryEntry);
+
+impl Message for RecordHistory {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<RecordHistory> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: RecordHistory, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::history::insert_history;
+
+        insert_history(msg.0, &mut self.0.get().expect("Failed to open connection"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadHistory {
+    pub card_id: Uuid,
+}
+
+impl Message for ReadHistory {
+    type Result = Result<Vec<CardHistoryEntry>, DbError>;
+}
+
+impl Handler<ReadHistory> for DbExecutor {
+    type Result = Result<Vec<CardHistoryEntry>, DbError>;
+
+    fn handle(&mut self, msg: ReadHistory, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::history::scan_history;
+
+        scan_history(
+            msg.card_id,
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadUndoable {
+    pub actor: Uuid,
+    pub since: chrono::NaiveDateTime,
+    pub token: Option<Uuid>,
+    pub count: i64,
+}
+
+impl Message for ReadUndoable {
+    type Result = Result<Vec<CardHistoryEntry>, DbError>;
+}
+
+impl Handler<ReadUndoable> for DbExecutor {
+    type Result = Result<Vec<CardHistoryEntry>, DbError>;
+
+    fn handle(&mut self, msg: ReadUndoable, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::history::scan_undoable;
+
+        scan_undoable(msg, &mut self.0.get().expect("Failed to open connection"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadLaterHistory {
+    pub card_id: Uuid,
+    pub after: chrono::NaiveDateTime,
+}
+
+impl Message for ReadLaterHistory {
+    type Result = Result<Vec<CardHistoryEntry>, DbError>;
+}
+
+impl Handler<ReadLaterHistory> for DbExecutor {
+    type Result = Result<Vec<CardHistoryEntry>, DbError>;
+
+    fn handle(&mut self, msg: ReadLaterHistory, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::history::scan_later_history;
+
+        scan_later_history(msg, &mut self.0.get().expect("Failed to open connection"))
+    }
+}
+
+/// Marks an entry undone together with recording the `Undo` entry.
+#[derive(Debug, Clone)]
+pub struct MarkUndone {
+    pub id: Uuid,
+    pub undo: CardHistoryEntry,
+}
+
+impl Message for MarkUndone {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<MarkUndone> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: MarkUndone, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::history::mark_undone;
+
+        mark_undone(
+            msg.id,
+            msg.undo,
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(default)]
+    pub kid: String,
+    #[serde(default)]
+    pub alg: String,
+    #[serde(rename = "use", default)]
+    pub key_use: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+impl Jwk {
+    pub fn rsa(kid: &str, alg: String, n: String, e: String) -> Self {
+        Self {
+            kty: String::from("RSA"),
+            kid: kid.to_string(),
+            alg,
+            key_use: String::from("sig"),
+            n: Some(n),
+            e: Some(e),
+            crv: None,
+            x: None,
+            y: None,
+        }
+    }
+
+    pub fn ec(kid: &str, alg: String, x: String, y: String) -> Self {
+        Self {
+            kty: String::from("EC"),
+            kid: kid.to_string(),
+            alg,
+            key_use: String::from("sig"),
+            n: None,
+            e: None,
+            crv: Some(String::from("P-256")),
+            x: Some(x),
+            y: Some(y),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct JwksResponse {
+    pub keys: Vec<Jwk>,
+}
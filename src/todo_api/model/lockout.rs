@@ -0,0 +1,230 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::{
+        hashing::{generate_token, hash_token},
+        lockout::{account_key, ip_key, throttle_expiry, LockoutPolicy},
+    },
+    db::helpers::DbExecutor,
+    model::{
+        audit::{AuditAction, AuditEvent},
+        error::DbError,
+    },
+};
+use actix::prelude::*;
+use chrono::{NaiveDateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = login_throttle)]
+pub struct LoginThrottle {
+    pub key: String,
+    pub failures: i32,
+    pub last_failure_at: NaiveDateTime,
+    pub locked_until: Option<NaiveDateTime>,
+    pub unlock_token_hash: Option<String>,
+}
+
+impl LoginThrottle {
+    pub fn new(key: String, now: NaiveDateTime) -> Self {
+        Self {
+            key,
+            failures: 0,
+            last_failure_at: now,
+            locked_until: None,
+            unlock_token_hash: None,
+        }
+    }
+}
+
+/// What `BeginLogin` did with an attempt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginAttempt {
+    /// Nothing was counted, the next attempt is allowed at this time.
+    Throttled(NaiveDateTime),
+    /// Counted as a failure until the credentials check out, holds the keys
+    /// it locked.
+    Counted(Vec<String>),
+}
+
+/// Counts a login attempt for the account and the address before the
+/// credentials are checked, so parallel guesses cannot get past the lockout
+/// threshold.
+#[derive(Debug, Clone)]
+pub struct BeginLogin {
+    pub email: String,
+    pub ip: String,
+}
+
+impl Message for BeginLogin {
+    type Result = Result<LoginAttempt, DbError>;
+}
+
+impl Handler<BeginLogin> for DbExecutor {
+    type Result = Result<LoginAttempt, DbError>;
+
+    fn handle(&mut self, msg: BeginLogin, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::lockout::{count_attempt, purge_throttles};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let now = Utc::now().naive_utc();
+        purge_throttles(throttle_expiry(now), now, &mut conn)?;
+        count_attempt(
+            &[
+                (account_key(&msg.email), LockoutPolicy::account()),
+                (ip_key(&msg.ip), LockoutPolicy::ip()),
+            ],
+            now,
+            &mut conn,
+        )
+    }
+}
+
+/// Audits the lockouts a failed attempt caused. When it locked an existing
+/// account, an unlock token is issued and returned for the email.
+#[derive(Debug, Clone)]
+pub struct RecordLoginFailure {
+    pub email: String,
+    pub ip: String,
+    pub user_id: Option<Uuid>,
+    pub locked: Vec<String>,
+}
+
+impl Message for RecordLoginFailure {
+    type Result = Result<Option<String>, DbError>;
+}
+
+impl Handler<RecordLoginFailure> for DbExecutor {
+    type Result = Result<Option<String>, DbError>;
+
+    fn handle(&mut self, msg: RecordLoginFailure, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, lockout::set_unlock_token};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let account = account_key(&msg.email);
+        let ip = ip_key(&msg.ip);
+
+        if msg.locked.contains(&ip) {
+            insert_audit_event(AuditEvent::new(AuditAction::IpLocked, &ip, None), &mut conn)?;
+        }
+        if !msg.locked.contains(&account) {
+            return Ok(None);
+        }
+
+        insert_audit_event(
+            AuditEvent::new(AuditAction::AccountLocked, &account, msg.user_id),
+            &mut conn,
+        )?;
+        match msg.user_id {
+            None => Ok(None),
+            Some(_) => {
+                let token = generate_token();
+                set_unlock_token(&account, &hash_token(&token), &mut conn)?;
+                Ok(Some(token))
+            }
+        }
+    }
+}
+
+/// Resets the account's failures and takes back the attempt counted for the
+/// address.
+#[derive(Debug, Clone)]
+pub struct RecordLoginSuccess {
+    pub email: String,
+    pub ip: String,
+}
+
+impl Message for RecordLoginSuccess {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<RecordLoginSuccess> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: RecordLoginSuccess, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::lockout::{clear_throttle, release_attempt};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        clear_throttle(&account_key(&msg.email), &mut conn)?;
+        release_attempt(&ip_key(&msg.ip), &LockoutPolicy::ip(), &mut conn)
+    }
+}
+
+/// Takes back the attempt counted for the account and the address when the
+/// credentials were right but the login does not go through yet, like while
+/// the second factor is pending.
+#[derive(Debug, Clone)]
+pub struct ReleaseLoginAttempt {
+    pub email: String,
+    pub ip: String,
+}
+
+impl Message for ReleaseLoginAttempt {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<ReleaseLoginAttempt> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: ReleaseLoginAttempt, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::lockout::release_attempt;
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        release_attempt(
+            &account_key(&msg.email),
+            &LockoutPolicy::account(),
+            &mut conn,
+        )?;
+        release_attempt(&ip_key(&msg.ip), &LockoutPolicy::ip(), &mut conn)
+    }
+}
+
+/// Lifts an account lockout with the token from the lockout email.
+#[derive(Debug, Clone)]
+pub struct UnlockAccount {
+    pub token: String,
+}
+
+impl Message for UnlockAccount {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<UnlockAccount> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: UnlockAccount, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, lockout::unlock_by_token};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let account = unlock_by_token(&hash_token(&msg.token), &mut conn)?;
+        insert_audit_event(
+            AuditEvent::new(AuditAction::AccountUnlocked, &account, None),
+            &mut conn,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminUnlock {
+    pub email: String,
+    pub admin: Uuid,
+}
+
+impl Message for AdminUnlock {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<AdminUnlock> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: AdminUnlock, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, lockout::clear_throttle};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let account = account_key(&msg.email);
+        clear_throttle(&account, &mut conn)?;
+        insert_audit_event(
+            AuditEvent::new(AuditAction::AdminUnlocked, &account, Some(msg.admin)),
+            &mut conn,
+        )
+    }
+}
@@ -1,8 +1,23 @@
+pub mod api_token;
+pub mod audit;
 pub mod auth;
 pub mod core;
 pub mod error;
+pub mod history;
+pub mod jwks;
+pub mod lockout;
+pub mod oauth;
+pub mod oidc;
+pub mod password;
+pub mod profile;
+pub mod refresh;
+pub mod revocation;
+pub mod session;
+pub mod two_factor;
+pub mod verification;
 
 use aws_sdk_dynamodb::model::AttributeValue;
+use chrono::NaiveDateTime;
 use serde::Serialize;
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -28,6 +43,40 @@ pub struct TodoCardDb {
     pub owner: Uuid,
     pub tasks: Vec<TaskDb>,
     pub state: StateDb,
+    pub created_at: NaiveDateTime,
+    pub doing_at: Option<NaiveDateTime>,
+    pub done_at: Option<NaiveDateTime>,
+    pub transfers: Vec<TransferDb>,
+    pub collaborators: Vec<CollaboratorDb>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TransferDb {
+    pub from: Uuid,
+    pub to: Uuid,
+    pub at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum RoleDb {
+    Viewer,
+    Editor,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CollaboratorDb {
+    pub user: Uuid,
+    pub role: RoleDb,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TodoTemplateDb {
+    pub id: Uuid,
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub owner: Uuid,
+    pub tasks: Vec<TaskDb>,
 }
 
 impl TodoCardDb {
@@ -43,6 +92,12 @@ impl std::fmt::Display for StateDb {
     }
 }
 
+impl std::fmt::Display for RoleDb {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 impl Into<HashMap<String, AttributeValue>> for TodoCardDb {
     fn into(self) -> HashMap<String, AttributeValue> {
         let mut todo_card = HashMap::new();
@@ -53,10 +108,71 @@ impl Into<HashMap<String, AttributeValue>> for TodoCardDb {
         todo_card.insert("state".to_string(), val!(S => self.state.to_string()));
         todo_card.insert("tasks".to_string(), 
             val!(L => self.tasks.into_iter().map(|t| t.to_db_val()).collect::<Vec<AttributeValue>>()));
+        todo_card.insert(
+            "created_at".to_string(),
+            val!(S => date_to_db(self.created_at)),
+        );
+        if let Some(doing_at) = self.doing_at {
+            todo_card.insert("doing_at".to_string(), val!(S => date_to_db(doing_at)));
+        }
+        if let Some(done_at) = self.done_at {
+            todo_card.insert("done_at".to_string(), val!(S => date_to_db(done_at)));
+        }
+        if !self.transfers.is_empty() {
+            todo_card.insert(
+                "transfers".to_string(),
+                val!(L => self.transfers.into_iter().map(|t| t.to_db_val()).collect::<Vec<AttributeValue>>()),
+            );
+        }
+        if !self.collaborators.is_empty() {
+            todo_card.insert(
+                "collaborators".to_string(),
+                val!(L => self.collaborators.into_iter().map(|c| c.to_db_val()).collect::<Vec<AttributeValue>>()),
+            );
+        }
         todo_card
     }
 }
 
+impl From<TodoTemplateDb> for HashMap<String, AttributeValue> {
+    fn from(template: TodoTemplateDb) -> Self {
+        let mut item = HashMap::new();
+        item.insert("id".to_string(), val!(S => template.id.to_string()));
+        item.insert("name".to_string(), val!(S => template.name));
+        item.insert("title".to_string(), val!(S => template.title));
+        item.insert("description".to_string(), val!(S => template.description));
+        item.insert("owner".to_string(), val!(S => template.owner.to_string()));
+        item.insert(
+            "tasks".to_string(),
+            val!(L => template.tasks.into_iter().map(|t| t.to_db_val()).collect::<Vec<AttributeValue>>()),
+        );
+        item
+    }
+}
+
+impl TransferDb {
+    pub fn to_db_val(self) -> AttributeValue {
+        let mut transfer_hash = HashMap::new();
+        transfer_hash.insert("from".to_string(), val!(S => self.from.to_string()));
+        transfer_hash.insert("to".to_string(), val!(S => self.to.to_string()));
+        transfer_hash.insert("at".to_string(), val!(S => date_to_db(self.at)));
+        val!(M => transfer_hash)
+    }
+}
+
+impl CollaboratorDb {
+    pub fn to_db_val(self) -> AttributeValue {
+        let mut collaborator_hash = HashMap::new();
+        collaborator_hash.insert("user".to_string(), val!(S => self.user.to_string()));
+        collaborator_hash.insert("role".to_string(), val!(S => self.role.to_string()));
+        val!(M => collaborator_hash)
+    }
+}
+
+pub fn date_to_db(date: NaiveDateTime) -> String {
+    date.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+}
+
 impl TaskDb {
     pub fn to_db_val(self) -> AttributeValue {
         let mut tasks_hash = HashMap::new();
@@ -0,0 +1,331 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::{
+        hashing::{generate_token, hash_token},
+        oauth::{generate_client_id, verify_pkce, OAUTH_CODE_SECS},
+    },
+    db::helpers::DbExecutor,
+    model::{auth::User, error::DbError},
+};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = oauth_client)]
+pub struct OAuthClient {
+    pub id: String,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub redirect_uris: String,
+    pub secret_hash: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl OAuthClient {
+    /// Confidential clients get a secret, returned in clear only here. Public
+    /// clients such as a CLI rely on PKCE alone.
+    pub fn register(
+        owner_id: Uuid,
+        name: &str,
+        redirect_uris: &[String],
+        confidential: bool,
+    ) -> (Self, Option<String>) {
+        let secret = confidential.then(generate_token);
+        let client = Self {
+            id: generate_client_id(),
+            owner_id,
+            name: name.trim().to_string(),
+            redirect_uris: redirect_uris.join(" "),
+            secret_hash: secret.as_deref().map(hash_token),
+            created_at: Utc::now().naive_utc(),
+        };
+        (client, secret)
+    }
+
+    pub fn redirect_uri_list(&self) -> Vec<String> {
+        self.redirect_uris
+            .split_whitespace()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Redirect URIs are compared exactly, never by prefix.
+    pub fn allows_redirect(&self, uri: &str) -> bool {
+        self.redirect_uris
+            .split_whitespace()
+            .any(|known| known == uri)
+    }
+
+    pub fn is_confidential(&self) -> bool {
+        self.secret_hash.is_some()
+    }
+
+    pub fn authenticate(&self, secret: Option<&str>) -> bool {
+        match (&self.secret_hash, secret) {
+            (None, _) => true,
+            (Some(hash), Some(secret)) => *hash == hash_token(secret),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = oauth_code)]
+pub struct OAuthCode {
+    pub code_hash: String,
+    pub client_id: String,
+    pub user_id: Uuid,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub code_challenge: String,
+    pub expires_at: NaiveDateTime,
+}
+
+impl OAuthCode {
+    pub fn issue(msg: IssueAuthorizationCode) -> (Self, String) {
+        let code = generate_token();
+        let row = Self {
+            code_hash: hash_token(&code),
+            client_id: msg.client_id,
+            user_id: msg.user_id,
+            redirect_uri: msg.redirect_uri,
+            scope: msg.scope,
+            code_challenge: msg.code_challenge,
+            expires_at: Utc::now().naive_utc() + Duration::seconds(OAUTH_CODE_SECS),
+        };
+        (row, code)
+    }
+
+    /// The code must be redeemed by the client and redirect URI it was issued
+    /// for, with the verifier matching its PKCE challenge.
+    pub fn check(&self, msg: &RedeemAuthorizationCode, now: NaiveDateTime) -> bool {
+        self.expires_at > now
+            && self.client_id == msg.client_id
+            && self.redirect_uri == msg.redirect_uri
+            && verify_pkce(&msg.code_verifier, &self.code_challenge)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RegisterClient {
+    pub owner_id: Uuid,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+}
+
+impl Message for RegisterClient {
+    type Result = Result<(OAuthClient, Option<String>), DbError>;
+}
+
+impl Handler<RegisterClient> for DbExecutor {
+    type Result = Result<(OAuthClient, Option<String>), DbError>;
+
+    fn handle(&mut self, msg: RegisterClient, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::oauth::insert_client;
+
+        let (client, secret) = OAuthClient::register(
+            msg.owner_id,
+            &msg.name,
+            &msg.redirect_uris,
+            msg.confidential,
+        );
+        insert_client(
+            client.clone(),
+            &mut self.0.get().expect("Failed to open connection"),
+        )?;
+        Ok((client, secret))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadClients {
+    pub owner_id: Uuid,
+}
+
+impl Message for ReadClients {
+    type Result = Result<Vec<OAuthClient>, DbError>;
+}
+
+impl Handler<ReadClients> for DbExecutor {
+    type Result = Result<Vec<OAuthClient>, DbError>;
+
+    fn handle(&mut self, msg: ReadClients, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::oauth::scan_clients;
+
+        scan_clients(
+            msg.owner_id,
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadClient {
+    pub id: String,
+}
+
+impl Message for ReadClient {
+    type Result = Result<Option<OAuthClient>, DbError>;
+}
+
+impl Handler<ReadClient> for DbExecutor {
+    type Result = Result<Option<OAuthClient>, DbError>;
+
+    fn handle(&mut self, msg: ReadClient, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::oauth::scan_client;
+
+        scan_client(
+            &msg.id,
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+/// Deletes a client with its pending codes and revokes the sessions granted
+/// to it.
+#[derive(Debug, Clone)]
+pub struct DeleteClient {
+    pub id: String,
+    pub owner_id: Uuid,
+}
+
+impl Message for DeleteClient {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<DeleteClient> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: DeleteClient, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::oauth::delete_client;
+
+        delete_client(msg, &mut self.0.get().expect("Failed to open connection"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IssueAuthorizationCode {
+    pub client_id: String,
+    pub user_id: Uuid,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub code_challenge: String,
+}
+
+impl Message for IssueAuthorizationCode {
+    type Result = Result<String, DbError>;
+}
+
+impl Handler<IssueAuthorizationCode> for DbExecutor {
+    type Result = Result<String, DbError>;
+
+    fn handle(&mut self, msg: IssueAuthorizationCode, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::oauth::insert_code;
+
+        let (row, code) = OAuthCode::issue(msg);
+        insert_code(row, &mut self.0.get().expect("Failed to open connection"))?;
+        Ok(code)
+    }
+}
+
+/// Consumes an authorization code, returns its user and granted scope.
+#[derive(Debug, Clone)]
+pub struct RedeemAuthorizationCode {
+    pub code: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub code_verifier: String,
+}
+
+impl Message for RedeemAuthorizationCode {
+    type Result = Result<(User, String), DbError>;
+}
+
+impl Handler<RedeemAuthorizationCode> for DbExecutor {
+    type Result = Result<(User, String), DbError>;
+
+    fn handle(&mut self, msg: RedeemAuthorizationCode, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::oauth::take_code;
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let (code, user) = take_code(&hash_token(&msg.code), &mut conn)?;
+        match code.check(&msg, Utc::now().naive_utc()) {
+            true => Ok((user, code.scope)),
+            false => Err(DbError::InvalidGrant),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todo_api::core::oauth::pkce_challenge;
+
+    static VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+    fn code() -> (OAuthCode, RedeemAuthorizationCode) {
+        let (row, code) = OAuthCode::issue(IssueAuthorizationCode {
+            client_id: "tdc_cli".to_string(),
+            user_id: Uuid::new_v4(),
+            redirect_uri: "http://127.0.0.1:8123/cb".to_string(),
+            scope: "todo:read".to_string(),
+            code_challenge: pkce_challenge(VERIFIER),
+        });
+        let redeem = RedeemAuthorizationCode {
+            code,
+            client_id: "tdc_cli".to_string(),
+            redirect_uri: "http://127.0.0.1:8123/cb".to_string(),
+            code_verifier: VERIFIER.to_string(),
+        };
+        (row, redeem)
+    }
+
+    #[test]
+    fn code_is_bound_to_client_redirect_and_verifier() {
+        let (row, redeem) = code();
+        let now = Utc::now().naive_utc();
+        assert!(row.check(&redeem, now));
+
+        let other_client = RedeemAuthorizationCode {
+            client_id: "tdc_other".to_string(),
+            ..redeem.clone()
+        };
+        let other_redirect = RedeemAuthorizationCode {
+            redirect_uri: "http://127.0.0.1:9000/cb".to_string(),
+            ..redeem.clone()
+        };
+        let other_verifier = RedeemAuthorizationCode {
+            code_verifier: "x".repeat(43),
+            ..redeem.clone()
+        };
+        assert!(!row.check(&other_client, now));
+        assert!(!row.check(&other_redirect, now));
+        assert!(!row.check(&other_verifier, now));
+        assert!(!row.check(&redeem, row.expires_at));
+    }
+
+    #[test]
+    fn public_clients_have_no_secret() {
+        let uris = vec!["http://127.0.0.1:8123/cb".to_string()];
+        let (public, secret) = OAuthClient::register(Uuid::new_v4(), "cli", &uris, false);
+
+        assert!(secret.is_none());
+        assert!(!public.is_confidential());
+        assert!(public.authenticate(None));
+        assert!(public.allows_redirect("http://127.0.0.1:8123/cb"));
+        assert!(!public.allows_redirect("http://127.0.0.1:8123/cb/other"));
+    }
+
+    #[test]
+    fn confidential_clients_need_their_secret() {
+        let uris = vec!["https://app.example.com/cb".to_string()];
+        let (client, secret) = OAuthClient::register(Uuid::new_v4(), "app", &uris, true);
+
+        assert!(client.is_confidential());
+        assert!(client.authenticate(secret.as_deref()));
+        assert!(!client.authenticate(Some("wrong")));
+        assert!(!client.authenticate(None));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::hashing::{generate_token, hash_password, hash_token},
+    db::helpers::DbExecutor,
+    model::{
+        audit::{AuditAction, AuditEvent},
+        auth::User,
+        error::DbError,
+    },
+    oidc::OIDC_LOGIN_SECS,
+};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+/// Pending login with the OpenID provider. The state goes to the browser and
+/// only its hash is stored, next to the nonce and PKCE verifier it binds.
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = oidc_login)]
+pub struct OidcLogin {
+    pub state_hash: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub expires_at: NaiveDateTime,
+}
+
+impl OidcLogin {
+    pub fn start() -> (Self, String) {
+        let state = generate_token();
+        let login = Self {
+            state_hash: hash_token(&state),
+            nonce: generate_token(),
+            code_verifier: generate_token(),
+            expires_at: Utc::now().naive_utc() + Duration::seconds(OIDC_LOGIN_SECS),
+        };
+        (login, state)
+    }
+
+    pub fn is_valid(&self, now: NaiveDateTime) -> bool {
+        self.expires_at > now
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = external_identity)]
+pub struct ExternalIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub user_id: Uuid,
+    pub email: String,
+    pub created_at: NaiveDateTime,
+    pub last_login_at: NaiveDateTime,
+}
+
+impl ExternalIdentity {
+    pub fn link(msg: &LinkExternalIdentity, user_id: Uuid) -> Self {
+        let now = Utc::now().naive_utc();
+        Self {
+            issuer: msg.issuer.clone(),
+            subject: msg.subject.clone(),
+            user_id,
+            email: msg.email.clone(),
+            created_at: now,
+            last_login_at: now,
+        }
+    }
+}
+
+/// Users created on their first external login get a random password, they
+/// can still set one through a password reset.
+pub fn external_user(email: &str) -> User {
+    let mut user = User::from(email.to_string(), hash_password(&generate_token()));
+    user.email_verified = true;
+    user
+}
+
+/// Local account a new external identity is linked to.
+#[derive(Debug, Clone)]
+pub enum LinkTarget {
+    Existing(User),
+    /// Anyone knowing the address could have registered an unverified account
+    /// with a password of their choosing, so linking replaces its password and
+    /// signs out everything that was authenticated with it.
+    Reclaimed(User),
+    Created(User),
+}
+
+pub fn link_target(existing: Option<User>, email: &str) -> LinkTarget {
+    match existing {
+        Some(user) if user.email_verified => LinkTarget::Existing(user),
+        Some(user) => LinkTarget::Reclaimed(user),
+        None => LinkTarget::Created(external_user(email)),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StartOidcLogin;
+
+impl Message for StartOidcLogin {
+    type Result = Result<(OidcLogin, String), DbError>;
+}
+
+impl Handler<StartOidcLogin> for DbExecutor {
+    type Result = Result<(OidcLogin, String), DbError>;
+
+    fn handle(&mut self, _: StartOidcLogin, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::oidc::insert_oidc_login;
+
+        let (login, state) = OidcLogin::start();
+        insert_oidc_login(
+            login.clone(),
+            &mut self.0.get().expect("Failed to open connection"),
+        )?;
+        Ok((login, state))
+    }
+}
+
+/// Consumes the login state returned by the provider.
+#[derive(Debug, Clone)]
+pub struct FinishOidcLogin {
+    pub state: String,
+}
+
+impl Message for FinishOidcLogin {
+    type Result = Result<OidcLogin, DbError>;
+}
+
+impl Handler<FinishOidcLogin> for DbExecutor {
+    type Result = Result<OidcLogin, DbError>;
+
+    fn handle(&mut self, msg: FinishOidcLogin, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::oidc::take_oidc_login;
+
+        let login = take_oidc_login(
+            &hash_token(&msg.state),
+            &mut self.0.get().expect("Failed to open connection"),
+        )?;
+        match login.is_valid(Utc::now().naive_utc()) {
+            true => Ok(login),
+            false => Err(DbError::InvalidOidcState),
+        }
+    }
+}
+
+/// Finds the user of an external identity. Unknown identities are linked to
+/// the user with the same email, created if needed, but only when the provider
+/// verified that email. See `LinkTarget` for unverified local accounts.
+#[derive(Debug, Clone)]
+pub struct LinkExternalIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+
+impl Message for LinkExternalIdentity {
+    type Result = Result<User, DbError>;
+}
+
+impl Handler<LinkExternalIdentity> for DbExecutor {
+    type Result = Result<User, DbError>;
+
+    fn handle(&mut self, msg: LinkExternalIdentity, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, oidc::link_external_identity};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let (user, linked) = link_external_identity(&msg, &mut conn)?;
+        if linked {
+            insert_audit_event(
+                AuditEvent::new(
+                    AuditAction::ExternalIdentityLinked,
+                    &msg.issuer,
+                    Some(user.id),
+                ),
+                &mut conn,
+            )?;
+        }
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::todo_api::core::oauth::is_code_verifier_valid;
+
+    #[test]
+    fn login_stores_only_the_state_hash() {
+        let (login, state) = OidcLogin::start();
+
+        assert_eq!(login.state_hash, hash_token(&state));
+        assert_ne!(login.nonce, state);
+        assert!(is_code_verifier_valid(&login.code_verifier));
+        assert!(login.is_valid(Utc::now().naive_utc()));
+        assert!(!login.is_valid(login.expires_at));
+    }
+
+    #[test]
+    fn external_users_are_verified() {
+        let user = external_user("sso@email.com");
+
+        assert!(user.email_verified);
+        assert_eq!(user.email, "sso@email.com");
+    }
+
+    #[test]
+    fn unverified_local_accounts_are_reclaimed() {
+        let mut local = User::from(String::from("sso@email.com"), String::from("attacker hash"));
+
+        assert!(matches!(
+            link_target(Some(local.clone()), &local.email),
+            LinkTarget::Reclaimed(user) if user.id == local.id
+        ));
+        local.email_verified = true;
+        assert!(matches!(
+            link_target(Some(local.clone()), &local.email),
+            LinkTarget::Existing(user) if user.id == local.id
+        ));
+        assert!(matches!(
+            link_target(None, "sso@email.com"),
+            LinkTarget::Created(user) if user.email_verified
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::{
+        hashing::{generate_token, hash_password, hash_token},
+        lockout::{account_key, ip_key, reset_key, throttle_expiry, LockoutPolicy},
+        password::password_reset_secs,
+    },
+    db::helpers::DbExecutor,
+    model::error::DbError,
+};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = password_reset)]
+pub struct PasswordReset {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+impl PasswordReset {
+    /// Creates the stored row and returns it with the raw token for the email.
+    pub fn issue(user_id: Uuid) -> (Self, String) {
+        let token = generate_token();
+        let now = Utc::now().naive_utc();
+        let row = Self {
+            id: Uuid::new_v4(),
+            user_id,
+            token_hash: hash_token(&token),
+            created_at: now,
+            expires_at: now + Duration::seconds(password_reset_secs()),
+            used_at: None,
+        };
+        (row, token)
+    }
+
+    pub fn check(&self, now: NaiveDateTime) -> Result<(), DbError> {
+        if self.used_at.is_some() || self.expires_at <= now {
+            Err(DbError::InvalidResetToken)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Issues a reset token for the account, replacing any pending one. Unknown
+/// emails resolve to `None` so callers can answer the same way for both.
+#[derive(Debug, Clone)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+impl Message for ForgotPassword {
+    type Result = Result<Option<String>, DbError>;
+}
+
+impl Handler<ForgotPassword> for DbExecutor {
+    type Result = Result<Option<String>, DbError>;
+
+    fn handle(&mut self, msg: ForgotPassword, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{auth::scan_user, password::replace_password_reset};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let user = match scan_user(msg.email, &mut conn) {
+            Err(DbError::CannotFindUser) => return Ok(None),
+            Err(e) => return Err(e),
+            Ok(user) => user,
+        };
+
+        let (row, token) = PasswordReset::issue(user.id);
+        replace_password_reset(row, &mut conn)?;
+        Ok(Some(token))
+    }
+}
+
+/// Counts a reset request for the email and the address, resolves to the
+/// time the next one is allowed when either is throttled.
+#[derive(Debug, Clone)]
+pub struct ThrottlePasswordReset {
+    pub email: String,
+    pub ip: String,
+}
+
+impl Message for ThrottlePasswordReset {
+    type Result = Result<Option<NaiveDateTime>, DbError>;
+}
+
+impl Handler<ThrottlePasswordReset> for DbExecutor {
+    type Result = Result<Option<NaiveDateTime>, DbError>;
+
+    fn handle(&mut self, msg: ThrottlePasswordReset, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::lockout::{count_attempt, purge_throttles};
+        use crate::todo_api::model::lockout::LoginAttempt;
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let now = Utc::now().naive_utc();
+        purge_throttles(throttle_expiry(now), now, &mut conn)?;
+        let keys = [
+            (
+                reset_key(&account_key(&msg.email)),
+                LockoutPolicy::password_reset(),
+            ),
+            (reset_key(&ip_key(&msg.ip)), LockoutPolicy::ip()),
+        ];
+        match count_attempt(&keys, now, &mut conn)? {
+            LoginAttempt::Throttled(retry_at) => Ok(Some(retry_at)),
+            LoginAttempt::Counted(_) => Ok(None),
+        }
+    }
+}
+
+/// Consumes a reset token, sets the new password and signs the user out of
+/// every session.
+#[derive(Debug, Clone)]
+pub struct ResetPassword {
+    pub token: String,
+    pub password: String,
+}
+
+impl Message for ResetPassword {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<ResetPassword> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: ResetPassword, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::password::reset_password;
+
+        reset_password(
+            &hash_token(&msg.token),
+            hash_password(&msg.password),
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fresh_reset_is_usable() {
+        let (row, token) = PasswordReset::issue(Uuid::new_v4());
+
+        assert_eq!(row.check(Utc::now().naive_utc()), Ok(()));
+        assert_eq!(row.token_hash, hash_token(&token));
+    }
+
+    #[test]
+    fn used_or_expired_reset_is_invalid() {
+        let (mut row, _) = PasswordReset::issue(Uuid::new_v4());
+
+        assert_eq!(row.check(row.expires_at), Err(DbError::InvalidResetToken));
+        row.used_at = Some(Utc::now().naive_utc());
+        assert_eq!(
+            row.check(Utc::now().naive_utc()),
+            Err(DbError::InvalidResetToken)
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use crate::todo_api::{
+    core::hashing::hash_password,
+    db::helpers::DbExecutor,
+    model::{
+        audit::{AuditAction, AuditEvent},
+        auth::User,
+        error::DbError,
+        verification::EmailVerification,
+    },
+};
+use actix::prelude::*;
+use chrono::Utc;
+use uuid::Uuid;
+
+/// Replaces the display name and timezone, `None` clears the display name.
+#[derive(Debug, Clone)]
+pub struct UpdateProfile {
+    pub user_id: Uuid,
+    pub display_name: Option<String>,
+    pub timezone: String,
+}
+
+impl Message for UpdateProfile {
+    type Result = Result<User, DbError>;
+}
+
+impl Handler<UpdateProfile> for DbExecutor {
+    type Result = Result<User, DbError>;
+
+    fn handle(&mut self, msg: UpdateProfile, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::profile::update_profile;
+
+        update_profile(msg, &mut self.0.get().expect("Failed to open connection"))
+    }
+}
+
+/// Issues a token for the new address, the account email only changes once
+/// it is redeemed through `/auth/email/verify`. Shares the resend throttle of
+/// verification emails.
+#[derive(Debug, Clone)]
+pub struct RequestEmailChange {
+    pub user_id: Uuid,
+    pub email: String,
+}
+
+impl Message for RequestEmailChange {
+    type Result = Result<String, DbError>;
+}
+
+impl Handler<RequestEmailChange> for DbExecutor {
+    type Result = Result<String, DbError>;
+
+    fn handle(&mut self, msg: RequestEmailChange, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{auth::scan_user, verification::insert_resent_verification};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        match scan_user(msg.email.clone(), &mut conn) {
+            Err(DbError::CannotFindUser) => (),
+            Err(e) => return Err(e),
+            Ok(_) => return Err(DbError::EmailTaken),
+        }
+
+        let (row, token) = EmailVerification::issue_for(msg.user_id, Some(msg.email));
+        insert_resent_verification(row, Utc::now().naive_utc(), &mut conn)?;
+        Ok(token)
+    }
+}
+
+/// Sets a new password, already checked against the current one, and revokes
+/// every session but `keep_session`.
+#[derive(Debug, Clone)]
+pub struct ChangePassword {
+    pub user_id: Uuid,
+    pub password: String,
+    pub keep_session: Option<Uuid>,
+}
+
+impl Message for ChangePassword {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<ChangePassword> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: ChangePassword, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, profile::change_password};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        change_password(
+            msg.user_id,
+            &hash_password(&msg.password),
+            msg.keep_session,
+            &mut conn,
+        )?;
+        insert_audit_event(
+            AuditEvent::new(
+                AuditAction::PasswordChanged,
+                &msg.user_id.to_string(),
+                Some(msg.user_id),
+            ),
+            &mut conn,
+        )
+    }
+}
@@ -0,0 +1,182 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::{
+        hashing::{generate_token, hash_token},
+        refresh::refresh_token_secs,
+    },
+    db::helpers::DbExecutor,
+    model::{auth::User, error::DbError},
+};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = refresh_token)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub family: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub issued_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked: bool,
+    pub session_id: Option<Uuid>,
+}
+
+impl RefreshToken {
+    /// Creates the stored row and returns it with the raw token for the client.
+    pub fn issue(user_id: Uuid, session_id: Option<Uuid>, family: Option<Uuid>) -> (Self, String) {
+        let token = generate_token();
+        let now = Utc::now().naive_utc();
+        let row = Self {
+            id: Uuid::new_v4(),
+            family: family.unwrap_or_else(Uuid::new_v4),
+            user_id,
+            token_hash: hash_token(&token),
+            issued_at: now,
+            expires_at: now + Duration::seconds(refresh_token_secs()),
+            used_at: None,
+            revoked: false,
+            session_id,
+        };
+        (row, token)
+    }
+
+    /// A token that was already used signals a replay.
+    pub fn check(&self, now: NaiveDateTime) -> Result<(), DbError> {
+        if self.used_at.is_some() {
+            Err(DbError::RefreshTokenReused)
+        } else if self.revoked || self.expires_at <= now {
+            Err(DbError::InvalidRefreshToken)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IssueRefreshToken {
+    pub user_id: Uuid,
+    pub session_id: Option<Uuid>,
+}
+
+impl Message for IssueRefreshToken {
+    type Result = Result<String, DbError>;
+}
+
+impl Handler<IssueRefreshToken> for DbExecutor {
+    type Result = Result<String, DbError>;
+
+    fn handle(&mut self, msg: IssueRefreshToken, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::refresh::insert_refresh_token;
+
+        let (row, token) = RefreshToken::issue(msg.user_id, msg.session_id, None);
+        insert_refresh_token(row, &mut self.0.get().expect("Failed to open connection"))?;
+        Ok(token)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RotatedRefreshToken {
+    pub user: User,
+    pub session_id: Option<Uuid>,
+    pub token: String,
+    /// Scope of the session when it was granted to an OAuth client.
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RotateRefreshToken {
+    pub token: String,
+}
+
+impl Message for RotateRefreshToken {
+    type Result = Result<RotatedRefreshToken, DbError>;
+}
+
+impl Handler<RotateRefreshToken> for DbExecutor {
+    type Result = Result<RotatedRefreshToken, DbError>;
+
+    fn handle(&mut self, msg: RotateRefreshToken, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::refresh::rotate_refresh_token;
+
+        rotate_refresh_token(
+            &hash_token(&msg.token),
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RevokeRefreshTokens {
+    pub user_id: Uuid,
+}
+
+impl Message for RevokeRefreshTokens {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<RevokeRefreshTokens> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: RevokeRefreshTokens, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::refresh::revoke_user_refresh_tokens;
+
+        revoke_user_refresh_tokens(
+            msg.user_id,
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fresh_token_is_usable() {
+        let (row, _) = RefreshToken::issue(Uuid::new_v4(), None, None);
+
+        assert_eq!(row.check(Utc::now().naive_utc()), Ok(()));
+    }
+
+    #[test]
+    fn used_token_is_reuse() {
+        let (mut row, _) = RefreshToken::issue(Uuid::new_v4(), None, None);
+        row.used_at = Some(Utc::now().naive_utc());
+
+        assert_eq!(
+            row.check(Utc::now().naive_utc()),
+            Err(DbError::RefreshTokenReused)
+        );
+    }
+
+    #[test]
+    fn revoked_token_is_invalid() {
+        let (mut row, _) = RefreshToken::issue(Uuid::new_v4(), None, None);
+        row.revoked = true;
+
+        assert_eq!(
+            row.check(Utc::now().naive_utc()),
+            Err(DbError::InvalidRefreshToken)
+        );
+    }
+
+    #[test]
+    fn expired_token_is_invalid() {
+        let (row, _) = RefreshToken::issue(Uuid::new_v4(), None, None);
+
+        assert_eq!(row.check(row.expires_at), Err(DbError::InvalidRefreshToken));
+    }
+
+    #[test]
+    fn rotated_token_stays_in_family() {
+        let (first, _) = RefreshToken::issue(Uuid::new_v4(), None, None);
+        let (second, _) = RefreshToken::issue(first.user_id, None, Some(first.family));
+
+        assert_eq!(first.family, second.family);
+        assert_ne!(first.token_hash, second.token_hash);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::schema::*;
+use crate::todo_api::{db::helpers::DbExecutor, model::error::DbError};
+use actix::prelude::*;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = revoked_token)]
+pub struct RevokedToken {
+    pub jti: Uuid,
+    pub user_id: Uuid,
+    pub revoked_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct RevokeToken(pub RevokedToken);
+
+impl Message for RevokeToken {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<RevokeToken> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: RevokeToken, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::revocation::insert_revoked_token;
+
+        insert_revoked_token(msg.0, &mut self.0.get().expect("Failed to open connection"))
+    }
+}
+
+/// Drops expired revocations and reads the ones made since the last sync.
+#[derive(Debug, Clone)]
+pub struct SyncRevokedTokens {
+    pub since: Option<NaiveDateTime>,
+}
+
+impl Message for SyncRevokedTokens {
+    type Result = Result<Vec<RevokedToken>, DbError>;
+}
+
+impl Handler<SyncRevokedTokens> for DbExecutor {
+    type Result = Result<Vec<RevokedToken>, DbError>;
+
+    fn handle(&mut self, msg: SyncRevokedTokens, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::revocation::{purge_revoked_tokens, scan_revoked_tokens};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        purge_revoked_tokens(&mut conn)?;
+        scan_revoked_tokens(msg.since, &mut conn)
+    }
+}
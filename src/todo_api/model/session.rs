@@ -0,0 +1,188 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::{hashing::hash_token, refresh::refresh_token_secs},
+    db::helpers::DbExecutor,
+    model::error::DbError,
+};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub static MAX_DEVICE_LEN: usize = 128;
+pub static SESSION_TOUCH_SECS: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Queryable, Insertable)]
+#[diesel(table_name = session)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub device: String,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub revoked: bool,
+    /// Scopes and client of sessions granted to an OAuth client.
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+}
+
+impl Session {
+    pub fn new(user_id: Uuid, device: &str) -> Self {
+        let now = Utc::now().naive_utc();
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            device: device.chars().take(MAX_DEVICE_LEN).collect(),
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now + Duration::seconds(refresh_token_secs()),
+            revoked: false,
+            scope: None,
+            client_id: None,
+        }
+    }
+
+    pub fn is_valid(&self, user: Uuid, now: NaiveDateTime) -> bool {
+        !self.revoked && self.user_id == user && self.expires_at > now
+    }
+
+    /// Avoids writing `last_seen_at` on every authenticated request.
+    pub fn needs_touch(&self, now: NaiveDateTime) -> bool {
+        now - self.last_seen_at >= Duration::seconds(SESSION_TOUCH_SECS)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateSession {
+    pub user_id: Uuid,
+    pub device: String,
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+}
+
+impl Message for CreateSession {
+    type Result = Result<Session, DbError>;
+}
+
+impl Handler<CreateSession> for DbExecutor {
+    type Result = Result<Session, DbError>;
+
+    fn handle(&mut self, msg: CreateSession, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::session::insert_session;
+
+        let session = Session {
+            scope: msg.scope,
+            client_id: msg.client_id,
+            ..Session::new(msg.user_id, &msg.device)
+        };
+        insert_session(
+            session.clone(),
+            &mut self.0.get().expect("Failed to open connection"),
+        )?;
+        Ok(session)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadSessions {
+    pub user_id: Uuid,
+}
+
+impl Message for ReadSessions {
+    type Result = Result<Vec<Session>, DbError>;
+}
+
+impl Handler<ReadSessions> for DbExecutor {
+    type Result = Result<Vec<Session>, DbError>;
+
+    fn handle(&mut self, msg: ReadSessions, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::session::scan_sessions;
+
+        scan_sessions(
+            msg.user_id,
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+/// Finds a session by id, or by a refresh token issued to it.
+#[derive(Debug, Clone)]
+pub enum ReadSession {
+    Id(Uuid),
+    RefreshToken(String),
+}
+
+impl Message for ReadSession {
+    type Result = Result<Option<Session>, DbError>;
+}
+
+impl Handler<ReadSession> for DbExecutor {
+    type Result = Result<Option<Session>, DbError>;
+
+    fn handle(&mut self, msg: ReadSession, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::session::{scan_refresh_session, scan_session};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        match msg {
+            ReadSession::Id(id) => scan_session(id, &mut conn),
+            ReadSession::RefreshToken(token) => {
+                scan_refresh_session(&hash_token(&token), &mut conn)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RevokeSession {
+    pub id: Uuid,
+    pub user_id: Uuid,
+}
+
+impl Message for RevokeSession {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<RevokeSession> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: RevokeSession, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::session::revoke_session;
+
+        revoke_session(msg, &mut self.0.get().expect("Failed to open connection"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn session_belongs_to_its_user() {
+        let user = Uuid::new_v4();
+        let session = Session::new(user, "laptop");
+        let now = Utc::now().naive_utc();
+
+        assert!(session.is_valid(user, now));
+        assert!(!session.is_valid(Uuid::new_v4(), now));
+        assert!(!session.is_valid(user, session.expires_at));
+    }
+
+    #[test]
+    fn revoked_session_is_invalid() {
+        let user = Uuid::new_v4();
+        let mut session = Session::new(user, "phone");
+        session.revoked = true;
+
+        assert!(!session.is_valid(user, Utc::now().naive_utc()));
+    }
+
+    #[test]
+    fn touches_only_stale_sessions() {
+        let session = Session::new(Uuid::new_v4(), &"x".repeat(200));
+
+        assert_eq!(session.device.len(), MAX_DEVICE_LEN);
+        assert!(!session.needs_touch(session.last_seen_at));
+        assert!(session.needs_touch(session.last_seen_at + Duration::minutes(5)));
+    }
+}
@@ -0,0 +1,279 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::{
+        hashing::hash_token,
+        keys::totp_key,
+        totp::{
+            generate_recovery_codes, generate_secret, normalize_recovery_code, open_secret,
+            seal_secret,
+        },
+    },
+    db::helpers::DbExecutor,
+    model::{
+        audit::{AuditAction, AuditEvent},
+        error::DbError,
+        revocation::RevokedToken,
+    },
+};
+use actix::prelude::*;
+use chrono::{NaiveDateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = two_factor)]
+pub struct TwoFactor {
+    pub user_id: Uuid,
+    pub secret: String,
+    pub enabled: bool,
+    pub created_at: NaiveDateTime,
+    pub enabled_at: Option<NaiveDateTime>,
+    pub last_used_step: Option<i64>,
+}
+
+impl TwoFactor {
+    /// Pending enrollment, enabled once a first code is confirmed. Returns the
+    /// row, holding the sealed secret, with the secret in clear for the user.
+    pub fn new(user_id: Uuid) -> (Self, String) {
+        let secret = generate_secret();
+        let row = Self {
+            user_id,
+            secret: seal_secret(totp_key(), &secret),
+            enabled: false,
+            created_at: Utc::now().naive_utc(),
+            enabled_at: None,
+            last_used_step: None,
+        };
+        (row, secret)
+    }
+
+    pub fn totp_secret(&self) -> Option<String> {
+        open_secret(totp_key(), &self.secret)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = recovery_code)]
+pub struct RecoveryCode {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+impl RecoveryCode {
+    /// Creates the stored rows and returns them with the raw codes for the user.
+    pub fn issue_all(user_id: Uuid) -> (Vec<Self>, Vec<String>) {
+        let codes = generate_recovery_codes();
+        let rows = codes
+            .iter()
+            .map(|code| Self {
+                id: Uuid::new_v4(),
+                user_id,
+                code_hash: hash_recovery_code(code),
+                used_at: None,
+            })
+            .collect();
+        (rows, codes)
+    }
+}
+
+pub fn hash_recovery_code(code: &str) -> String {
+    hash_token(&normalize_recovery_code(code))
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadTwoFactor {
+    pub user_id: Uuid,
+}
+
+impl Message for ReadTwoFactor {
+    type Result = Result<Option<TwoFactor>, DbError>;
+}
+
+impl Handler<ReadTwoFactor> for DbExecutor {
+    type Result = Result<Option<TwoFactor>, DbError>;
+
+    fn handle(&mut self, msg: ReadTwoFactor, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::two_factor::scan_two_factor;
+
+        scan_two_factor(
+            msg.user_id,
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+/// Starts or restarts an enrollment and returns the new secret.
+#[derive(Debug, Clone)]
+pub struct StartEnrollment {
+    pub user_id: Uuid,
+}
+
+impl Message for StartEnrollment {
+    type Result = Result<String, DbError>;
+}
+
+impl Handler<StartEnrollment> for DbExecutor {
+    type Result = Result<String, DbError>;
+
+    fn handle(&mut self, msg: StartEnrollment, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::two_factor::replace_pending_two_factor;
+
+        let (pending, secret) = TwoFactor::new(msg.user_id);
+        replace_pending_two_factor(
+            pending,
+            &mut self.0.get().expect("Failed to open connection"),
+        )?;
+        Ok(secret)
+    }
+}
+
+/// Enables two-factor with a first valid code and returns fresh recovery codes.
+#[derive(Debug, Clone)]
+pub struct ConfirmEnrollment {
+    pub user_id: Uuid,
+    pub code: String,
+}
+
+impl Message for ConfirmEnrollment {
+    type Result = Result<Vec<String>, DbError>;
+}
+
+impl Handler<ConfirmEnrollment> for DbExecutor {
+    type Result = Result<Vec<String>, DbError>;
+
+    fn handle(&mut self, msg: ConfirmEnrollment, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, two_factor::confirm_two_factor};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let codes = confirm_two_factor(msg.user_id, &msg.code, &mut conn)?;
+        insert_audit_event(
+            AuditEvent::new(
+                AuditAction::TwoFactorEnabled,
+                &msg.user_id.to_string(),
+                Some(msg.user_id),
+            ),
+            &mut conn,
+        )?;
+        Ok(codes)
+    }
+}
+
+/// Checks a TOTP code, or consumes a recovery code, for an enabled account.
+#[derive(Debug, Clone)]
+pub struct VerifySecondFactor {
+    pub user_id: Uuid,
+    pub code: String,
+}
+
+impl Message for VerifySecondFactor {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<VerifySecondFactor> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: VerifySecondFactor, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, two_factor::verify_second_factor};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        if !verify_second_factor(msg.user_id, &msg.code, &mut conn)? {
+            return Ok(());
+        }
+        insert_audit_event(
+            AuditEvent::new(
+                AuditAction::RecoveryCodeUsed,
+                &msg.user_id.to_string(),
+                Some(msg.user_id),
+            ),
+            &mut conn,
+        )
+    }
+}
+
+/// Spends the challenge of a login, so it is exchanged for one token only.
+#[derive(Debug, Clone)]
+pub struct ConsumeChallenge {
+    pub jti: Uuid,
+    pub user_id: Uuid,
+    pub expires_at: NaiveDateTime,
+}
+
+impl Message for ConsumeChallenge {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<ConsumeChallenge> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: ConsumeChallenge, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::revocation::consume_token;
+
+        consume_token(
+            RevokedToken {
+                jti: msg.jti,
+                user_id: msg.user_id,
+                revoked_at: Utc::now().naive_utc(),
+                expires_at: msg.expires_at,
+            },
+            &mut self.0.get().expect("Failed to open connection"),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DisableTwoFactor {
+    pub user_id: Uuid,
+}
+
+impl Message for DisableTwoFactor {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<DisableTwoFactor> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: DisableTwoFactor, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, two_factor::delete_two_factor};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        delete_two_factor(msg.user_id, &mut conn)?;
+        insert_audit_event(
+            AuditEvent::new(
+                AuditAction::TwoFactorDisabled,
+                &msg.user_id.to_string(),
+                Some(msg.user_id),
+            ),
+            &mut conn,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn totp_secrets_are_stored_sealed() {
+        let (row, secret) = TwoFactor::new(Uuid::new_v4());
+
+        assert_ne!(row.secret, secret);
+        assert_eq!(row.totp_secret(), Some(secret));
+    }
+
+    #[test]
+    fn recovery_codes_are_stored_hashed() {
+        let user = Uuid::new_v4();
+        let (rows, codes) = RecoveryCode::issue_all(user);
+
+        assert_eq!(rows.len(), codes.len());
+        assert_eq!(rows[0].code_hash, hash_recovery_code(&codes[0]));
+        assert_eq!(
+            rows[0].code_hash,
+            hash_recovery_code(&format!(" {} ", codes[0].to_uppercase()))
+        );
+        assert!(rows
+            .iter()
+            .all(|row| row.user_id == user && row.used_at.is_none()));
+    }
+}
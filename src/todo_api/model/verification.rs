@@ -0,0 +1,178 @@
+use crate::schema::*;
+use crate::todo_api::{
+    core::{
+        hashing::{generate_token, hash_token},
+        verification::{verification_resend_secs, verification_secs, MAX_VERIFICATIONS_PER_HOUR},
+    },
+    db::helpers::DbExecutor,
+    model::{
+        audit::{AuditAction, AuditEvent},
+        error::DbError,
+    },
+};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = email_verification)]
+pub struct EmailVerification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub new_email: Option<String>,
+}
+
+impl EmailVerification {
+    /// Creates the stored row and returns it with the raw token for the email.
+    pub fn issue(user_id: Uuid) -> (Self, String) {
+        Self::issue_for(user_id, None)
+    }
+
+    /// Token confirming a change of address, sent to `new_email` which
+    /// replaces the account email once redeemed.
+    pub fn issue_for(user_id: Uuid, new_email: Option<String>) -> (Self, String) {
+        let token = generate_token();
+        let now = Utc::now().naive_utc();
+        let row = Self {
+            id: Uuid::new_v4(),
+            user_id,
+            token_hash: hash_token(&token),
+            created_at: now,
+            expires_at: now + Duration::seconds(verification_secs()),
+            used_at: None,
+            new_email,
+        };
+        (row, token)
+    }
+
+    pub fn check(&self, now: NaiveDateTime) -> Result<(), DbError> {
+        if self.used_at.is_some() || self.expires_at <= now {
+            Err(DbError::InvalidVerificationToken)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Allows one email per `VERIFICATION_RESEND_SECS` and at most
+/// `MAX_VERIFICATIONS_PER_HOUR`, given the send dates of the last hour.
+pub fn check_resend(sent: &[NaiveDateTime], now: NaiveDateTime) -> Result<(), DbError> {
+    let cooldown = now - Duration::seconds(verification_resend_secs());
+    if sent.len() >= MAX_VERIFICATIONS_PER_HOUR || sent.iter().any(|at| *at > cooldown) {
+        Err(DbError::VerificationThrottled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Issues a new verification token for an unverified account. Unknown and
+/// already verified emails resolve to `None`.
+#[derive(Debug, Clone)]
+pub struct ResendVerification {
+    pub email: String,
+}
+
+impl Message for ResendVerification {
+    type Result = Result<Option<String>, DbError>;
+}
+
+impl Handler<ResendVerification> for DbExecutor {
+    type Result = Result<Option<String>, DbError>;
+
+    fn handle(&mut self, msg: ResendVerification, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{auth::scan_user, verification::insert_resent_verification};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let user = match scan_user(msg.email, &mut conn) {
+            Err(DbError::CannotFindUser) => return Ok(None),
+            Err(e) => return Err(e),
+            Ok(user) if user.email_verified => return Ok(None),
+            Ok(user) => user,
+        };
+
+        let (row, token) = EmailVerification::issue(user.id);
+        insert_resent_verification(row, Utc::now().naive_utc(), &mut conn)?;
+        Ok(Some(token))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyEmail {
+    pub token: String,
+}
+
+impl Message for VerifyEmail {
+    type Result = Result<(), DbError>;
+}
+
+impl Handler<VerifyEmail> for DbExecutor {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, msg: VerifyEmail, _: &mut Self::Context) -> Self::Result {
+        use crate::todo_api::db::{audit::insert_audit_event, verification::verify_email};
+
+        let mut conn = self.0.get().expect("Failed to open connection");
+        let verified = verify_email(&hash_token(&msg.token), &mut conn)?;
+        match verified.new_email {
+            None => Ok(()),
+            Some(_) => insert_audit_event(
+                AuditEvent::new(
+                    AuditAction::EmailChanged,
+                    &verified.user_id.to_string(),
+                    Some(verified.user_id),
+                ),
+                &mut conn,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn used_or_expired_verification_is_invalid() {
+        let (mut row, _) = EmailVerification::issue(Uuid::new_v4());
+
+        assert_eq!(row.check(Utc::now().naive_utc()), Ok(()));
+        assert_eq!(
+            row.check(row.expires_at),
+            Err(DbError::InvalidVerificationToken)
+        );
+        row.used_at = Some(Utc::now().naive_utc());
+        assert_eq!(
+            row.check(Utc::now().naive_utc()),
+            Err(DbError::InvalidVerificationToken)
+        );
+    }
+
+    #[test]
+    fn resend_waits_for_cooldown() {
+        let now = Utc::now().naive_utc();
+
+        assert_eq!(check_resend(&[], now), Ok(()));
+        assert_eq!(
+            check_resend(&[now - Duration::seconds(10)], now),
+            Err(DbError::VerificationThrottled)
+        );
+        assert_eq!(check_resend(&[now - Duration::minutes(5)], now), Ok(()));
+    }
+
+    #[test]
+    fn resend_is_capped_per_hour() {
+        let now = Utc::now().naive_utc();
+        let sent = (1..=MAX_VERIFICATIONS_PER_HOUR as i64)
+            .map(|i| now - Duration::minutes(i * 5))
+            .collect::<Vec<NaiveDateTime>>();
+
+        assert_eq!(
+            check_resend(&sent, now),
+            Err(DbError::VerificationThrottled)
+        );
+    }
+}
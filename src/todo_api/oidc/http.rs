@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use hyper::{
+    body,
+    client::HttpConnector,
+    header::{ACCEPT, CONTENT_TYPE},
+    Body, Client, Request,
+};
+use hyper_rustls::HttpsConnector;
+use serde::de::DeserializeOwned;
+
+use super::OidcError;
+
+static HTTP_TIMEOUT_SECS: u64 = 10;
+
+pub type HttpClient = Client<HttpsConnector<HttpConnector>>;
+
+/// HTTP and HTTPS client trusting the system roots. Connections are not pooled,
+/// the provider is only called on logins and key rotations.
+pub fn http_client() -> HttpClient {
+    Client::builder()
+        .pool_max_idle_per_host(0)
+        .build(HttpsConnector::with_native_roots())
+}
+
+pub async fn get_json<T: DeserializeOwned>(client: &HttpClient, url: &str) -> Result<T, OidcError> {
+    let request = Request::get(url)
+        .header(ACCEPT, "application/json")
+        .body(Body::empty())
+        .map_err(|e| OidcError::Http(e.to_string()))?;
+    send(client, request).await
+}
+
+pub async fn post_form<T: DeserializeOwned>(
+    client: &HttpClient,
+    url: &str,
+    form: &[(&str, &str)],
+) -> Result<T, OidcError> {
+    let form = serde_urlencoded::to_string(form).map_err(|e| OidcError::Http(e.to_string()))?;
+    let request = Request::post(url)
+        .header(ACCEPT, "application/json")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(form))
+        .map_err(|e| OidcError::Http(e.to_string()))?;
+    send(client, request).await
+}
+
+async fn send<T: DeserializeOwned>(
+    client: &HttpClient,
+    request: Request<Body>,
+) -> Result<T, OidcError> {
+    let exchange = async {
+        let response = client.request(request).await?;
+        let status = response.status();
+        Ok::<_, hyper::Error>((status, body::to_bytes(response.into_body()).await?))
+    };
+    let (status, bytes) = actix_rt::time::timeout(Duration::from_secs(HTTP_TIMEOUT_SECS), exchange)
+        .await
+        .map_err(|_| OidcError::Http(String::from("request timed out")))?
+        .map_err(|e| OidcError::Http(e.to_string()))?;
+
+    if !status.is_success() {
+        let reply = String::from_utf8_lossy(&bytes[..bytes.len().min(200)]).to_string();
+        return Err(OidcError::Provider(status.as_u16(), reply));
+    }
+    serde_json::from_slice(&bytes).map_err(|e| OidcError::Http(e.to_string()))
+}
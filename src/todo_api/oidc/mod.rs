@@ -0,0 +1,467 @@
+pub mod http;
+
+use std::{
+    env,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use jsonwebtokens::{
+    raw::{decode_json_token_slice, split_token},
+    Verifier,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use self::http::{get_json, http_client, post_form, HttpClient};
+use crate::todo_api::core::{
+    keys::{algorithm_id, JwtKey},
+    oauth::{pkce_challenge, redirect_with, PKCE_METHOD},
+};
+use crate::todo_api::model::jwks::JwksResponse;
+
+pub static DEFAULT_OIDC_SCOPES: &str = "openid email profile";
+pub static OIDC_LOGIN_SECS: i64 = 600;
+static DISCOVERY_CACHE_SECS: u64 = 3600;
+static ID_TOKEN_LEEWAY_SECS: u32 = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+}
+
+impl OidcConfig {
+    /// Reads `OIDC_ISSUER`, `OIDC_CLIENT_ID`, `OIDC_CLIENT_SECRET`,
+    /// `OIDC_REDIRECT_URI` and `OIDC_SCOPES`. Login with the provider is
+    /// disabled unless the issuer, client id and redirect URI are set.
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.trim().is_empty());
+
+        Some(Self {
+            issuer: var("OIDC_ISSUER")?,
+            client_id: var("OIDC_CLIENT_ID")?,
+            client_secret: var("OIDC_CLIENT_SECRET"),
+            redirect_uri: var("OIDC_REDIRECT_URI")?,
+            scopes: var("OIDC_SCOPES").unwrap_or_else(|| DEFAULT_OIDC_SCOPES.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    email_verified: Option<Value>,
+}
+
+impl IdTokenClaims {
+    /// Some providers send `email_verified` as a string.
+    pub fn is_email_verified(&self) -> bool {
+        match &self.email_verified {
+            Some(Value::Bool(verified)) => *verified,
+            Some(Value::String(verified)) => verified == "true",
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug)]
+pub enum OidcError {
+    Http(String),
+    Provider(u16, String),
+    Discovery(String),
+    UnknownKey,
+    InvalidIdToken(String),
+}
+
+impl std::fmt::Display for OidcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OidcError::Http(e) => write!(f, "OpenID provider could not be reached: {}", e),
+            OidcError::Provider(status, reply) => {
+                write!(f, "OpenID provider answered {}: {}", status, reply)
+            }
+            OidcError::Discovery(e) => write!(f, "OpenID provider metadata is invalid: {}", e),
+            OidcError::UnknownKey => write!(f, "ID token is signed by an unknown key"),
+            OidcError::InvalidIdToken(e) => write!(f, "ID token is invalid: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for OidcError {}
+
+#[derive(Debug, Clone)]
+struct Discovery {
+    metadata: ProviderMetadata,
+    keys: Vec<JwtKey>,
+    fetched_at: Instant,
+}
+
+/// External OpenID Connect provider, its metadata and signing keys are cached
+/// for an hour and refreshed early when an ID token names an unknown key.
+#[derive(Debug)]
+pub struct OidcProvider {
+    pub config: OidcConfig,
+    client: HttpClient,
+    discovery: RwLock<Option<Discovery>>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            config,
+            client: http_client(),
+            discovery: RwLock::new(None),
+        }
+    }
+
+    async fn discovery(&self, refresh: bool) -> Result<Discovery, OidcError> {
+        let cached = self.discovery.read().unwrap().clone();
+        match cached {
+            Some(discovery)
+                if !refresh
+                    && discovery.fetched_at.elapsed()
+                        < Duration::from_secs(DISCOVERY_CACHE_SECS) =>
+            {
+                return Ok(discovery)
+            }
+            _ => (),
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = get_json(&self.client, &url).await?;
+        if metadata.issuer != self.config.issuer {
+            return Err(OidcError::Discovery(format!(
+                "issuer {} does not match {}",
+                metadata.issuer, self.config.issuer
+            )));
+        }
+        let jwks: JwksResponse = get_json(&self.client, &metadata.jwks_uri).await?;
+        let discovery = Discovery {
+            metadata,
+            keys: jwks.keys.iter().filter_map(JwtKey::from_jwk).collect(),
+            fetched_at: Instant::now(),
+        };
+
+        *self.discovery.write().unwrap() = Some(discovery.clone());
+        Ok(discovery)
+    }
+
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_verifier: &str,
+    ) -> Result<String, OidcError> {
+        let discovery = self.discovery(false).await?;
+        let challenge = pkce_challenge(code_verifier);
+
+        Ok(redirect_with(
+            &discovery.metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.config.client_id),
+                ("redirect_uri", &self.config.redirect_uri),
+                ("scope", &self.config.scopes),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", &challenge),
+                ("code_challenge_method", PKCE_METHOD),
+            ],
+        ))
+    }
+
+    /// Redeems the code at the token endpoint and validates the ID token it
+    /// returns.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let discovery = self.discovery(false).await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.config.redirect_uri),
+            ("client_id", &self.config.client_id),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let response: TokenResponse =
+            post_form(&self.client, &discovery.metadata.token_endpoint, &form).await?;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        match validate_id_token(
+            &response.id_token,
+            &discovery.keys,
+            &self.config,
+            nonce,
+            now,
+        ) {
+            Err(OidcError::UnknownKey) => {
+                let discovery = self.discovery(true).await?;
+                validate_id_token(
+                    &response.id_token,
+                    &discovery.keys,
+                    &self.config,
+                    nonce,
+                    now,
+                )
+            }
+            validated => validated,
+        }
+    }
+}
+
+/// Checks the signature against the provider keys, the issuer, the audience,
+/// the expiry and the nonce of the login.
+pub fn validate_id_token(
+    token: &str,
+    keys: &[JwtKey],
+    config: &OidcConfig,
+    nonce: &str,
+    now: u64,
+) -> Result<IdTokenClaims, OidcError> {
+    let invalid = |e: jsonwebtokens::error::Error| OidcError::InvalidIdToken(e.to_string());
+
+    let header =
+        decode_json_token_slice(split_token(token).map_err(invalid)?.header).map_err(invalid)?;
+    let alg = header
+        .get("alg")
+        .and_then(|alg| alg.as_str())
+        .and_then(algorithm_id);
+    let kid = header.get("kid").and_then(|kid| kid.as_str());
+    let key = keys
+        .iter()
+        .filter(|key| Some(key.alg) == alg)
+        .find(|key| kid.is_none_or(|kid| key.kid == kid))
+        .ok_or(OidcError::UnknownKey)?;
+
+    let verifier = Verifier::create()
+        .issuer(config.issuer.as_str())
+        .audience(config.client_id.as_str())
+        .nonce(nonce)
+        .leeway(ID_TOKEN_LEEWAY_SECS)
+        .claim_callback("exp", |exp| exp.is_u64())
+        .build()
+        .map_err(invalid)?;
+    let verified = verifier
+        .verify_for_time(token, &key.verifier().map_err(invalid)?, now)
+        .map_err(invalid)?;
+
+    serde_json::from_value(verified.claims).map_err(|e| OidcError::InvalidIdToken(e.to_string()))
+}
+
+pub fn oidc_from_env() -> Option<Arc<OidcProvider>> {
+    OidcConfig::from_env().map(|config| Arc::new(OidcProvider::new(config)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
+    use jsonwebtokens::AlgorithmID;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    static CLIENT_ID: &str = "todo-server";
+    static NONCE: &str = "n-0S6_WzA2Mj";
+    static VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+    fn provider_key() -> JwtKey {
+        let pem = std::fs::read(crate::todo_api::core::keys::test_keys::rs256_pem()).unwrap();
+        JwtKey::from_pem("mock", AlgorithmID::RS256, &pem).unwrap()
+    }
+
+    fn config(issuer: &str) -> OidcConfig {
+        OidcConfig {
+            issuer: issuer.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: "http://localhost:4000/auth/oidc/callback".to_string(),
+            scopes: DEFAULT_OIDC_SCOPES.to_string(),
+        }
+    }
+
+    fn id_token(issuer: &str, kid: &str, overrides: Value) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let mut claims = json!({
+            "iss": issuer,
+            "sub": "248289761001",
+            "aud": CLIENT_ID,
+            "nonce": NONCE,
+            "iat": now,
+            "exp": now + 300,
+            "email": "sso@email.com",
+            "email_verified": true,
+        });
+        for (claim, value) in overrides.as_object().unwrap() {
+            claims[claim] = value.clone();
+        }
+        let signer = provider_key().signer().unwrap();
+        let header = json!({ "alg": "RS256", "typ": "JWT", "kid": kid });
+        jsonwebtokens::encode(&header, &claims, &signer).unwrap()
+    }
+
+    fn validate(token: &str) -> Result<IdTokenClaims, OidcError> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let keys = vec![JwtKey::from_jwk(&provider_key().jwk().unwrap()).unwrap()];
+        validate_id_token(token, &keys, &config("https://idp.example.com"), NONCE, now)
+    }
+
+    #[test]
+    fn valid_id_token_is_accepted() {
+        let token = id_token("https://idp.example.com", "mock", json!({}));
+        let claims = validate(&token).unwrap();
+
+        assert_eq!(claims.sub, "248289761001");
+        assert_eq!(claims.email.as_deref(), Some("sso@email.com"));
+        assert!(claims.is_email_verified());
+    }
+
+    #[test]
+    fn id_token_claims_must_match_the_login() {
+        let issuer = "https://idp.example.com";
+        let now = chrono::Utc::now().timestamp();
+
+        for overrides in [
+            json!({ "iss": "https://evil.example.com" }),
+            json!({ "aud": "other-client" }),
+            json!({ "nonce": "replayed" }),
+            json!({ "exp": now - 3600 }),
+        ] {
+            let token = id_token(issuer, "mock", overrides);
+            assert!(matches!(
+                validate(&token),
+                Err(OidcError::InvalidIdToken(_))
+            ));
+        }
+        let token = id_token(issuer, "mock", json!({ "aud": ["other", CLIENT_ID] }));
+        assert!(validate(&token).is_ok());
+    }
+
+    #[test]
+    fn unknown_key_asks_for_a_refresh() {
+        let token = id_token("https://idp.example.com", "rotated", json!({}));
+
+        assert!(matches!(validate(&token), Err(OidcError::UnknownKey)));
+    }
+
+    #[test]
+    fn email_verified_can_be_a_string() {
+        let claims = |verified: Value| IdTokenClaims {
+            iss: String::new(),
+            sub: String::new(),
+            email: None,
+            email_verified: Some(verified),
+        };
+
+        assert!(claims(json!("true")).is_email_verified());
+        assert!(!claims(json!("false")).is_email_verified());
+        assert!(!claims(json!(false)).is_email_verified());
+    }
+
+    #[get("/.well-known/openid-configuration")]
+    async fn mock_discovery(issuer: web::Data<String>) -> impl Responder {
+        HttpResponse::Ok().json(json!({
+            "issuer": issuer.as_str(),
+            "authorization_endpoint": format!("{}/authorize", issuer.as_str()),
+            "token_endpoint": format!("{}/token", issuer.as_str()),
+            "jwks_uri": format!("{}/jwks", issuer.as_str()),
+        }))
+    }
+
+    #[get("/jwks")]
+    async fn mock_jwks() -> impl Responder {
+        HttpResponse::Ok().json(json!({ "keys": [provider_key().jwk()] }))
+    }
+
+    /// Echoes the code as the nonce of the ID token.
+    #[post("/token")]
+    async fn mock_token(
+        issuer: web::Data<String>,
+        form: web::Form<HashMap<String, String>>,
+    ) -> impl Responder {
+        if form.get("code_verifier").map(String::as_str) != Some(VERIFIER) {
+            return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+        }
+        let nonce = form.get("code").cloned().unwrap_or_default();
+        HttpResponse::Ok().json(json!({
+            "access_token": "opaque",
+            "token_type": "Bearer",
+            "id_token": id_token(issuer.as_str(), "mock", json!({ "nonce": nonce })),
+        }))
+    }
+
+    /// Local OpenID provider serving discovery, JWKS and a token endpoint.
+    fn mock_provider() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let data = web::Data::new(issuer.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .service(mock_discovery)
+                .service(mock_jwks)
+                .service(mock_token)
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_rt::spawn(server);
+        issuer
+    }
+
+    #[actix_web::test]
+    async fn logs_in_against_a_mock_provider() {
+        let issuer = mock_provider();
+        let provider = OidcProvider::new(config(&issuer));
+
+        let url = provider
+            .authorization_url("state", NONCE, VERIFIER)
+            .await
+            .unwrap();
+        assert!(url.starts_with(&format!("{}/authorize?response_type=code", issuer)));
+        assert!(url.contains("code_challenge_method=S256"));
+
+        let claims = provider
+            .exchange_code(NONCE, VERIFIER, NONCE)
+            .await
+            .unwrap();
+        assert_eq!(claims.iss, issuer);
+        assert_eq!(claims.sub, "248289761001");
+
+        let replayed = provider.exchange_code("other", VERIFIER, NONCE).await;
+        assert!(matches!(replayed, Err(OidcError::InvalidIdToken(_))));
+        let wrong_verifier = provider.exchange_code(NONCE, &"x".repeat(43), NONCE).await;
+        assert!(matches!(wrong_verifier, Err(OidcError::Provider(400, _))));
+    }
+}
@@ -0,0 +1,35 @@
+use crate::todo_api::core::{jwt_from_request, lockout::admin_emails, scope::ADMIN};
+use crate::todo_api::model::lockout::AdminUnlock;
+use crate::todo_api_web::controller::todo::request_actor;
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::http::Clients;
+
+use actix_web::{delete, web, HttpRequest, HttpResponse, Responder};
+use log::error;
+
+#[delete("/admin/lockouts/{email}", wrap = "RequireScope(ADMIN)")]
+pub async fn admin_unlock_account(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    email: web::Path<String>,
+) -> impl Responder {
+    let (admin, admin_email) = match (request_actor(&req), jwt_from_request(&req)) {
+        (Some(actor), Some(jwt)) => (actor, jwt.email.to_lowercase()),
+        _ => return HttpResponse::Unauthorized().finish(),
+    };
+    if !admin_emails().contains(&admin_email) {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    let unlock = AdminUnlock {
+        email: email.into_inner(),
+        admin,
+    };
+    match state.postgres.send(unlock).await {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        e => {
+            error!("Failed to unlock account {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
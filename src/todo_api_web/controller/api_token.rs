@@ -0,0 +1,139 @@
+use crate::todo_api::core::{
+    api_token::{
+        is_api_token, is_expiry_valid, is_token_name_valid, normalize_scopes, token_expiry,
+    },
+    jwt_from_request,
+    scope::{missing_scope, ACCOUNT},
+    token_from_request,
+};
+use crate::todo_api::db::helpers::{
+    ERROR_API_TOKEN_MANAGEMENT, ERROR_API_TOKEN_NOT_FOUND, ERROR_API_TOKEN_READ,
+    ERROR_API_TOKEN_REQUEST,
+};
+use crate::todo_api::model::{
+    api_token::{CreateApiToken, ReadApiTokens, RevokeApiToken},
+    error::DbError,
+};
+use crate::todo_api_web::controller::todo::request_actor;
+use crate::todo_api_web::middleware::scope::{insufficient_scope, RequireScope};
+use crate::todo_api_web::model::{
+    api_token::{
+        ApiTokenResponse, ApiTokensResponse, CreateApiTokenRequest, CreatedApiTokenResponse,
+    },
+    http::Clients,
+};
+
+use actix_web::{
+    delete, get, http::header::ContentType, post, web, HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+use uuid::Uuid;
+
+/// Tokens are managed from an interactive login only, so a leaked API token
+/// cannot mint or revoke others.
+fn is_api_token_request(req: &HttpRequest) -> bool {
+    token_from_request(req).is_some_and(is_api_token)
+}
+
+#[post("/tokens", wrap = "RequireScope(ACCOUNT)")]
+pub async fn create_api_token(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<CreateApiTokenRequest>,
+) -> impl Responder {
+    if is_api_token_request(&req) {
+        return HttpResponse::Forbidden().body(ERROR_API_TOKEN_MANAGEMENT);
+    }
+    let (actor, granted) = match (request_actor(&req), jwt_from_request(&req)) {
+        (Some(actor), Some(jwt)) => (actor, jwt.granted_scopes()),
+        _ => return HttpResponse::Unauthorized().finish(),
+    };
+    let info = info.into_inner();
+    let now = chrono::Utc::now().naive_utc();
+    let scopes = match normalize_scopes(&info.scopes) {
+        Some(scopes)
+            if is_token_name_valid(&info.name) && is_expiry_valid(info.expires_in_days) =>
+        {
+            scopes
+        }
+        _ => return HttpResponse::BadRequest().body(ERROR_API_TOKEN_REQUEST),
+    };
+    // A token never grants more than the login creating it.
+    if let Some(scope) = missing_scope(&granted, &scopes) {
+        return insufficient_scope(scope);
+    }
+
+    let create = CreateApiToken {
+        user_id: actor,
+        name: info.name,
+        scopes,
+        expires_at: token_expiry(info.expires_in_days, now),
+    };
+    match state.postgres.send(create).await {
+        Ok(Ok((row, token))) => HttpResponse::Created()
+            .content_type(ContentType::json())
+            .json(CreatedApiTokenResponse {
+                token,
+                details: ApiTokenResponse::from(row),
+            }),
+        e => {
+            error!("Failed to create API token {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[get("/tokens", wrap = "RequireScope(ACCOUNT)")]
+pub async fn show_api_tokens(req: HttpRequest, state: web::Data<Clients>) -> impl Responder {
+    if is_api_token_request(&req) {
+        return HttpResponse::Forbidden().body(ERROR_API_TOKEN_MANAGEMENT);
+    }
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+
+    match state.postgres.send(ReadApiTokens { user_id: actor }).await {
+        Ok(Ok(tokens)) => {
+            HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .json(ApiTokensResponse {
+                    tokens: tokens.into_iter().map(ApiTokenResponse::from).collect(),
+                })
+        }
+        e => {
+            error!("Failed to read API tokens {:?}", e);
+            HttpResponse::InternalServerError().body(ERROR_API_TOKEN_READ)
+        }
+    }
+}
+
+#[delete("/tokens/{id}", wrap = "RequireScope(ACCOUNT)")]
+pub async fn revoke_api_token(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+) -> impl Responder {
+    if is_api_token_request(&req) {
+        return HttpResponse::Forbidden().body(ERROR_API_TOKEN_MANAGEMENT);
+    }
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let revoke = RevokeApiToken {
+        id: id.into_inner(),
+        user_id: actor,
+    };
+
+    match state.postgres.send(revoke).await {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        Ok(Err(DbError::CannotFindApiToken)) => {
+            HttpResponse::NotFound().body(ERROR_API_TOKEN_NOT_FOUND)
+        }
+        e => {
+            error!("Failed to revoke API token {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
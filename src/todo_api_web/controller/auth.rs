@@ -1,55 +1,430 @@
-use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
-use log::error;
+use actix_web::{
+    http::header::{ContentType, RETRY_AFTER, USER_AGENT},
+    post, web, HttpRequest, HttpResponse, Responder,
+};
+use chrono::NaiveDateTime;
+use log::{error, warn};
 
 use crate::{
     todo_api::{
-        core::{decode_jwt, generate_jwt, validate_jwt_date, validate_jwt_info},
-        model::core::JwtValue,
+        core::{
+            create_challenge_token, decode_challenge_token, generate_access_jwt, generate_jwt,
+            jwt_from_request,
+            lockout::{unlock_email, LockoutPolicy},
+            password::reset_email,
+            token_from_request, validate_jwt_date, validate_jwt_info,
+            validation::{
+                credential_errors, email_errors, is_email_valid, password_policy, FieldError,
+            },
+            verification::verification_email,
+        },
+        db::helpers::{
+            ERROR_EMAIL_NOT_VERIFIED, ERROR_EMAIL_TAKEN, ERROR_LOGIN_THROTTLED, ERROR_RESET_TOKEN,
+            ERROR_TWO_FACTOR_CODE, ERROR_UNLOCK_TOKEN, ERROR_VERIFICATION_THROTTLED,
+            ERROR_VERIFICATION_TOKEN,
+        },
+        mailer::Email,
+        model::{
+            auth::{RehashPassword, User},
+            error::DbError,
+            lockout::{
+                BeginLogin, LoginAttempt, RecordLoginFailure, RecordLoginSuccess,
+                ReleaseLoginAttempt, UnlockAccount,
+            },
+            password::{ForgotPassword, ResetPassword, ThrottlePasswordReset},
+            refresh::RotateRefreshToken,
+            two_factor::{ConsumeChallenge, ReadTwoFactor, VerifySecondFactor},
+            verification::{ResendVerification, VerifyEmail},
+        },
     },
     todo_api_web::model::{
-        auth::{Auth, SignUp},
-        http::Clients,
+        auth::{
+            Auth, ForgotPasswordRequest, RefreshRequest, ResendVerificationRequest,
+            ResetPasswordRequest, SignUp, UnlockRequest, VerifyEmailRequest,
+        },
+        http::{Clients, ValidationErrors},
+        two_factor::{TwoFactorChallenge, TwoFactorLoginRequest},
     },
 };
 
 #[post("/login")]
-pub async fn login(state: web::Data<Clients>, info: web::Json<Auth>) -> impl Responder {
+pub async fn login(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<Auth>,
+) -> impl Responder {
     let login_user = info.clone();
-    if !is_email_pswd_valids(&login_user.email, &login_user.password.clone().unwrap()) {
-        return HttpResponse::BadRequest().finish();
+    // The password policy only applies to new passwords, existing ones must
+    // keep working when it gets stricter.
+    let mut errors = email_errors(&login_user.email);
+    if login_user
+        .password
+        .as_deref()
+        .unwrap_or_default()
+        .is_empty()
+    {
+        errors.push(FieldError::new(
+            "password",
+            "missing",
+            "Password is required",
+        ));
+    }
+    if !errors.is_empty() {
+        return HttpResponse::BadRequest()
+            .content_type(ContentType::json())
+            .json(ValidationErrors { errors });
     }
+    let password = login_user.password.clone().unwrap_or_default();
 
-    let resp = state.postgres.send(login_user).await;
+    let ip = client_ip(&req);
+    let begin = BeginLogin {
+        email: login_user.email.clone(),
+        ip: ip.clone(),
+    };
+    let locked = match state.postgres.send(begin).await {
+        Ok(Ok(LoginAttempt::Counted(locked))) => locked,
+        Ok(Ok(LoginAttempt::Throttled(retry_at))) => return too_many_attempts(retry_at),
+        e => {
+            error!("Failed to count login attempt {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let resp = state.postgres.send(login_user.clone()).await;
 
     match resp {
         Err(e) => {
             error!("{:?}", e);
             HttpResponse::NoContent().finish()
         }
-        Ok(user) => {
-            let usr = user.unwrap();
-            match usr.verify(info.clone().password.unwrap()) {
-                Ok(true) => generate_jwt(usr, state).await,
-                Ok(false) => HttpResponse::NoContent().finish(),
-                Err(_) => HttpResponse::NoContent().finish(),
+        Ok(Err(_)) => {
+            login_failed(&state, &login_user.email, ip, None, locked).await;
+            HttpResponse::NoContent().finish()
+        }
+        Ok(Ok(usr)) => match usr.verify(password.clone()) {
+            true => {
+                upgrade_password_hash(&state, &usr, password.clone()).await;
+                if !usr.email_verified {
+                    release_login_attempt(&state, &usr.email, ip).await;
+                    return HttpResponse::Forbidden().body(ERROR_EMAIL_NOT_VERIFIED);
+                }
+                // Failure counters are only reset once the second factor is
+                // through, so codes cannot be guessed without limit.
+                if let Some(challenge) = two_factor_challenge(&state, &usr).await {
+                    release_login_attempt(&state, &usr.email, ip).await;
+                    return challenge;
+                }
+                login_succeeded(&state, &usr.email, ip).await;
+                generate_jwt(usr, &device_label(&req), state).await
+            }
+            _ => {
+                login_failed(&state, &usr.email, ip, Some(usr.id), locked).await;
+                HttpResponse::NoContent().finish()
             }
+        },
+    }
+}
+
+/// Second login step, exchanges the challenge token and a TOTP or recovery
+/// code for the JWT.
+#[post("/login/2fa")]
+pub async fn login_two_factor(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<TwoFactorLoginRequest>,
+) -> impl Responder {
+    let info = info.into_inner();
+    let challenge = match decode_challenge_token(&info.challenge_token) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(challenge) => challenge,
+    };
+
+    let ip = client_ip(&req);
+    let begin = BeginLogin {
+        email: challenge.email.clone(),
+        ip: ip.clone(),
+    };
+    let locked = match state.postgres.send(begin).await {
+        Ok(Ok(LoginAttempt::Counted(locked))) => locked,
+        Ok(Ok(LoginAttempt::Throttled(retry_at))) => return too_many_attempts(retry_at),
+        e => {
+            error!("Failed to count login attempt {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let verify = VerifySecondFactor {
+        user_id: challenge.sub,
+        code: info.code,
+    };
+    match state.postgres.send(verify).await {
+        Ok(Ok(_)) => (),
+        Ok(Err(DbError::InvalidTwoFactorCode)) | Ok(Err(DbError::TwoFactorNotEnrolled)) => {
+            login_failed(&state, &challenge.email, ip, Some(challenge.sub), locked).await;
+            return HttpResponse::Unauthorized().body(ERROR_TWO_FACTOR_CODE);
+        }
+        e => {
+            error!("Failed to verify two-factor code {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    let consume = ConsumeChallenge {
+        jti: challenge.jti,
+        user_id: challenge.sub,
+        expires_at: NaiveDateTime::from_timestamp_opt(challenge.exp, 0).unwrap_or_default(),
+    };
+    match state.postgres.send(consume).await {
+        Ok(Ok(_)) => (),
+        Ok(Err(DbError::ChallengeUsed)) => return HttpResponse::Unauthorized().finish(),
+        e => {
+            error!("Failed to consume two-factor challenge {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    let lookup = Auth {
+        email: challenge.email,
+        password: None,
+    };
+    match state.postgres.send(lookup).await {
+        Ok(Ok(usr)) if usr.id == challenge.sub && usr.is_active => {
+            login_succeeded(&state, &usr.email, ip).await;
+            generate_jwt(usr, &device_label(&req), state).await
+        }
+        Ok(_) => HttpResponse::Unauthorized().finish(),
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Answers with a `TwoFactorChallenge` in place of the JWT when the user has
+/// two-factor enabled, `None` lets the login go through.
+pub(crate) async fn two_factor_challenge(
+    state: &web::Data<Clients>,
+    user: &User,
+) -> Option<HttpResponse> {
+    match state
+        .postgres
+        .send(ReadTwoFactor { user_id: user.id })
+        .await
+    {
+        Ok(Ok(Some(two_factor))) if two_factor.enabled => {
+            let (challenge_token, expires_in) = create_challenge_token(user);
+            Some(
+                HttpResponse::Ok()
+                    .content_type(ContentType::json())
+                    .json(TwoFactorChallenge {
+                        two_factor_required: true,
+                        challenge_token,
+                        expires_in,
+                    }),
+            )
+        }
+        Ok(Ok(_)) => None,
+        e => {
+            error!("Failed to read two-factor settings {:?}", e);
+            Some(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+/// Moves bcrypt and outdated Argon2 hashes to the configured Argon2id
+/// parameters once the password was verified.
+pub(crate) async fn upgrade_password_hash(
+    state: &web::Data<Clients>,
+    user: &User,
+    password: String,
+) {
+    if !user.needs_rehash() {
+        return;
+    }
+    let rehash = RehashPassword {
+        user: user.clone(),
+        password,
+    };
+    match state.postgres.send(rehash).await {
+        Ok(Ok(_)) => (),
+        e => warn!("Failed to upgrade password hash {:?}", e),
+    }
+}
+
+pub(crate) async fn login_succeeded(state: &web::Data<Clients>, email: &str, ip: String) {
+    let success = RecordLoginSuccess {
+        email: email.to_string(),
+        ip,
+    };
+    if let Err(e) = state.postgres.send(success).await {
+        error!("Failed to reset login attempts {:?}", e);
+    }
+}
+
+pub(crate) async fn release_login_attempt(state: &web::Data<Clients>, email: &str, ip: String) {
+    let release = ReleaseLoginAttempt {
+        email: email.to_string(),
+        ip,
+    };
+    match state.postgres.send(release).await {
+        Ok(Ok(_)) => (),
+        e => error!("Failed to release login attempt {:?}", e),
+    }
+}
+
+/// `locked` are the keys `BeginLogin` locked with this attempt.
+pub(crate) async fn login_failed(
+    state: &web::Data<Clients>,
+    email: &str,
+    ip: String,
+    user_id: Option<uuid::Uuid>,
+    locked: Vec<String>,
+) {
+    let failure = RecordLoginFailure {
+        email: email.to_string(),
+        ip,
+        user_id,
+        locked,
+    };
+    match state.postgres.send(failure).await {
+        Ok(Ok(Some(token))) => {
+            let email = unlock_email(email, &token, &LockoutPolicy::account());
+            send_email(state, email).await
+        }
+        Ok(Ok(None)) => (),
+        e => error!("Failed to record login failure {:?}", e),
+    }
+}
+
+pub(crate) fn too_many_attempts(retry_at: NaiveDateTime) -> HttpResponse {
+    let wait = (retry_at - chrono::Utc::now().naive_utc()).num_seconds() + 1;
+    HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, wait.max(1).to_string()))
+        .body(ERROR_LOGIN_THROTTLED)
+}
+
+#[post("/unlock")]
+pub async fn unlock_account(
+    state: web::Data<Clients>,
+    info: web::Json<UnlockRequest>,
+) -> impl Responder {
+    let unlock = UnlockAccount {
+        token: info.into_inner().token,
+    };
+
+    match state.postgres.send(unlock).await {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        Ok(Err(DbError::InvalidUnlockToken)) => HttpResponse::BadRequest().body(ERROR_UNLOCK_TOKEN),
+        e => {
+            error!("Failed to unlock account {:?}", e);
+            HttpResponse::InternalServerError().finish()
         }
     }
 }
 #[post("/signup")]
 pub async fn signup_user(state: web::Data<Clients>, info: web::Json<SignUp>) -> impl Responder {
     let signup = info.into_inner();
-    if !is_email_pswd_valids(&signup.email, &signup.password) {
-        return HttpResponse::BadRequest();
+    let errors = credential_errors(&signup.email, &signup.password);
+    if !errors.is_empty() {
+        return HttpResponse::BadRequest()
+            .content_type(ContentType::json())
+            .json(ValidationErrors { errors });
     }
 
+    let email = signup.email.clone();
     let resp = state.postgres.send(signup).await;
 
     match resp {
-        Ok(_) => HttpResponse::Created(),
+        Ok(Ok(token)) => {
+            send_email(&state, verification_email(&email, &token)).await;
+            HttpResponse::Created().finish()
+        }
+        Ok(Err(e)) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Err(e) => {
+            error!("{:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[post("/email/verify")]
+pub async fn verify_email(
+    state: web::Data<Clients>,
+    info: web::Json<VerifyEmailRequest>,
+) -> impl Responder {
+    let verify = VerifyEmail {
+        token: info.into_inner().token,
+    };
+
+    match state.postgres.send(verify).await {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        Ok(Err(DbError::InvalidVerificationToken)) => {
+            HttpResponse::BadRequest().body(ERROR_VERIFICATION_TOKEN)
+        }
+        Ok(Err(DbError::EmailTaken)) => HttpResponse::Conflict().body(ERROR_EMAIL_TAKEN),
+        e => {
+            error!("Failed to verify email {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Answers 202 for unknown and already verified emails too, only throttled
+/// requests get a 429.
+#[post("/email/resend")]
+pub async fn resend_verification(
+    state: web::Data<Clients>,
+    info: web::Json<ResendVerificationRequest>,
+) -> impl Responder {
+    let email = info.into_inner().email;
+    let resend = ResendVerification {
+        email: email.clone(),
+    };
+
+    match state.postgres.send(resend).await {
+        Ok(Ok(Some(token))) => {
+            send_email(&state, verification_email(&email, &token)).await;
+            HttpResponse::Accepted().finish()
+        }
+        Ok(Ok(None)) => HttpResponse::Accepted().finish(),
+        Ok(Err(DbError::VerificationThrottled)) => {
+            HttpResponse::TooManyRequests().body(ERROR_VERIFICATION_THROTTLED)
+        }
+        e => {
+            error!("Failed to resend verification email {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[post("/refresh")]
+pub async fn refresh(state: web::Data<Clients>, info: web::Json<RefreshRequest>) -> impl Responder {
+    let rotate = RotateRefreshToken {
+        token: info.into_inner().refresh_token,
+    };
+
+    match state.postgres.send(rotate).await {
+        Ok(Ok(rotated)) if rotated.user.is_active => {
+            generate_access_jwt(
+                rotated.user,
+                rotated.session_id,
+                rotated.token,
+                rotated.scope,
+                state,
+            )
+            .await
+        }
+        Ok(Err(DbError::RefreshTokenReused)) => {
+            warn!("Refresh token reused, token family revoked");
+            HttpResponse::Unauthorized().finish()
+        }
+        Ok(_) => HttpResponse::Unauthorized().finish(),
         Err(e) => {
             error!("{:?}", e);
-            HttpResponse::InternalServerError()
+            HttpResponse::InternalServerError().finish()
         }
     }
 }
@@ -60,89 +435,117 @@ pub async fn logout(
     state: web::Data<Clients>,
     info: web::Json<Auth>,
 ) -> impl Responder {
-    use regex::Regex;
-
-    let jwt = req.headers().get("x-auth");
     let logout_user = info.clone();
-    let email_regex = Regex::new("\\w{1,}@\\w{2,}.[a-z]{2,3}(.[a-z]{2,3})?$").unwrap();
-
-    if !email_regex.is_match(&logout_user.email) {
+    if !is_email_valid(&logout_user.email) {
         return HttpResponse::BadRequest().finish();
     }
 
     let resp = state.postgres.send(logout_user.clone());
 
-    match jwt {
-        None => return HttpResponse::BadRequest().finish(),
-        Some(jwt) => {
-            let jwt_value: JwtValue = serde_json::from_value(decode_jwt(jwt.to_str().unwrap()))
-                .expect("failed to parse JWT Value");
-            match validate_jwt_date(jwt_value.expires_at) {
-                false => HttpResponse::Unauthorized().finish(),
-                true => {
-                    validate_jwt_info(
-                        jwt_value.email,
-                        logout_user.email,
-                        resp.await.expect("Failed to read contact info"),
-                        state,
-                    )
-                    .await
-                }
-            }
+    if token_from_request(&req).is_none() {
+        return HttpResponse::BadRequest().finish();
+    }
+    let jwt_value = match jwt_from_request(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(jwt_value) => jwt_value,
+    };
+    match validate_jwt_date(jwt_value.expires_at) {
+        false => HttpResponse::Unauthorized().finish(),
+        true => {
+            validate_jwt_info(
+                jwt_value,
+                logout_user.email,
+                resp.await.expect("Failed to read contact info"),
+                state,
+            )
+            .await
         }
     }
 }
 
-pub fn is_email_pswd_valids(email: &str, pswd: &str) -> bool {
-    use regex::Regex;
-
-    let email_regex = Regex::new("\\w{1,}@\\w{2,}.[a-z]{2,3}(.[a-z]{2,3})?$").unwrap();
-    let pswd_regex = Regex::new("[[a-z]+[A-Z]+[0-9]+(\\s@!=_#&~\\[\\]\\{\\}\\?)]{32,64}").unwrap();
+/// Always answers 202 before looking the email up, so neither the response
+/// nor its timing reveals which emails have an account.
+#[post("/password/forgot")]
+pub async fn forgot_password(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<ForgotPasswordRequest>,
+) -> impl Responder {
+    let email = info.into_inner().email;
+    let throttle = ThrottlePasswordReset {
+        email: email.clone(),
+        ip: client_ip(&req),
+    };
+    match state.postgres.send(throttle).await {
+        Ok(Ok(None)) => (),
+        Ok(Ok(Some(retry_at))) => return too_many_attempts(retry_at),
+        e => {
+            error!("Failed to check password reset requests {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
 
-    email_regex.is_match(email) && pswd_regex.is_match(pswd)
+    actix_web::rt::spawn(async move {
+        let forgot = ForgotPassword {
+            email: email.clone(),
+        };
+        match state.postgres.send(forgot).await {
+            Ok(Ok(Some(token))) => send_email(&state, reset_email(&email, &token)).await,
+            Ok(Ok(None)) => (),
+            e => error!("Failed to issue password reset {:?}", e),
+        }
+    });
+    HttpResponse::Accepted().finish()
 }
 
-#[cfg(test)]
-mod valid_email_pswd {
-    use super::is_email_pswd_valids;
-
-    #[test]
-    fn valid_email_and_pswd() {
-        assert!(is_email_pswd_valids(
-            "my@email.com",
-            "My cr4zy P@ssw0rd My cr4zy P@ssw0rd"
-        ));
+#[post("/password/reset")]
+pub async fn reset_password(
+    state: web::Data<Clients>,
+    info: web::Json<ResetPasswordRequest>,
+) -> impl Responder {
+    let info = info.into_inner();
+    // The email is only known once the token is redeemed, so similarity to it
+    // is not checked here.
+    let errors = password_policy().check(&info.password, None);
+    if !errors.is_empty() {
+        return HttpResponse::BadRequest()
+            .content_type(ContentType::json())
+            .json(ValidationErrors { errors });
     }
 
-    #[test]
-    fn invalid_emails() {
-        assert!(!is_email_pswd_valids(
-            "my_email.com",
-            "My cr4zy P@ssw0rd My cr4zy P@ssw0rd"
-        ));
-        assert!(!is_email_pswd_valids(
-            "my@email.com.br.us",
-            "My cr4zy P@ssw0rd My cr4zy P@ssw0rd"
-        ));
+    let reset = ResetPassword {
+        token: info.token,
+        password: info.password,
+    };
+    match state.postgres.send(reset).await {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        Ok(Err(DbError::InvalidResetToken)) => HttpResponse::BadRequest().body(ERROR_RESET_TOKEN),
+        e => {
+            error!("Failed to reset password {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
     }
+}
 
-    #[test]
-    fn invalid_passwords() {
-        assert!(!is_email_pswd_valids(
-            "my@email.com.br",
-            "My cr4zy P@ssw0rd"
-        ));
-        assert!(is_email_pswd_valids(
-            "my@email.com",
-            "my cr4zy p@ssw0rd my cr4zy p@ssw0rd"
-        ));
-        assert!(is_email_pswd_valids(
-            "my@email.com",
-            "My crazy P@ssword My crazy P@ssword"
-        ));
-        assert!(is_email_pswd_valids(
-            "my@email.com",
-            "My cr4zy Passw0rd My cr4zy Passw0rd"
-        ));
+/// Delivery failures are logged, the user can always ask for a new email.
+pub(crate) async fn send_email(state: &web::Data<Clients>, email: Email) {
+    let mailer = state.mailer.clone();
+    match web::block(move || mailer.send(&email)).await {
+        Ok(Ok(_)) => (),
+        e => error!("Failed to send email {:?}", e),
     }
 }
+
+pub(crate) fn client_ip(req: &HttpRequest) -> String {
+    req.peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+pub(crate) fn device_label(req: &HttpRequest) -> String {
+    req.headers()
+        .get(USER_AGENT)
+        .and_then(|agent| agent.to_str().ok())
+        .unwrap_or("unknown device")
+        .to_string()
+}
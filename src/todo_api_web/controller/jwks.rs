@@ -0,0 +1,13 @@
+use crate::todo_api::core::keys::jwt_keys;
+use crate::todo_api::model::jwks::JwksResponse;
+
+use actix_web::{get, http::header::ContentType, HttpResponse, Responder};
+
+#[get("/.well-known/jwks.json")]
+pub async fn jwks() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(JwksResponse {
+            keys: jwt_keys().public_keys(),
+        })
+}
@@ -1,5 +1,16 @@
+pub mod admin;
+pub mod api_token;
 pub mod auth;
+pub mod jwks;
+pub mod oauth;
+pub mod oidc;
+pub mod profile;
+pub mod session;
+pub mod stats;
+pub mod template;
 pub mod todo;
+pub mod two_factor;
+pub mod undo;
 
 use actix_web::{get, HttpResponse, Responder};
 
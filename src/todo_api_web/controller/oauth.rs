@@ -0,0 +1,637 @@
+use crate::todo_api::core::{
+    access_jwt,
+    api_token::normalize_scopes,
+    jwt_from_token,
+    oauth::{
+        consent_page, error_page, is_client_valid, redirect_with, DEFAULT_OAUTH_SCOPE, PKCE_METHOD,
+    },
+    revocation::{is_token_revoked, revoke_token},
+    scope::{missing_scope, user_scopes, ACCOUNT},
+    start_session,
+};
+use crate::todo_api::db::helpers::{
+    ERROR_EMAIL_NOT_VERIFIED, ERROR_INVALID_CREDENTIALS, ERROR_LOGIN_THROTTLED,
+    ERROR_OAUTH_CLIENT_NOT_FOUND, ERROR_OAUTH_CLIENT_READ, ERROR_OAUTH_CLIENT_REQUEST,
+    ERROR_TWO_FACTOR_CODE,
+};
+use crate::todo_api::model::{
+    auth::User,
+    error::DbError,
+    lockout::{BeginLogin, LoginAttempt},
+    oauth::{
+        DeleteClient, IssueAuthorizationCode, OAuthClient, ReadClient, ReadClients,
+        RedeemAuthorizationCode, RegisterClient,
+    },
+    refresh::RotateRefreshToken,
+    session::{ReadSession, RevokeSession, Session},
+    two_factor::{ReadTwoFactor, VerifySecondFactor},
+};
+use crate::todo_api_web::controller::auth::{
+    client_ip, login_failed, login_succeeded, release_login_attempt, upgrade_password_hash,
+};
+use crate::todo_api_web::controller::todo::request_actor;
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::{
+    auth::Auth,
+    http::{AuthError, Clients},
+    oauth::{
+        AuthorizeForm, AuthorizeQuery, ClientResponse, ClientsResponse, IntrospectionResponse,
+        OAuthTokenResponse, RegisterClientRequest, RegisteredClientResponse, TokenActionRequest,
+        TokenRequest,
+    },
+};
+
+use actix_web::{
+    delete, get,
+    http::{
+        header::{ContentType, CACHE_CONTROL, CONTENT_SECURITY_POLICY, LOCATION, X_FRAME_OPTIONS},
+        StatusCode,
+    },
+    post, web, HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+
+#[post("/oauth/clients", wrap = "RequireScope(ACCOUNT)")]
+pub async fn register_client(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<RegisterClientRequest>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let info = info.into_inner();
+    if !is_client_valid(&info.name, &info.redirect_uris) {
+        return HttpResponse::BadRequest().body(ERROR_OAUTH_CLIENT_REQUEST);
+    }
+
+    let register = RegisterClient {
+        owner_id: actor,
+        name: info.name,
+        redirect_uris: info.redirect_uris,
+        confidential: info.confidential,
+    };
+    match state.postgres.send(register).await {
+        Ok(Ok((client, client_secret))) => HttpResponse::Created()
+            .content_type(ContentType::json())
+            .json(RegisteredClientResponse {
+                details: ClientResponse::from(client),
+                client_secret,
+            }),
+        e => {
+            error!("Failed to register OAuth client {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[get("/oauth/clients", wrap = "RequireScope(ACCOUNT)")]
+pub async fn show_clients(req: HttpRequest, state: web::Data<Clients>) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+
+    match state.postgres.send(ReadClients { owner_id: actor }).await {
+        Ok(Ok(clients)) => {
+            HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .json(ClientsResponse {
+                    clients: clients.into_iter().map(ClientResponse::from).collect(),
+                })
+        }
+        e => {
+            error!("Failed to read OAuth clients {:?}", e);
+            HttpResponse::InternalServerError().body(ERROR_OAUTH_CLIENT_READ)
+        }
+    }
+}
+
+#[delete("/oauth/clients/{id}", wrap = "RequireScope(ACCOUNT)")]
+pub async fn remove_client(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<String>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let delete = DeleteClient {
+        id: id.into_inner(),
+        owner_id: actor,
+    };
+
+    match state.postgres.send(delete).await {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        Ok(Err(DbError::CannotFindClient)) => {
+            HttpResponse::NotFound().body(ERROR_OAUTH_CLIENT_NOT_FOUND)
+        }
+        e => {
+            error!("Failed to delete OAuth client {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Shows the login and consent page of an authorization code request. Only
+/// `S256` PKCE requests are accepted.
+#[get("/authorize")]
+pub async fn authorize(
+    state: web::Data<Clients>,
+    query: web::Query<AuthorizeQuery>,
+) -> impl Responder {
+    let request = query.into_inner();
+    let client = match authorization_client(&state, &request).await {
+        Err(description) => return html(StatusCode::BAD_REQUEST, error_page(description)),
+        Ok(client) => client,
+    };
+
+    match requested_scope(&request) {
+        Err((error, description)) => redirect_error(&request, error, description),
+        Ok(scope) => consent(StatusCode::OK, &client, &request, &scope, None),
+    }
+}
+
+/// Authenticates the user on the consent page and redirects back to the client
+/// with an authorization code, or with an error when access is denied.
+#[post("/authorize")]
+pub async fn approve_authorization(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    form: web::Form<AuthorizeForm>,
+) -> impl Responder {
+    let form = form.into_inner();
+    let request = form.request();
+    let client = match authorization_client(&state, &request).await {
+        Err(description) => return html(StatusCode::BAD_REQUEST, error_page(description)),
+        Ok(client) => client,
+    };
+    let scope = match requested_scope(&request) {
+        Err((error, description)) => return redirect_error(&request, error, description),
+        Ok(scope) => scope,
+    };
+    if form.decision != "approve" {
+        return redirect_error(&request, "access_denied", "The user denied access");
+    }
+
+    let user = match consenting_user(&req, &state, &form).await {
+        Err((status, message)) => return consent(status, &client, &request, &scope, Some(message)),
+        Ok(user) => user,
+    };
+    if let Some(missing) = missing_scope(&user_scopes(&user.email), &scope) {
+        return redirect_error(&request, "invalid_scope", missing);
+    }
+
+    let issue = IssueAuthorizationCode {
+        client_id: client.id,
+        user_id: user.id,
+        redirect_uri: request.redirect_uri.clone(),
+        scope,
+        code_challenge: request.code_challenge.clone().unwrap_or_default(),
+    };
+    match state.postgres.send(issue).await {
+        Ok(Ok(code)) => {
+            let mut params = vec![("code", code.as_str())];
+            if let Some(client_state) = request.state.as_deref() {
+                params.push(("state", client_state));
+            }
+            HttpResponse::Found()
+                .insert_header((LOCATION, redirect_with(&request.redirect_uri, &params)))
+                .finish()
+        }
+        e => {
+            error!("Failed to issue authorization code {:?}", e);
+            redirect_error(
+                &request,
+                "server_error",
+                "Failed to issue authorization code",
+            )
+        }
+    }
+}
+
+#[post("/token")]
+pub async fn token(state: web::Data<Clients>, form: web::Form<TokenRequest>) -> impl Responder {
+    let form = form.into_inner();
+    let client = match authenticated_client(&state, &form.client_id, &form.client_secret).await {
+        None => return oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", "Unknown client"),
+        Some(client) => client,
+    };
+
+    match form.grant_type.as_str() {
+        "authorization_code" => exchange_code(state, client, form).await,
+        "refresh_token" => refresh_grant(state, client, form).await,
+        _ => oauth_error(
+            StatusCode::BAD_REQUEST,
+            "unsupported_grant_type",
+            "Only authorization_code and refresh_token are supported",
+        ),
+    }
+}
+
+async fn exchange_code(
+    state: web::Data<Clients>,
+    client: OAuthClient,
+    form: TokenRequest,
+) -> HttpResponse {
+    let redeem = match (form.code, form.redirect_uri, form.code_verifier) {
+        (Some(code), Some(redirect_uri), Some(code_verifier)) => RedeemAuthorizationCode {
+            code,
+            client_id: client.id.clone(),
+            redirect_uri,
+            code_verifier,
+        },
+        _ => {
+            return oauth_error(
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+                "code, redirect_uri and code_verifier are required",
+            )
+        }
+    };
+
+    let (user, scope) = match state.postgres.send(redeem).await {
+        Ok(Ok(granted)) => granted,
+        Ok(Err(DbError::InvalidGrant)) => {
+            return oauth_error(
+                StatusCode::BAD_REQUEST,
+                "invalid_grant",
+                "Authorization code is invalid or expired",
+            )
+        }
+        e => {
+            error!("Failed to redeem authorization code {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let device = format!("oauth: {}", client.name);
+    match start_session(user, &device, Some(scope.clone()), Some(client.id), &state).await {
+        Some(jwt) => token_response(OAuthTokenResponse::new(jwt, scope)),
+        None => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// Refresh tokens can only be rotated by the client their session was granted to.
+async fn refresh_grant(
+    state: web::Data<Clients>,
+    client: OAuthClient,
+    form: TokenRequest,
+) -> HttpResponse {
+    let refresh_token = match form.refresh_token {
+        None => {
+            return oauth_error(
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+                "refresh_token is required",
+            )
+        }
+        Some(refresh_token) => refresh_token,
+    };
+    let invalid_grant = || {
+        oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_grant",
+            "Refresh token is invalid or expired",
+        )
+    };
+
+    match client_session(&state, ReadSession::RefreshToken(refresh_token.clone())).await {
+        Some(session) if session.client_id.as_deref() == Some(client.id.as_str()) => (),
+        _ => return invalid_grant(),
+    }
+    let rotated = match state
+        .postgres
+        .send(RotateRefreshToken {
+            token: refresh_token,
+        })
+        .await
+    {
+        Ok(Ok(rotated)) if rotated.user.is_active => rotated,
+        Ok(_) => return invalid_grant(),
+        Err(e) => {
+            error!("{:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let scope = rotated.scope.clone().unwrap_or_default();
+    match access_jwt(
+        rotated.user,
+        rotated.session_id,
+        rotated.token,
+        rotated.scope,
+        &state,
+    )
+    .await
+    {
+        Some(jwt) => token_response(OAuthTokenResponse::new(jwt, scope)),
+        None => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// RFC 7662 introspection of access tokens for authenticated clients.
+#[post("/introspect")]
+pub async fn introspect(
+    state: web::Data<Clients>,
+    form: web::Form<TokenActionRequest>,
+) -> impl Responder {
+    let form = form.into_inner();
+    if authenticated_client(&state, &form.client_id, &form.client_secret)
+        .await
+        .is_none()
+    {
+        return oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", "Unknown client");
+    }
+
+    let inactive = || {
+        HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(IntrospectionResponse::inactive())
+    };
+    let jwt = match jwt_from_token(&form.token) {
+        None => return inactive(),
+        Some(jwt) => jwt,
+    };
+    if let Some(jti) = jwt.jti {
+        if is_token_revoked(&state, jti).await {
+            return inactive();
+        }
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let client_id = match jwt.sid {
+        None => None,
+        Some(sid) => match client_session(&state, ReadSession::Id(sid)).await {
+            Some(session) if !session.revoked && session.expires_at > now => session.client_id,
+            _ => return inactive(),
+        },
+    };
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(IntrospectionResponse {
+            active: true,
+            scope: Some(jwt.granted_scopes()),
+            client_id,
+            username: Some(jwt.email),
+            sub: Some(jwt.id),
+            exp: Some(jwt.expires_at.timestamp()),
+            token_type: Some("Bearer".to_string()),
+        })
+}
+
+/// RFC 7009 revocation of access or refresh tokens, revoking the session they
+/// belong to. Unknown tokens are answered with a 200 as well.
+#[post("/revoke")]
+pub async fn revoke(
+    state: web::Data<Clients>,
+    form: web::Form<TokenActionRequest>,
+) -> impl Responder {
+    let form = form.into_inner();
+    let client = match authenticated_client(&state, &form.client_id, &form.client_secret).await {
+        None => return oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", "Unknown client"),
+        Some(client) => client,
+    };
+
+    let jwt = jwt_from_token(&form.token);
+    let read = match jwt.as_ref().map(|jwt| jwt.sid) {
+        Some(Some(sid)) => ReadSession::Id(sid),
+        Some(None) => return HttpResponse::Ok().finish(),
+        None => ReadSession::RefreshToken(form.token.clone()),
+    };
+    let session = match client_session(&state, read).await {
+        None => return HttpResponse::Ok().finish(),
+        Some(session) => session,
+    };
+    if session.client_id.as_deref() != Some(client.id.as_str()) {
+        return oauth_error(
+            StatusCode::BAD_REQUEST,
+            "unauthorized_client",
+            "Token was not issued to this client",
+        );
+    }
+
+    if let Some(jwt) = jwt {
+        if let Some(jti) = jwt.jti {
+            revoke_token(&state, jti, session.user_id, jwt.expires_at).await;
+        }
+    }
+    let revoke = RevokeSession {
+        id: session.id,
+        user_id: session.user_id,
+    };
+    match state.postgres.send(revoke).await {
+        Ok(Ok(_)) | Ok(Err(DbError::CannotFindSession)) => HttpResponse::Ok().finish(),
+        e => {
+            error!("Failed to revoke OAuth session {:?}", e);
+            HttpResponse::ServiceUnavailable().finish()
+        }
+    }
+}
+
+/// The client and redirect URI must be valid before anything is redirected.
+async fn authorization_client(
+    state: &web::Data<Clients>,
+    request: &AuthorizeQuery,
+) -> Result<OAuthClient, &'static str> {
+    let read = ReadClient {
+        id: request.client_id.clone(),
+    };
+    match state.postgres.send(read).await {
+        Ok(Ok(Some(client))) if client.allows_redirect(&request.redirect_uri) => Ok(client),
+        Ok(Ok(Some(_))) => Err("The redirect URI is not registered for this client"),
+        Ok(Ok(None)) => Err("Unknown client"),
+        e => {
+            error!("Failed to read OAuth client {:?}", e);
+            Err("Failed to read client, try again later")
+        }
+    }
+}
+
+fn requested_scope(request: &AuthorizeQuery) -> Result<String, (&'static str, &'static str)> {
+    if request.response_type != "code" {
+        return Err((
+            "unsupported_response_type",
+            "Only the code response type is supported",
+        ));
+    }
+    if request
+        .code_challenge
+        .as_deref()
+        .unwrap_or_default()
+        .is_empty()
+        || request.code_challenge_method.as_deref() != Some(PKCE_METHOD)
+    {
+        return Err(("invalid_request", "PKCE with the S256 method is required"));
+    }
+
+    let scopes = request
+        .scope
+        .as_deref()
+        .unwrap_or(DEFAULT_OAUTH_SCOPE)
+        .split_whitespace()
+        .map(String::from)
+        .collect::<Vec<String>>();
+    normalize_scopes(&scopes).ok_or(("invalid_scope", "Unknown scope requested"))
+}
+
+/// Password login of the consent page, with the same throttling, email
+/// verification and two-factor rules as `/auth/login`.
+async fn consenting_user(
+    req: &HttpRequest,
+    state: &web::Data<Clients>,
+    form: &AuthorizeForm,
+) -> Result<User, (StatusCode, &'static str)> {
+    let ip = client_ip(req);
+    let begin = BeginLogin {
+        email: form.email.clone(),
+        ip: ip.clone(),
+    };
+    let locked = match state.postgres.send(begin).await {
+        Ok(Ok(LoginAttempt::Counted(locked))) => locked,
+        Ok(Ok(LoginAttempt::Throttled(_))) => {
+            return Err((StatusCode::TOO_MANY_REQUESTS, ERROR_LOGIN_THROTTLED))
+        }
+        e => {
+            error!("Failed to count login attempt {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, ERROR_INVALID_CREDENTIALS));
+        }
+    };
+
+    let lookup = Auth {
+        email: form.email.clone(),
+        password: None,
+    };
+    let user = match state.postgres.send(lookup).await {
+        Ok(Ok(user)) if user.verify(form.password.clone()) => {
+            upgrade_password_hash(state, &user, form.password.clone()).await;
+            user
+        }
+        Ok(Ok(user)) => {
+            login_failed(state, &user.email, ip, Some(user.id), locked).await;
+            return Err((StatusCode::UNAUTHORIZED, ERROR_INVALID_CREDENTIALS));
+        }
+        _ => {
+            login_failed(state, &form.email, ip, None, locked).await;
+            return Err((StatusCode::UNAUTHORIZED, ERROR_INVALID_CREDENTIALS));
+        }
+    };
+    if !user.email_verified {
+        release_login_attempt(state, &user.email, ip).await;
+        return Err((StatusCode::FORBIDDEN, ERROR_EMAIL_NOT_VERIFIED));
+    }
+
+    match state
+        .postgres
+        .send(ReadTwoFactor { user_id: user.id })
+        .await
+    {
+        Ok(Ok(Some(two_factor))) if two_factor.enabled => {
+            let verify = VerifySecondFactor {
+                user_id: user.id,
+                code: form.otp.clone(),
+            };
+            if !matches!(state.postgres.send(verify).await, Ok(Ok(_))) {
+                login_failed(state, &user.email, ip, Some(user.id), locked).await;
+                return Err((StatusCode::UNAUTHORIZED, ERROR_TWO_FACTOR_CODE));
+            }
+        }
+        Ok(Ok(_)) => (),
+        e => {
+            error!("Failed to read two-factor settings {:?}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, ERROR_INVALID_CREDENTIALS));
+        }
+    }
+
+    login_succeeded(state, &user.email, ip).await;
+    Ok(user)
+}
+
+async fn authenticated_client(
+    state: &web::Data<Clients>,
+    client_id: &str,
+    client_secret: &Option<String>,
+) -> Option<OAuthClient> {
+    let read = ReadClient {
+        id: client_id.to_string(),
+    };
+    match state.postgres.send(read).await {
+        Ok(Ok(Some(client))) if client.authenticate(client_secret.as_deref()) => Some(client),
+        Ok(_) => None,
+        Err(e) => {
+            error!("Failed to read OAuth client {:?}", e);
+            None
+        }
+    }
+}
+
+async fn client_session(state: &web::Data<Clients>, read: ReadSession) -> Option<Session> {
+    match state.postgres.send(read).await {
+        Ok(Ok(session)) => session,
+        e => {
+            error!("Failed to read session {:?}", e);
+            None
+        }
+    }
+}
+
+fn consent(
+    status: StatusCode,
+    client: &OAuthClient,
+    request: &AuthorizeQuery,
+    scope: &str,
+    error: Option<&str>,
+) -> HttpResponse {
+    let fields = [
+        ("response_type", request.response_type.as_str()),
+        ("client_id", request.client_id.as_str()),
+        ("redirect_uri", request.redirect_uri.as_str()),
+        ("scope", scope),
+        ("state", request.state.as_deref().unwrap_or_default()),
+        (
+            "code_challenge",
+            request.code_challenge.as_deref().unwrap_or_default(),
+        ),
+        (
+            "code_challenge_method",
+            request.code_challenge_method.as_deref().unwrap_or_default(),
+        ),
+    ];
+    html(status, consent_page(&client.name, scope, &fields, error))
+}
+
+/// Pages served here carry the consent form, they must never be framed by
+/// another origin.
+fn html(status: StatusCode, body: String) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(ContentType::html())
+        .insert_header((X_FRAME_OPTIONS, "DENY"))
+        .insert_header((CONTENT_SECURITY_POLICY, "frame-ancestors 'none'"))
+        .body(body)
+}
+
+fn redirect_error(request: &AuthorizeQuery, error: &str, description: &str) -> HttpResponse {
+    let mut params = vec![("error", error), ("error_description", description)];
+    if let Some(client_state) = request.state.as_deref() {
+        params.push(("state", client_state));
+    }
+    HttpResponse::Found()
+        .insert_header((LOCATION, redirect_with(&request.redirect_uri, &params)))
+        .finish()
+}
+
+fn oauth_error(status: StatusCode, error: &str, description: &str) -> HttpResponse {
+    HttpResponse::build(status)
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .content_type(ContentType::json())
+        .json(AuthError::new(error, description))
+}
+
+fn token_response(response: OAuthTokenResponse) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .content_type(ContentType::json())
+        .json(response)
+}
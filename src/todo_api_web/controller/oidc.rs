@@ -0,0 +1,135 @@
+use crate::todo_api::{
+    core::generate_jwt,
+    db::helpers::{
+        ERROR_OIDC_DISABLED, ERROR_OIDC_EMAIL_NOT_VERIFIED, ERROR_OIDC_PROVIDER, ERROR_OIDC_STATE,
+    },
+    model::{
+        error::DbError,
+        oidc::{FinishOidcLogin, LinkExternalIdentity, StartOidcLogin},
+    },
+    oidc::OidcError,
+};
+use crate::todo_api_web::controller::auth::{device_label, two_factor_challenge};
+use crate::todo_api_web::model::{
+    http::{AuthError, Clients},
+    oidc::OidcCallbackQuery,
+};
+
+use actix_web::{
+    get,
+    http::header::{ContentType, LOCATION},
+    web, HttpRequest, HttpResponse, Responder,
+};
+use log::{error, warn};
+
+/// Redirects the browser to the OpenID provider with a fresh state, nonce and
+/// PKCE challenge.
+#[get("/oidc/login")]
+pub async fn oidc_login(state: web::Data<Clients>) -> impl Responder {
+    let provider = match &state.oidc {
+        None => return HttpResponse::NotFound().body(ERROR_OIDC_DISABLED),
+        Some(provider) => provider.clone(),
+    };
+
+    let (login, login_state) = match state.postgres.send(StartOidcLogin).await {
+        Ok(Ok(started)) => started,
+        e => {
+            error!("Failed to start OpenID login {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match provider
+        .authorization_url(&login_state, &login.nonce, &login.code_verifier)
+        .await
+    {
+        Ok(url) => HttpResponse::Found()
+            .insert_header((LOCATION, url))
+            .finish(),
+        Err(e) => {
+            error!("{}", e);
+            HttpResponse::BadGateway().body(ERROR_OIDC_PROVIDER)
+        }
+    }
+}
+
+/// Completes the login when the provider redirects back, answering like
+/// `/auth/login`, including the two-factor challenge of accounts that
+/// enabled it.
+#[get("/oidc/callback")]
+pub async fn oidc_callback(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    query: web::Query<OidcCallbackQuery>,
+) -> impl Responder {
+    let provider = match &state.oidc {
+        None => return HttpResponse::NotFound().body(ERROR_OIDC_DISABLED),
+        Some(provider) => provider.clone(),
+    };
+    let query = query.into_inner();
+
+    let finish = FinishOidcLogin { state: query.state };
+    let login = match state.postgres.send(finish).await {
+        Ok(Ok(login)) => login,
+        Ok(Err(DbError::InvalidOidcState)) => {
+            return HttpResponse::BadRequest().body(ERROR_OIDC_STATE)
+        }
+        e => {
+            error!("Failed to read OpenID login {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let code = match (query.code, query.error) {
+        (Some(code), None) => code,
+        (_, error) => {
+            return HttpResponse::Unauthorized()
+                .content_type(ContentType::json())
+                .json(AuthError::new(
+                    error.as_deref().unwrap_or("invalid_request"),
+                    query
+                        .error_description
+                        .as_deref()
+                        .unwrap_or("The provider returned no code"),
+                ))
+        }
+    };
+
+    let claims = match provider
+        .exchange_code(&code, &login.code_verifier, &login.nonce)
+        .await
+    {
+        Ok(claims) => claims,
+        Err(e @ (OidcError::InvalidIdToken(_) | OidcError::UnknownKey)) => {
+            warn!("{}", e);
+            return HttpResponse::Unauthorized().body(ERROR_OIDC_PROVIDER);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return HttpResponse::BadGateway().body(ERROR_OIDC_PROVIDER);
+        }
+    };
+    let email_verified = claims.is_email_verified();
+    let email = match claims.email {
+        None => return HttpResponse::Forbidden().body(ERROR_OIDC_EMAIL_NOT_VERIFIED),
+        Some(email) => email,
+    };
+
+    let link = LinkExternalIdentity {
+        issuer: claims.iss,
+        subject: claims.sub,
+        email,
+        email_verified,
+    };
+    match state.postgres.send(link).await {
+        Ok(Ok(user)) => match two_factor_challenge(&state, &user).await {
+            Some(challenge) => challenge,
+            None => generate_jwt(user, &device_label(&req), state).await,
+        },
+        Ok(Err(DbError::ExternalEmailNotVerified)) => {
+            HttpResponse::Forbidden().body(ERROR_OIDC_EMAIL_NOT_VERIFIED)
+        }
+        e => {
+            error!("Failed to link external identity {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
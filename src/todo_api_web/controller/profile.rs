@@ -0,0 +1,256 @@
+use crate::todo_api::core::{
+    jwt_from_request,
+    scope::ACCOUNT,
+    validation::{display_name_errors, email_errors, password_policy, timezone_errors, FieldError},
+    verification::verification_email,
+};
+use crate::todo_api::db::helpers::{
+    ERROR_CURRENT_PASSWORD, ERROR_EMAIL_TAKEN, ERROR_PROFILE_READ, ERROR_VERIFICATION_THROTTLED,
+};
+use crate::todo_api::model::{
+    auth::User,
+    core::JwtValue,
+    error::DbError,
+    lockout::{BeginLogin, LoginAttempt},
+    profile::{ChangePassword, RequestEmailChange, UpdateProfile},
+};
+use crate::todo_api_web::controller::auth::{
+    client_ip, login_failed, release_login_attempt, send_email, too_many_attempts,
+};
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::{
+    auth::Auth,
+    http::{Clients, ValidationErrors},
+    profile::{ChangeEmailRequest, ChangePasswordRequest, ProfileResponse, UpdateProfileRequest},
+};
+
+use actix::MailboxError;
+use actix_web::{
+    get, http::header::ContentType, patch, post, web, HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+
+#[get("", wrap = "RequireScope(ACCOUNT)")]
+pub async fn show_profile(req: HttpRequest, state: web::Data<Clients>) -> impl Responder {
+    let jwt = match jwt_from_request(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(jwt) => jwt,
+    };
+
+    match account(&state, &jwt).await {
+        Ok(Some(user)) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(ProfileResponse::from(user)),
+        Ok(None) => HttpResponse::Unauthorized().finish(),
+        Err(e) => {
+            error!("Failed to read profile {:?}", e);
+            HttpResponse::InternalServerError().body(ERROR_PROFILE_READ)
+        }
+    }
+}
+
+#[patch("", wrap = "RequireScope(ACCOUNT)")]
+pub async fn update_profile(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<UpdateProfileRequest>,
+) -> impl Responder {
+    let jwt = match jwt_from_request(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(jwt) => jwt,
+    };
+    let info = info.into_inner();
+    let mut errors = info
+        .display_name
+        .as_deref()
+        .map(display_name_errors)
+        .unwrap_or_default();
+    errors.extend(
+        info.timezone
+            .as_deref()
+            .map(timezone_errors)
+            .unwrap_or_default(),
+    );
+    if !errors.is_empty() {
+        return HttpResponse::BadRequest()
+            .content_type(ContentType::json())
+            .json(ValidationErrors { errors });
+    }
+
+    let user = match account(&state, &jwt).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().finish(),
+        Err(e) => {
+            error!("Failed to read profile {:?}", e);
+            return HttpResponse::InternalServerError().body(ERROR_PROFILE_READ);
+        }
+    };
+    let update = UpdateProfile {
+        user_id: user.id,
+        display_name: match info.display_name {
+            None => user.display_name,
+            Some(name) if name.trim().is_empty() => None,
+            Some(name) => Some(name.trim().to_string()),
+        },
+        timezone: info.timezone.unwrap_or(user.timezone),
+    };
+
+    match state.postgres.send(update).await {
+        Ok(Ok(user)) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(ProfileResponse::from(user)),
+        Ok(Err(DbError::CannotFindUser)) => HttpResponse::Unauthorized().finish(),
+        e => {
+            error!("Failed to update profile {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Sends a verification link to the new address, which replaces the current
+/// one and signs out every session once followed.
+#[post("/email", wrap = "RequireScope(ACCOUNT)")]
+pub async fn change_email(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<ChangeEmailRequest>,
+) -> impl Responder {
+    let jwt = match jwt_from_request(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(jwt) => jwt,
+    };
+    let info = info.into_inner();
+    let mut errors = email_errors(&info.email);
+    if info.email == jwt.email {
+        errors.push(FieldError::new(
+            "email",
+            "unchanged",
+            "Email is already the account email",
+        ));
+    }
+    if !errors.is_empty() {
+        return HttpResponse::BadRequest()
+            .content_type(ContentType::json())
+            .json(ValidationErrors { errors });
+    }
+
+    let user = match account(&state, &jwt).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().finish(),
+        Err(e) => {
+            error!("Failed to read profile {:?}", e);
+            return HttpResponse::InternalServerError().body(ERROR_PROFILE_READ);
+        }
+    };
+    if let Some(rejection) = confirm_password(&req, &state, &user, info.password).await {
+        return rejection;
+    }
+
+    let change = RequestEmailChange {
+        user_id: user.id,
+        email: info.email.clone(),
+    };
+    match state.postgres.send(change).await {
+        Ok(Ok(token)) => {
+            send_email(&state, verification_email(&info.email, &token)).await;
+            HttpResponse::Accepted().finish()
+        }
+        Ok(Err(DbError::EmailTaken)) => HttpResponse::Conflict().body(ERROR_EMAIL_TAKEN),
+        Ok(Err(DbError::VerificationThrottled)) => {
+            HttpResponse::TooManyRequests().body(ERROR_VERIFICATION_THROTTLED)
+        }
+        e => {
+            error!("Failed to request email change {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Every other session is revoked, the one making the request stays signed in.
+#[post("/password", wrap = "RequireScope(ACCOUNT)")]
+pub async fn change_password(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<ChangePasswordRequest>,
+) -> impl Responder {
+    let jwt = match jwt_from_request(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(jwt) => jwt,
+    };
+    let info = info.into_inner();
+    let errors = password_policy().check(&info.new_password, Some(&jwt.email));
+    if !errors.is_empty() {
+        return HttpResponse::BadRequest()
+            .content_type(ContentType::json())
+            .json(ValidationErrors { errors });
+    }
+
+    let user = match account(&state, &jwt).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().finish(),
+        Err(e) => {
+            error!("Failed to read profile {:?}", e);
+            return HttpResponse::InternalServerError().body(ERROR_PROFILE_READ);
+        }
+    };
+    if let Some(rejection) = confirm_password(&req, &state, &user, info.current_password).await {
+        return rejection;
+    }
+
+    let change = ChangePassword {
+        user_id: user.id,
+        password: info.new_password,
+        keep_session: jwt.sid,
+    };
+    match state.postgres.send(change).await {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        Ok(Err(DbError::CannotFindUser)) => HttpResponse::Unauthorized().finish(),
+        e => {
+            error!("Failed to change password {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Account the token was issued to, `None` once its email or id changed.
+async fn account(state: &web::Data<Clients>, jwt: &JwtValue) -> Result<Option<User>, MailboxError> {
+    let lookup = Auth {
+        email: jwt.email.clone(),
+        password: None,
+    };
+
+    match state.postgres.send(lookup).await? {
+        Ok(user) if user.id.to_string() == jwt.id => Ok(Some(user)),
+        _ => Ok(None),
+    }
+}
+
+/// Checks the current password under the same throttling as login, wrong
+/// guesses count towards the account lockout.
+pub(crate) async fn confirm_password(
+    req: &HttpRequest,
+    state: &web::Data<Clients>,
+    user: &User,
+    password: String,
+) -> Option<HttpResponse> {
+    let ip = client_ip(req);
+    let begin = BeginLogin {
+        email: user.email.clone(),
+        ip: ip.clone(),
+    };
+    let locked = match state.postgres.send(begin).await {
+        Ok(Ok(LoginAttempt::Counted(locked))) => locked,
+        Ok(Ok(LoginAttempt::Throttled(retry_at))) => return Some(too_many_attempts(retry_at)),
+        e => {
+            error!("Failed to count login attempt {:?}", e);
+            return Some(HttpResponse::InternalServerError().finish());
+        }
+    };
+
+    if user.verify(password) {
+        release_login_attempt(state, &user.email, ip).await;
+        return None;
+    }
+    login_failed(state, &user.email, ip, Some(user.id), locked).await;
+    Some(HttpResponse::Forbidden().body(ERROR_CURRENT_PASSWORD))
+}
@@ -0,0 +1,70 @@
+use crate::todo_api::core::jwt_from_request;
+use crate::todo_api::core::scope::ACCOUNT;
+use crate::todo_api::db::helpers::{ERROR_SESSION_NOT_FOUND, ERROR_SESSION_READ};
+use crate::todo_api::model::{
+    error::DbError,
+    session::{ReadSessions, RevokeSession},
+};
+use crate::todo_api_web::controller::todo::request_actor;
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::http::Clients;
+use crate::todo_api_web::model::session::{SessionResponse, SessionsResponse};
+
+use actix_web::{
+    delete, get, http::header::ContentType, web, HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+use uuid::Uuid;
+
+#[get("/sessions", wrap = "RequireScope(ACCOUNT)")]
+pub async fn show_sessions(req: HttpRequest, state: web::Data<Clients>) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let current = jwt_from_request(&req).and_then(|jwt| jwt.sid);
+
+    match state.postgres.send(ReadSessions { user_id: actor }).await {
+        Ok(Ok(sessions)) => {
+            HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .json(SessionsResponse {
+                    sessions: sessions
+                        .into_iter()
+                        .map(|session| SessionResponse::new(session, current))
+                        .collect(),
+                })
+        }
+        e => {
+            error!("Failed to read sessions {:?}", e);
+            HttpResponse::InternalServerError().body(ERROR_SESSION_READ)
+        }
+    }
+}
+
+#[delete("/sessions/{id}", wrap = "RequireScope(ACCOUNT)")]
+pub async fn revoke_session(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let revoke = RevokeSession {
+        id: id.into_inner(),
+        user_id: actor,
+    };
+
+    match state.postgres.send(revoke).await {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        Ok(Err(DbError::CannotFindSession)) => {
+            HttpResponse::NotFound().body(ERROR_SESSION_NOT_FOUND)
+        }
+        e => {
+            error!("Failed to revoke session {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::todo_api::core::scope::TODO_READ;
+use crate::todo_api::core::stats::card_stats;
+use crate::todo_api::db::helpers::ERROR_READ;
+use crate::todo_api::db::todo::get_owner_todos;
+use crate::todo_api_web::controller::todo::request_actor;
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::{http::Clients, stats::StatsQuery};
+
+use actix_web::{get, http::header::ContentType, web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use log::error;
+
+#[get("/stats", wrap = "RequireScope(TODO_READ)")]
+pub async fn show_stats(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    query: web::Query<StatsQuery>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let client = state.dynamo.clone();
+    match get_owner_todos(&client, actor).await {
+        None => {
+            error!("Failed to read todo cards for stats");
+            HttpResponse::InternalServerError().body(ERROR_READ)
+        }
+        Some(cards) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(card_stats(&cards, query.window(), Utc::now().date_naive())),
+    }
+}
@@ -0,0 +1,204 @@
+use crate::todo_api::adapter::{self, template::template_to_db};
+use crate::todo_api::core::scope::{TODO_READ, TODO_WRITE};
+use crate::todo_api::core::template::instantiate;
+use crate::todo_api::db::helpers::{
+    ERROR_CREATE, ERROR_TEMPLATE_CREATE, ERROR_TEMPLATE_DELETE, ERROR_TEMPLATE_NOT_FOUND,
+    ERROR_TEMPLATE_READ, ERROR_TEMPLATE_UPDATE,
+};
+use crate::todo_api::db::template::{
+    delete_template, get_owner_templates, get_template, put_template,
+};
+use crate::todo_api::db::todo::put_todo;
+use crate::todo_api::model::history::HistoryAction;
+use crate::todo_api_web::controller::todo::{
+    card_owner, record_history, request_actor, with_undo_token,
+};
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::http::Clients;
+use crate::todo_api_web::model::template::{
+    InstantiateTemplate, MissingVariables, TemplatesResponse, TodoTemplate,
+};
+use crate::todo_api_web::model::todo::TodoIdResponse;
+
+use actix_web::{
+    delete, get, http::header::ContentType, post, put, web, HttpRequest, HttpResponse, Responder,
+};
+use log::error;
+use uuid::Uuid;
+
+#[post("/templates", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn create_template(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<TodoTemplate>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = Uuid::new_v4();
+    let client = state.dynamo.clone();
+
+    match put_template(&client, template_to_db(info.into_inner(), id, actor)).await {
+        None => {
+            error!("Failed to create template {}", ERROR_TEMPLATE_CREATE);
+            HttpResponse::BadRequest().body(ERROR_TEMPLATE_CREATE)
+        }
+        Some(id) => HttpResponse::Created()
+            .content_type(ContentType::json())
+            .json(TodoIdResponse::new(id)),
+    }
+}
+
+#[get("/templates", wrap = "RequireScope(TODO_READ)")]
+pub async fn show_all_templates(req: HttpRequest, state: web::Data<Clients>) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let client = state.dynamo.clone();
+    match get_owner_templates(&client, actor).await {
+        None => {
+            error!("Failed to read templates");
+            HttpResponse::InternalServerError().body(ERROR_TEMPLATE_READ)
+        }
+        Some(templates) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(TemplatesResponse { templates }),
+    }
+}
+
+#[get("/templates/{id}", wrap = "RequireScope(TODO_READ)")]
+pub async fn show_template(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let client = state.dynamo.clone();
+    match get_template(&client, id.into_inner()).await {
+        None => HttpResponse::NotFound().body(ERROR_TEMPLATE_NOT_FOUND),
+        Some(template) if template.owner != Some(actor) => HttpResponse::Forbidden().finish(),
+        Some(template) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(template),
+    }
+}
+
+#[put("/templates/{id}", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn update_template(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+    info: web::Json<TodoTemplate>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = id.into_inner();
+    let client = state.dynamo.clone();
+    match get_template(&client, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_TEMPLATE_NOT_FOUND),
+        Some(template) if template.owner != Some(actor) => {
+            return HttpResponse::Forbidden().finish()
+        }
+        Some(_) => (),
+    };
+
+    let mut template = info.into_inner();
+    template.id = Some(id);
+    template.owner = Some(actor);
+    match put_template(&client, template_to_db(template.clone(), id, actor)).await {
+        None => {
+            error!("Failed to update template {}", id);
+            HttpResponse::InternalServerError().body(ERROR_TEMPLATE_UPDATE)
+        }
+        Some(_) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(template),
+    }
+}
+
+#[delete("/templates/{id}", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn remove_template(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = id.into_inner();
+    let client = state.dynamo.clone();
+    match get_template(&client, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_TEMPLATE_NOT_FOUND),
+        Some(template) if template.owner != Some(actor) => {
+            return HttpResponse::Forbidden().finish()
+        }
+        Some(_) => (),
+    };
+
+    match delete_template(&client, id).await {
+        None => {
+            error!("Failed to delete template {}", id);
+            HttpResponse::InternalServerError().body(ERROR_TEMPLATE_DELETE)
+        }
+        Some(_) => HttpResponse::NoContent().finish(),
+    }
+}
+
+#[post("/templates/{id}/instantiate", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn instantiate_template(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+    info: web::Json<InstantiateTemplate>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let client = state.dynamo.clone();
+    let template = match get_template(&client, id.into_inner()).await {
+        None => return HttpResponse::NotFound().body(ERROR_TEMPLATE_NOT_FOUND),
+        Some(template) if template.owner != Some(actor) => {
+            return HttpResponse::Forbidden().finish()
+        }
+        Some(template) => template,
+    };
+
+    let info = info.into_inner();
+    let owner = match card_owner(&req, info.owner, actor) {
+        None => return HttpResponse::Forbidden().finish(),
+        Some(owner) => owner,
+    };
+    let card = match instantiate(&template, owner, &info.variables) {
+        Err(missing) => {
+            return HttpResponse::BadRequest()
+                .content_type(ContentType::json())
+                .json(MissingVariables { missing })
+        }
+        Ok(card) => card,
+    };
+
+    let id = Uuid::new_v4();
+    let card = adapter::new_todo_card(card, id);
+    match put_todo(&client, adapter::todo_card_to_db(card.clone(), id)).await {
+        None => {
+            error!("Failed to create todo card from template {}", ERROR_CREATE);
+            HttpResponse::InternalServerError().body(ERROR_CREATE)
+        }
+        Some(id) => {
+            let token =
+                record_history(&state, id, actor, HistoryAction::Create, None, Some(&card)).await;
+            with_undo_token(HttpResponse::Created(), token)
+                .content_type(ContentType::json())
+                .json(TodoIdResponse::new(id))
+        }
+    }
+}
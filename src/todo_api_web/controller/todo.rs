@@ -1,35 +1,69 @@
 use crate::todo_api::adapter;
-use crate::todo_api::db::helpers::{ERROR_CREATE, ERROR_READ};
-use crate::todo_api::db::todo::{get_todos, put_todo};
-use crate::todo_api_web::model::http::Clients;
-use crate::todo_api_web::model::todo::{TodoCard, TodoCardsResponse, TodoIdResponse};
+use crate::todo_api::core::jwt_from_request;
+use crate::todo_api::core::scope::{has_scope, ADMIN, TODO_READ, TODO_WRITE};
+use crate::todo_api::db::helpers::{
+    ERROR_COLLABORATOR_NOT_FOUND, ERROR_CREATE, ERROR_DELETE, ERROR_HISTORY, ERROR_NOT_FOUND,
+    ERROR_READ, ERROR_SHARE_OWNER, ERROR_UPDATE, ERROR_USER_NOT_FOUND,
+};
+use crate::todo_api::db::todo::{delete_todo, get_readable_todos, get_todo, put_todo};
+use crate::todo_api::model::history::{
+    CardHistoryEntry, HistoryAction, ReadHistory, RecordHistory,
+};
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::todo::{
+    CardHistoryResponse, DuplicateCard, ShareCard, StateTransition, TodoCard, TodoCardsResponse,
+    TodoIdResponse, TransferCard,
+};
+use crate::todo_api_web::model::undo::UNDO_TOKEN_HEADER;
+use crate::todo_api_web::model::{auth::Auth, http::Clients};
 
-use actix_web::get;
+use actix_web::{delete, get, put, HttpRequest, HttpResponseBuilder};
 use actix_web::{http::header::ContentType, post, web, HttpResponse, Responder};
+use chrono::Utc;
 use log::error;
 use uuid::Uuid;
 
-#[post("/create")]
-pub async fn create_todo(state: web::Data<Clients>, info: web::Json<TodoCard>) -> impl Responder {
+#[post("/create", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn create_todo(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<TodoCard>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let info = info.into_inner();
+    if card_owner(&req, Some(info.owner), actor).is_none() {
+        return HttpResponse::Forbidden().finish();
+    }
     let id = Uuid::new_v4();
-    let todo_card = adapter::todo_json_to_db(info, id);
+    let card = adapter::new_todo_card(info, id);
     let client = state.dynamo.clone();
 
-    match put_todo(&client, todo_card).await {
+    match put_todo(&client, adapter::todo_card_to_db(card.clone(), id)).await {
         None => {
             error!("Failed to create todo card {}", ERROR_CREATE);
             HttpResponse::BadRequest().body(ERROR_CREATE)
         }
-        Some(id) => HttpResponse::Created()
-            .content_type(ContentType::json())
-            .json(TodoIdResponse::new(id)),
+        Some(id) => {
+            let token =
+                record_history(&state, id, actor, HistoryAction::Create, None, Some(&card)).await;
+            with_undo_token(HttpResponse::Created(), token)
+                .content_type(ContentType::json())
+                .json(TodoIdResponse::new(id))
+        }
     }
 }
 
-#[get("/index")]
-pub async fn show_all_todo(state: web::Data<Clients>) -> impl Responder {
+#[get("/index", wrap = "RequireScope(TODO_READ)")]
+pub async fn show_all_todo(req: HttpRequest, state: web::Data<Clients>) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
     let client = state.dynamo.clone();
-    let resp = get_todos(&client).await;
+    let resp = get_readable_todos(&client, actor).await;
     match resp {
         None => {
             error!("Failed to read todo cards");
@@ -40,3 +74,396 @@ pub async fn show_all_todo(state: web::Data<Clients>) -> impl Responder {
             .json(TodoCardsResponse { cards }),
     }
 }
+
+#[put("/todo/{id}", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn update_todo(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+    info: web::Json<TodoCard>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = id.into_inner();
+    let client = state.dynamo.clone();
+    let before = match get_todo(&client, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_NOT_FOUND),
+        Some(card) if !card.can_edit(actor) => return HttpResponse::Forbidden().finish(),
+        Some(card) => card,
+    };
+
+    let mut card = before.clone();
+    card.apply_update(info.into_inner(), Utc::now().naive_utc());
+    match put_todo(&client, adapter::todo_card_to_db(card.clone(), id)).await {
+        None => {
+            error!("Failed to update todo card {}", id);
+            HttpResponse::InternalServerError().body(ERROR_UPDATE)
+        }
+        Some(_) => {
+            let token = record_history(
+                &state,
+                id,
+                actor,
+                HistoryAction::Update,
+                Some(&before),
+                Some(&card),
+            )
+            .await;
+            with_undo_token(HttpResponse::Ok(), token)
+                .content_type(ContentType::json())
+                .json(card)
+        }
+    }
+}
+
+#[put("/todo/{id}/state", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn update_todo_state(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+    info: web::Json<StateTransition>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = id.into_inner();
+    let client = state.dynamo.clone();
+    let before = match get_todo(&client, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_NOT_FOUND),
+        Some(card) if !card.can_edit(actor) => return HttpResponse::Forbidden().finish(),
+        Some(card) => card,
+    };
+
+    let mut card = before.clone();
+    card.transition(info.into_inner().state, Utc::now().naive_utc());
+    match put_todo(&client, adapter::todo_card_to_db(card.clone(), id)).await {
+        None => {
+            error!("Failed to update todo card {}", id);
+            HttpResponse::InternalServerError().body(ERROR_UPDATE)
+        }
+        Some(_) => {
+            let token = record_history(
+                &state,
+                id,
+                actor,
+                HistoryAction::Transition,
+                Some(&before),
+                Some(&card),
+            )
+            .await;
+            with_undo_token(HttpResponse::Ok(), token)
+                .content_type(ContentType::json())
+                .json(card)
+        }
+    }
+}
+
+#[delete("/todo/{id}", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn remove_todo(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = id.into_inner();
+    let client = state.dynamo.clone();
+    let before = match get_todo(&client, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_NOT_FOUND),
+        Some(card) if card.owner != actor => return HttpResponse::Forbidden().finish(),
+        Some(card) => card,
+    };
+
+    match delete_todo(&client, id).await {
+        None => {
+            error!("Failed to delete todo card {}", id);
+            HttpResponse::InternalServerError().body(ERROR_DELETE)
+        }
+        Some(_) => {
+            let token = record_history(
+                &state,
+                id,
+                actor,
+                HistoryAction::Delete,
+                Some(&before),
+                None,
+            )
+            .await;
+            with_undo_token(HttpResponse::NoContent(), token).finish()
+        }
+    }
+}
+
+#[post("/todo/{id}/duplicate", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn duplicate_todo(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+    info: web::Json<DuplicateCard>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let client = state.dynamo.clone();
+    let original = match get_todo(&client, id.into_inner()).await {
+        None => return HttpResponse::NotFound().body(ERROR_NOT_FOUND),
+        Some(card) if !card.can_read(actor) => return HttpResponse::Forbidden().finish(),
+        Some(card) => card,
+    };
+
+    let id = Uuid::new_v4();
+    let card = adapter::duplicated_todo_card(original.duplicate(actor, info.keep_progress), id);
+    match put_todo(&client, adapter::todo_card_to_db(card.clone(), id)).await {
+        None => {
+            error!("Failed to duplicate todo card {}", ERROR_CREATE);
+            HttpResponse::InternalServerError().body(ERROR_CREATE)
+        }
+        Some(id) => {
+            let token =
+                record_history(&state, id, actor, HistoryAction::Create, None, Some(&card)).await;
+            with_undo_token(HttpResponse::Created(), token)
+                .content_type(ContentType::json())
+                .json(TodoIdResponse::new(id))
+        }
+    }
+}
+
+#[post("/todo/{id}/transfer", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn transfer_todo(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+    info: web::Json<TransferCard>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = id.into_inner();
+    let client = state.dynamo.clone();
+    let before = match get_todo(&client, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_NOT_FOUND),
+        Some(card) if card.owner != actor => return HttpResponse::Forbidden().finish(),
+        Some(card) => card,
+    };
+
+    let target = Auth {
+        email: info.into_inner().email,
+        password: None,
+    };
+    let target = match state.postgres.send(target).await {
+        Ok(Ok(user)) => user.id,
+        _ => return HttpResponse::NotFound().body(ERROR_USER_NOT_FOUND),
+    };
+
+    let mut card = before.clone();
+    card.transfer(target, Utc::now().naive_utc());
+    match put_todo(&client, adapter::todo_card_to_db(card.clone(), id)).await {
+        None => {
+            error!("Failed to transfer todo card {}", id);
+            HttpResponse::InternalServerError().body(ERROR_UPDATE)
+        }
+        Some(_) => {
+            let token = record_history(
+                &state,
+                id,
+                actor,
+                HistoryAction::Transfer,
+                Some(&before),
+                Some(&card),
+            )
+            .await;
+            with_undo_token(HttpResponse::Ok(), token)
+                .content_type(ContentType::json())
+                .json(card)
+        }
+    }
+}
+
+#[post("/todo/{id}/share", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn share_todo(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+    info: web::Json<ShareCard>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = id.into_inner();
+    let client = state.dynamo.clone();
+    let before = match get_todo(&client, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_NOT_FOUND),
+        Some(card) if card.owner != actor => return HttpResponse::Forbidden().finish(),
+        Some(card) => card,
+    };
+
+    let info = info.into_inner();
+    let target = Auth {
+        email: info.email,
+        password: None,
+    };
+    let target = match state.postgres.send(target).await {
+        Ok(Ok(user)) if user.id == before.owner => {
+            return HttpResponse::BadRequest().body(ERROR_SHARE_OWNER)
+        }
+        Ok(Ok(user)) => user.id,
+        _ => return HttpResponse::NotFound().body(ERROR_USER_NOT_FOUND),
+    };
+
+    let mut card = before.clone();
+    card.share(target, info.role);
+    match put_todo(&client, adapter::todo_card_to_db(card.clone(), id)).await {
+        None => {
+            error!("Failed to share todo card {}", id);
+            HttpResponse::InternalServerError().body(ERROR_UPDATE)
+        }
+        Some(_) => {
+            let token = record_history(
+                &state,
+                id,
+                actor,
+                HistoryAction::Share,
+                Some(&before),
+                Some(&card),
+            )
+            .await;
+            with_undo_token(HttpResponse::Ok(), token)
+                .content_type(ContentType::json())
+                .json(card)
+        }
+    }
+}
+
+#[delete("/todo/{id}/share/{user}", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn revoke_todo(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let (id, user) = path.into_inner();
+    let client = state.dynamo.clone();
+    let before = match get_todo(&client, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_NOT_FOUND),
+        Some(card) if card.owner != actor => return HttpResponse::Forbidden().finish(),
+        Some(card) => card,
+    };
+
+    let mut card = before.clone();
+    if !card.revoke(user) {
+        return HttpResponse::NotFound().body(ERROR_COLLABORATOR_NOT_FOUND);
+    }
+    match put_todo(&client, adapter::todo_card_to_db(card.clone(), id)).await {
+        None => {
+            error!("Failed to revoke access to todo card {}", id);
+            HttpResponse::InternalServerError().body(ERROR_UPDATE)
+        }
+        Some(_) => {
+            let token = record_history(
+                &state,
+                id,
+                actor,
+                HistoryAction::Revoke,
+                Some(&before),
+                Some(&card),
+            )
+            .await;
+            with_undo_token(HttpResponse::Ok(), token)
+                .content_type(ContentType::json())
+                .json(card)
+        }
+    }
+}
+
+#[get("/todo/{id}/history", wrap = "RequireScope(TODO_READ)")]
+pub async fn show_todo_history(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    id: web::Path<Uuid>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let id = id.into_inner();
+    match get_todo(&state.dynamo, id).await {
+        None => return HttpResponse::NotFound().body(ERROR_NOT_FOUND),
+        Some(card) if !card.can_read(actor) => return HttpResponse::Forbidden().finish(),
+        Some(_) => (),
+    };
+
+    let resp = state.postgres.send(ReadHistory { card_id: id }).await;
+
+    match resp {
+        Ok(Ok(history)) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(CardHistoryResponse { history }),
+        e => {
+            error!("Failed to read todo card history {:?}", e);
+            HttpResponse::InternalServerError().body(ERROR_HISTORY)
+        }
+    }
+}
+
+pub(crate) fn request_actor(req: &HttpRequest) -> Option<Uuid> {
+    Uuid::parse_str(&jwt_from_request(req)?.id).ok()
+}
+
+/// Owner of a card created by `actor`, cards are only created on behalf of
+/// someone else with the `admin` scope.
+pub(crate) fn card_owner(req: &HttpRequest, requested: Option<Uuid>, actor: Uuid) -> Option<Uuid> {
+    match requested {
+        None => Some(actor),
+        Some(owner) if owner == actor => Some(owner),
+        Some(owner) => jwt_from_request(req)
+            .filter(|jwt| has_scope(&jwt.granted_scopes(), ADMIN))
+            .map(|_| owner),
+    }
+}
+
+/// History is best-effort: cards live in DynamoDB and can't share a transaction
+/// with `card_history`, so the change to the card stands when the entry fails to
+/// be written. The failure is logged and the response carries no undo token.
+pub(crate) async fn record_history(
+    state: &web::Data<Clients>,
+    card_id: Uuid,
+    actor: Uuid,
+    action: HistoryAction,
+    before: Option<&TodoCard>,
+    after: Option<&TodoCard>,
+) -> Option<Uuid> {
+    let entry = CardHistoryEntry::new(card_id, actor, action, before, after);
+    let entry_id = entry.id;
+
+    match state.postgres.send(RecordHistory(entry)).await {
+        Ok(Ok(_)) => Some(entry_id),
+        e => {
+            error!(
+                "Failed to record history for todo card {}: {:?}",
+                card_id, e
+            );
+            None
+        }
+    }
+}
+
+pub(crate) fn with_undo_token(
+    mut resp: HttpResponseBuilder,
+    token: Option<Uuid>,
+) -> HttpResponseBuilder {
+    if let Some(token) = token {
+        resp.insert_header((UNDO_TOKEN_HEADER, token.to_string()));
+    }
+    resp
+}
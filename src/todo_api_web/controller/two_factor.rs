@@ -0,0 +1,175 @@
+use crate::todo_api::core::{jwt_from_request, scope::ACCOUNT, totp::otpauth_uri};
+use crate::todo_api::db::helpers::{
+    ERROR_TWO_FACTOR_CODE, ERROR_TWO_FACTOR_ENABLED, ERROR_TWO_FACTOR_NOT_ENROLLED,
+};
+use crate::todo_api::model::{
+    error::DbError,
+    lockout::{BeginLogin, LoginAttempt},
+    two_factor::{ConfirmEnrollment, DisableTwoFactor, StartEnrollment, VerifySecondFactor},
+};
+use crate::todo_api_web::controller::{
+    auth::{client_ip, login_failed, release_login_attempt, too_many_attempts},
+    profile::confirm_password,
+    todo::request_actor,
+};
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::{
+    auth::Auth,
+    http::Clients,
+    two_factor::{
+        ConfirmTwoFactorRequest, DisableTwoFactorRequest, EnrollmentResponse, RecoveryCodesResponse,
+    },
+};
+
+use actix_web::{http::header::ContentType, post, web, HttpRequest, HttpResponse, Responder};
+use log::error;
+use uuid::Uuid;
+
+#[post("/2fa/enroll", wrap = "RequireScope(ACCOUNT)")]
+pub async fn enroll_two_factor(req: HttpRequest, state: web::Data<Clients>) -> impl Responder {
+    let jwt = match jwt_from_request(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(jwt) => jwt,
+    };
+    let actor = match Uuid::parse_str(&jwt.id) {
+        Err(_) => return HttpResponse::Unauthorized().finish(),
+        Ok(actor) => actor,
+    };
+
+    match state
+        .postgres
+        .send(StartEnrollment { user_id: actor })
+        .await
+    {
+        Ok(Ok(secret)) => {
+            HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .json(EnrollmentResponse {
+                    otpauth_uri: otpauth_uri(&secret, &jwt.email),
+                    secret,
+                })
+        }
+        Ok(Err(DbError::TwoFactorAlreadyEnabled)) => {
+            HttpResponse::Conflict().body(ERROR_TWO_FACTOR_ENABLED)
+        }
+        e => {
+            error!("Failed to start two-factor enrollment {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[post("/2fa/confirm", wrap = "RequireScope(ACCOUNT)")]
+pub async fn confirm_two_factor(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<ConfirmTwoFactorRequest>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let confirm = ConfirmEnrollment {
+        user_id: actor,
+        code: info.into_inner().code,
+    };
+
+    match state.postgres.send(confirm).await {
+        Ok(Ok(recovery_codes)) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json(RecoveryCodesResponse { recovery_codes }),
+        Ok(Err(DbError::InvalidTwoFactorCode)) => {
+            HttpResponse::BadRequest().body(ERROR_TWO_FACTOR_CODE)
+        }
+        Ok(Err(DbError::TwoFactorNotEnrolled)) => {
+            HttpResponse::NotFound().body(ERROR_TWO_FACTOR_NOT_ENROLLED)
+        }
+        Ok(Err(DbError::TwoFactorAlreadyEnabled)) => {
+            HttpResponse::Conflict().body(ERROR_TWO_FACTOR_ENABLED)
+        }
+        e => {
+            error!("Failed to confirm two-factor enrollment {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Re-authenticates with the password and a current code before disabling,
+/// wrong guesses count towards the account lockout like logins do.
+#[post("/2fa/disable", wrap = "RequireScope(ACCOUNT)")]
+pub async fn disable_two_factor(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<DisableTwoFactorRequest>,
+) -> impl Responder {
+    let jwt = match jwt_from_request(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(jwt) => jwt,
+    };
+    let info = info.into_inner();
+    let lookup = Auth {
+        email: jwt.email,
+        password: None,
+    };
+
+    let user = match state.postgres.send(lookup).await {
+        Ok(Ok(user)) if user.id.to_string() == jwt.id => user,
+        Ok(_) => return HttpResponse::Unauthorized().finish(),
+        Err(e) => {
+            error!("Failed to read user {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    if let Some(rejection) = confirm_password(&req, &state, &user, info.password).await {
+        return rejection;
+    }
+
+    let ip = client_ip(&req);
+    let begin = BeginLogin {
+        email: user.email.clone(),
+        ip: ip.clone(),
+    };
+    let locked = match state.postgres.send(begin).await {
+        Ok(Ok(LoginAttempt::Counted(locked))) => locked,
+        Ok(Ok(LoginAttempt::Throttled(retry_at))) => return too_many_attempts(retry_at),
+        e => {
+            error!("Failed to count login attempt {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let verify = VerifySecondFactor {
+        user_id: user.id,
+        code: info.code,
+    };
+    match state.postgres.send(verify).await {
+        Ok(Ok(_)) => release_login_attempt(&state, &user.email, ip).await,
+        Ok(Err(DbError::InvalidTwoFactorCode)) => {
+            login_failed(&state, &user.email, ip, Some(user.id), locked).await;
+            return HttpResponse::Forbidden().body(ERROR_TWO_FACTOR_CODE);
+        }
+        Ok(Err(DbError::TwoFactorNotEnrolled)) => {
+            release_login_attempt(&state, &user.email, ip).await;
+            return HttpResponse::NotFound().body(ERROR_TWO_FACTOR_NOT_ENROLLED);
+        }
+        e => {
+            error!("Failed to verify two-factor code {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    match state
+        .postgres
+        .send(DisableTwoFactor { user_id: user.id })
+        .await
+    {
+        Ok(Ok(_)) => HttpResponse::NoContent().finish(),
+        Ok(Err(DbError::TwoFactorNotEnrolled)) => {
+            HttpResponse::NotFound().body(ERROR_TWO_FACTOR_NOT_ENROLLED)
+        }
+        e => {
+            error!("Failed to disable two-factor {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::todo_api::adapter;
+use crate::todo_api::core::history::{can_revert, conflicting_edit};
+use crate::todo_api::core::scope::TODO_WRITE;
+use crate::todo_api::db::helpers::{ERROR_HISTORY, ERROR_NOTHING_TO_UNDO, ERROR_UPDATE};
+use crate::todo_api::db::todo::{delete_todo, get_todo, put_todo};
+use crate::todo_api::model::history::{
+    CardHistoryEntry, HistoryAction, MarkUndone, ReadLaterHistory, ReadUndoable,
+};
+use crate::todo_api_web::controller::todo::request_actor;
+use crate::todo_api_web::middleware::scope::RequireScope;
+use crate::todo_api_web::model::http::Clients;
+use crate::todo_api_web::model::todo::TodoCard;
+use crate::todo_api_web::model::undo::{undo_window_secs, UndoConflict, UndoRequest, UndoResponse};
+
+use actix_web::{http::header::ContentType, post, web, HttpRequest, HttpResponse, Responder};
+use aws_sdk_dynamodb::Client;
+use chrono::{Duration, Utc};
+use log::error;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[post("/undo", wrap = "RequireScope(TODO_WRITE)")]
+pub async fn undo(
+    req: HttpRequest,
+    state: web::Data<Clients>,
+    info: web::Json<UndoRequest>,
+) -> impl Responder {
+    let actor = match request_actor(&req) {
+        None => return HttpResponse::Unauthorized().finish(),
+        Some(actor) => actor,
+    };
+    let undoable = ReadUndoable {
+        actor,
+        since: Utc::now().naive_utc() - Duration::seconds(undo_window_secs()),
+        token: info.token,
+        count: info.count(),
+    };
+
+    let entries = match state.postgres.send(undoable).await {
+        Ok(Ok(entries)) if entries.is_empty() => {
+            return HttpResponse::NotFound().body(ERROR_NOTHING_TO_UNDO)
+        }
+        Ok(Ok(entries)) => entries,
+        e => {
+            error!("Failed to read undoable history {:?}", e);
+            return HttpResponse::InternalServerError().body(ERROR_HISTORY);
+        }
+    };
+
+    // Every entry is checked before anything is written, so an undo is either
+    // applied in full or not at all.
+    let client = state.dynamo.clone();
+    let undoing = entries.iter().map(|e| e.id).collect::<Vec<Uuid>>();
+    let mut cards: HashMap<Uuid, Option<TodoCard>> = HashMap::new();
+    for entry in &entries {
+        let later = ReadLaterHistory {
+            card_id: entry.card_id,
+            after: entry.changed_at,
+        };
+        match state.postgres.send(later).await {
+            Ok(Ok(later)) => {
+                if let Some(conflict) = conflicting_edit(&later, &undoing, actor) {
+                    return HttpResponse::Conflict()
+                        .content_type(ContentType::json())
+                        .json(UndoConflict {
+                            entry: entry.id,
+                            card_id: entry.card_id,
+                            conflicting_entry: conflict.id,
+                            conflicting_actor: conflict.actor,
+                        });
+                }
+            }
+            e => {
+                error!("Failed to read later history {:?}", e);
+                return HttpResponse::InternalServerError().body(ERROR_HISTORY);
+            }
+        }
+
+        let current = match cards.remove(&entry.card_id) {
+            Some(card) => card,
+            None => get_todo(&client, entry.card_id).await,
+        };
+        let restored = entry.snapshot_card();
+        if !can_revert(actor, current.as_ref(), restored.as_ref()) {
+            return HttpResponse::Forbidden().finish();
+        }
+        cards.insert(entry.card_id, restored);
+    }
+
+    for entry in &entries {
+        if let Some(failure) = revert(&state, actor, entry).await {
+            return failure;
+        }
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(UndoResponse { undone: undoing })
+}
+
+/// Puts the card back as it was before `entry`, `None` once it is done. The
+/// card is restored again when the entry cannot be marked undone.
+async fn revert(
+    state: &web::Data<Clients>,
+    actor: Uuid,
+    entry: &CardHistoryEntry,
+) -> Option<HttpResponse> {
+    let client = state.dynamo.clone();
+    let current = get_todo(&client, entry.card_id).await;
+    let restored = entry.snapshot_card();
+
+    if !put_back(&client, entry.card_id, restored.clone()).await {
+        error!("Failed to revert todo card {}", entry.card_id);
+        return Some(HttpResponse::InternalServerError().body(ERROR_UPDATE));
+    }
+
+    let undo_entry = CardHistoryEntry::new(
+        entry.card_id,
+        actor,
+        HistoryAction::Undo,
+        current.as_ref(),
+        restored.as_ref(),
+    );
+    match state
+        .postgres
+        .send(MarkUndone {
+            id: entry.id,
+            undo: undo_entry,
+        })
+        .await
+    {
+        Ok(Ok(_)) => None,
+        e => {
+            error!("Failed to mark history entry {} undone {:?}", entry.id, e);
+            if !put_back(&client, entry.card_id, current).await {
+                error!("Failed to restore todo card {} after undo", entry.card_id);
+            }
+            Some(HttpResponse::InternalServerError().body(ERROR_HISTORY))
+        }
+    }
+}
+
+async fn put_back(client: &Client, id: Uuid, card: Option<TodoCard>) -> bool {
+    match card {
+        Some(card) => put_todo(client, adapter::todo_card_to_db(card, id)).await,
+        None => delete_todo(client, id).await,
+    }
+    .is_some()
+}
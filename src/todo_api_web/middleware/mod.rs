@@ -1,51 +1,188 @@
-use crate::todo_api::{core::decode_jwt, model::core::JwtValue};
+pub mod scope;
+
+use crate::todo_api::{
+    core::{
+        api_token::is_api_token, jwt_from_request, revocation::is_token_revoked,
+        stateless::token_validation, token_from_request,
+    },
+    model::api_token::AuthenticateApiToken,
+};
 use actix_web_lab::middleware::Next;
 
 use actix_web::Error;
 use actix_web::{
-    body::MessageBody,
+    body::{EitherBody, MessageBody},
     dev::{ServiceRequest, ServiceResponse},
+    http::header::{ContentType, WWW_AUTHENTICATE},
     web::Data,
+    HttpMessage, HttpResponse,
 };
+use log::error;
+
+use super::model::http::{AuthError, Clients};
+
+pub static AUTH_REALM: &str = "todo-server";
+
+/// Authenticates every request of the scopes it wraps, `/api` and `/auth/me`,
+/// before it reaches the handler. Tokens are read from `Authorization: Bearer`
+/// or `x-auth`, and rejected requests get a 401 with a `WWW-Authenticate`
+/// challenge and an `AuthError` body. API tokens are looked up in the database
+/// and their owner's identity is stored in the request extensions.
+pub async fn authentication_middleware<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if let Err(rejection) = authenticate(&req).await {
+        return Ok(req
+            .into_response(rejection.response())
+            .map_into_right_body());
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rejection {
+    MissingToken,
+    InvalidToken(&'static str),
+    ServerError,
+}
 
-use super::model::http::Clients;
-
-pub async fn authentication_middleware(
-    mut req: ServiceRequest,
-    next: Next<impl MessageBody>,
-) -> Result<ServiceResponse<impl MessageBody>, Error> {
-    if req.path().starts_with("/api/") {
-        let data = req.extract::<Data<Clients>>().await.unwrap();
-        let jwt = req.headers().get("x-auth");
-
-        match jwt {
-            None => Err(actix_web::error::ErrorInternalServerError(
-                "Error in authentication middleware",
-            )),
-            Some(token) => {
-                let decoded_jwt: JwtValue =
-                    serde_json::from_value(decode_jwt(token.to_str().unwrap()))
-                        .expect("Failed to parse Jwt.");
-
-                let valid_jwt = data.postgres.send(decoded_jwt);
-                let fut = next.call(req).await?;
-
-                match valid_jwt.await {
-                    Ok(true) => {
-                        let (req, res) = fut.into_parts();
-                        let res = ServiceResponse::new(req, res);
-                        Ok(res)
-                    }
-                    _ => Err(actix_web::error::ErrorInternalServerError(
-                        "Error in authentication middleware",
-                    )),
-                }
+impl Rejection {
+    fn response(self) -> HttpResponse {
+        let (challenge, body) = match self {
+            Rejection::MissingToken => (
+                format!("Bearer realm=\"{}\"", AUTH_REALM),
+                AuthError::new("missing_token", "Missing authentication token"),
+            ),
+            Rejection::InvalidToken(description) => (
+                format!(
+                    "Bearer realm=\"{}\", error=\"invalid_token\", error_description=\"{}\"",
+                    AUTH_REALM, description
+                ),
+                AuthError::new("invalid_token", description),
+            ),
+            Rejection::ServerError => {
+                return HttpResponse::InternalServerError()
+                    .content_type(ContentType::json())
+                    .json(AuthError::new(
+                        "server_error",
+                        "Failed to validate authentication token",
+                    ))
             }
+        };
+
+        HttpResponse::Unauthorized()
+            .insert_header((WWW_AUTHENTICATE, challenge))
+            .content_type(ContentType::json())
+            .json(body)
+    }
+}
+
+async fn authenticate(req: &ServiceRequest) -> Result<(), Rejection> {
+    let data = req
+        .app_data::<Data<Clients>>()
+        .ok_or(Rejection::ServerError)?;
+    let token = token_from_request(req.request()).ok_or(Rejection::MissingToken)?;
+    if is_api_token(token) {
+        return authenticate_api_token(req, data, token.to_string()).await;
+    }
+
+    let jwt = jwt_from_request(req.request())
+        .ok_or(Rejection::InvalidToken("Invalid authentication token"))?;
+
+    if let Some(jti) = jwt.jti {
+        if is_token_revoked(data, jti).await {
+            return Err(Rejection::InvalidToken("Revoked authentication token"));
+        }
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    // The percent-decoded path the router matched, not the raw one.
+    let path = req.match_info().as_str();
+    if !token_validation().requires_database(path, &jwt, now) {
+        return Ok(());
+    }
+
+    match data.postgres.send(jwt).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Rejection::InvalidToken("Inactive authentication token")),
+        Err(e) => {
+            error!("Failed to validate authentication token {:?}", e);
+            Err(Rejection::ServerError)
+        }
+    }
+}
+
+async fn authenticate_api_token(
+    req: &ServiceRequest,
+    data: &Data<Clients>,
+    token: String,
+) -> Result<(), Rejection> {
+    match data.postgres.send(AuthenticateApiToken { token }).await {
+        Ok(Ok(Some(identity))) => {
+            req.extensions_mut().insert(identity);
+            Ok(())
+        }
+        Ok(Ok(None)) => Err(Rejection::InvalidToken("Invalid or expired API token")),
+        e => {
+            error!("Failed to validate API token {:?}", e);
+            Err(Rejection::ServerError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::http::StatusCode;
+
+    #[test]
+    fn missing_token_challenge_has_no_error_code() {
+        let resp = Rejection::MissingToken.response();
+        let challenge = resp.headers().get(WWW_AUTHENTICATE).unwrap();
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(challenge, "Bearer realm=\"todo-server\"");
+    }
+
+    #[test]
+    fn invalid_token_challenge_describes_error() {
+        let resp = Rejection::InvalidToken("Revoked authentication token").response();
+        let challenge = resp.headers().get(WWW_AUTHENTICATE).unwrap();
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            challenge,
+            "Bearer realm=\"todo-server\", error=\"invalid_token\", error_description=\"Revoked authentication token\""
+        );
+    }
+
+    #[actix_web::test]
+    async fn encoded_paths_go_through_the_scope_middleware() {
+        use actix_web::{test, web, App};
+        use actix_web_lab::middleware::from_fn;
+
+        let app = test::init_service(
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .wrap(from_fn(authentication_middleware))
+                        .route("/index", web::get().to(HttpResponse::Ok)),
+                )
+                .route("/ping", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for path in ["/api/index", "/%61pi/index"] {
+            let req = test::TestRequest::get().uri(path).to_request();
+            let resp = test::call_service(&app, req).await;
+            // Without app data the middleware can't authenticate anything.
+            assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
         }
-    } else {
-        let fut = next.call(req).await?;
-        let (req, res) = fut.into_parts();
-        let res = ServiceResponse::new(req, res);
-        Ok(res)
+        let req = test::TestRequest::get().uri("/ping").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
     }
 }
@@ -0,0 +1,149 @@
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+};
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{ContentType, WWW_AUTHENTICATE},
+    Error, HttpResponse,
+};
+
+use super::AUTH_REALM;
+use crate::todo_api::core::{jwt_from_request, scope::has_scope};
+use crate::todo_api_web::model::http::AuthError;
+
+/// Route guard declaring the scope a handler needs, attached with the route
+/// macro's `wrap` argument. Requests without an identity are left to the
+/// handler, which answers them with a 401.
+#[derive(Debug, Clone, Copy)]
+pub struct RequireScope(pub &'static str);
+
+impl<S, B> Transform<S, ServiceRequest> for RequireScope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireScopeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireScopeMiddleware {
+            service,
+            scope: self.0,
+        }))
+    }
+}
+
+pub struct RequireScopeMiddleware<S> {
+    service: S,
+    scope: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireScopeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let granted = jwt_from_request(req.request()).map(|jwt| jwt.granted_scopes());
+        if granted.is_some_and(|granted| !has_scope(&granted, self.scope)) {
+            let resp = req
+                .into_response(insufficient_scope(self.scope))
+                .map_into_right_body();
+            return Box::pin(ready(Ok(resp)));
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
+
+/// 403 with the RFC 6750 `insufficient_scope` challenge naming the scope.
+pub fn insufficient_scope(scope: &str) -> HttpResponse {
+    HttpResponse::Forbidden()
+        .insert_header((
+            WWW_AUTHENTICATE,
+            format!(
+                "Bearer realm=\"{}\", error=\"insufficient_scope\", scope=\"{}\"",
+                AUTH_REALM, scope
+            ),
+        ))
+        .content_type(ContentType::json())
+        .json(AuthError::new(
+            "insufficient_scope",
+            &format!("Missing scope {}", scope),
+        ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::{http::StatusCode, test, web, App, HttpMessage, HttpResponse};
+
+    use crate::todo_api::model::core::JwtValue;
+
+    fn identity(scope: &str) -> JwtValue {
+        JwtValue {
+            id: uuid::Uuid::new_v4().to_string(),
+            email: "my@email.com".to_string(),
+            expires_at: chrono::Utc::now().naive_utc(),
+            sid: None,
+            jti: None,
+            scope: Some(scope.to_string()),
+        }
+    }
+
+    #[actix_web::test]
+    async fn missing_scope_is_forbidden() {
+        let app = test::init_service(
+            App::new().service(
+                web::resource("/todo")
+                    .wrap(RequireScope("todo:write"))
+                    .to(HttpResponse::Ok),
+            ),
+        )
+        .await;
+        let req = test::TestRequest::post().uri("/todo").to_request();
+        req.extensions_mut().insert(identity("todo:read"));
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            resp.headers().get(WWW_AUTHENTICATE).unwrap(),
+            "Bearer realm=\"todo-server\", error=\"insufficient_scope\", scope=\"todo:write\""
+        );
+
+        let body: AuthError = test::read_body_json(resp).await;
+        assert_eq!(body.error_description, "Missing scope todo:write");
+    }
+
+    #[actix_web::test]
+    async fn granted_scope_reaches_handler() {
+        let app = test::init_service(
+            App::new().service(
+                web::resource("/todo")
+                    .wrap(RequireScope("todo:write"))
+                    .to(HttpResponse::Ok),
+            ),
+        )
+        .await;
+        let req = test::TestRequest::post().uri("/todo").to_request();
+        req.extensions_mut()
+            .insert(identity("todo:read todo:write"));
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -1,10 +1,11 @@
 use actix::prelude::*;
+use diesel::Connection;
 use serde::{Deserialize, Serialize};
 
 use crate::todo_api::{
     adapter,
     db::helpers::DbExecutor,
-    model::{auth::User, error::DbError},
+    model::{auth::User, error::DbError, verification::EmailVerification},
 };
 
 // #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -33,19 +34,34 @@ pub struct SignUp {
     pub password: String,
 }
 
+/// Creates the unverified user and returns the token for its verification
+/// email.
 impl Message for SignUp {
-    type Result = Result<(), DbError>;
+    type Result = Result<String, DbError>;
 }
 
 impl Handler<SignUp> for DbExecutor {
-    type Result = Result<(), DbError>;
+    type Result = Result<String, DbError>;
 
     fn handle(&mut self, msg: SignUp, _: &mut Self::Context) -> Self::Result {
-        use crate::todo_api::db::auth::insert_new_user;
+        use crate::todo_api::db::{auth::insert_new_user, verification::insert_verification};
 
         let user = adapter::auth::signup_to_hash_user(msg);
-
-        insert_new_user(user, &mut self.0.get().expect("Failed to open connection"))
+        let (verification, token) = EmailVerification::issue(user.id);
+        let mut conn = self.0.get().expect("Failed to open connection");
+
+        // A user without its verification could never sign in, nor sign up again.
+        let mut failure = DbError::TryAgain;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            insert_new_user(user, conn)
+                .and_then(|_| insert_verification(verification, conn))
+                .map_err(|e| {
+                    failure = e;
+                    diesel::result::Error::RollbackTransaction
+                })
+        })
+        .map(|_| token)
+        .map_err(|_| failure)
     }
 }
 
@@ -90,3 +106,34 @@ impl Handler<Auth> for DbExecutor {
         )
     }
 }
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UnlockRequest {
+    pub token: String,
+}
@@ -1,18 +1,51 @@
+use std::sync::Arc;
+
 use actix::Addr;
 use aws_sdk_dynamodb::Client;
+use serde::{Deserialize, Serialize};
 
+use crate::todo_api::core::{revocation::RevocationCache, validation::FieldError};
 use crate::todo_api::db::helpers::{db_executor_address, get_client, DbExecutor};
+use crate::todo_api::mailer::{mailer_from_env, Mailer};
+use crate::todo_api::oidc::{oidc_from_env, OidcProvider};
 
 #[derive(Clone, Debug)]
 pub struct Clients {
     pub dynamo: Client,
     pub postgres: Addr<DbExecutor>,
+    pub revoked: Arc<RevocationCache>,
+    pub mailer: Arc<dyn Mailer>,
+    pub oidc: Option<Arc<OidcProvider>>,
 }
 impl Clients {
     pub async fn new() -> Self {
         Self {
             dynamo: get_client().await,
             postgres: db_executor_address(),
+            revoked: Arc::new(RevocationCache::default()),
+            mailer: mailer_from_env(),
+            oidc: oidc_from_env(),
+        }
+    }
+}
+
+/// Body of a 400 naming every rejected field.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AuthError {
+    pub error: String,
+    pub error_description: String,
+}
+
+impl AuthError {
+    pub fn new(error: &str, error_description: &str) -> Self {
+        Self {
+            error: error.to_string(),
+            error_description: error_description.to_string(),
         }
     }
 }
@@ -1,3 +1,12 @@
+pub mod api_token;
 pub mod auth;
 pub mod http;
+pub mod oauth;
+pub mod oidc;
+pub mod profile;
+pub mod session;
+pub mod stats;
+pub mod template;
 pub mod todo;
+pub mod two_factor;
+pub mod undo;
@@ -0,0 +1,157 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::todo_api::model::{core::Jwt, oauth::OAuthClient};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct RegisterClientRequest {
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ClientResponse {
+    pub client_id: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<OAuthClient> for ClientResponse {
+    fn from(client: OAuthClient) -> Self {
+        Self {
+            redirect_uris: client.redirect_uri_list(),
+            confidential: client.is_confidential(),
+            client_id: client.id,
+            name: client.name,
+            created_at: client.created_at,
+        }
+    }
+}
+
+/// Only response that carries the secret of a confidential client.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct RegisteredClientResponse {
+    #[serde(flatten)]
+    pub details: ClientResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ClientsResponse {
+    pub clients: Vec<ClientResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AuthorizeQuery {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// Consent form posted back with the original request, the user's
+/// credentials and their decision.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AuthorizeForm {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub otp: String,
+    pub decision: String,
+}
+
+impl AuthorizeForm {
+    pub fn request(&self) -> AuthorizeQuery {
+        AuthorizeQuery {
+            response_type: self.response_type.clone(),
+            client_id: self.client_id.clone(),
+            redirect_uri: self.redirect_uri.clone(),
+            scope: self.scope.clone(),
+            state: self.state.clone(),
+            code_challenge: self.code_challenge.clone(),
+            code_challenge_method: self.code_challenge_method.clone(),
+        }
+    }
+}
+
+/// Form body of the token endpoint for both supported grant types.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct OAuthTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub scope: String,
+}
+
+impl OAuthTokenResponse {
+    pub fn new(jwt: Jwt, scope: String) -> Self {
+        Self {
+            access_token: jwt.token,
+            token_type: "Bearer".to_string(),
+            expires_in: jwt.expires_in,
+            refresh_token: jwt.refresh_token,
+            scope,
+        }
+    }
+}
+
+/// Form body of the introspection and revocation endpoints.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TokenActionRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+}
+
+impl IntrospectionResponse {
+    pub fn inactive() -> Self {
+        Self::default()
+    }
+}
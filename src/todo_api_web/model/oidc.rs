@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Redirect from the OpenID provider, carrying either a code or an error.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct OidcCallbackQuery {
+    pub state: String,
+    pub code: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::todo_api::model::auth::User;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ProfileResponse {
+    pub id: Uuid,
+    pub email: String,
+    pub email_verified: bool,
+    pub display_name: Option<String>,
+    pub timezone: String,
+}
+
+impl From<User> for ProfileResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            email: user.email,
+            email_verified: user.email_verified,
+            display_name: user.display_name,
+            timezone: user.timezone,
+        }
+    }
+}
+
+/// Omitted fields are left unchanged, an empty display name clears it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UpdateProfileRequest {
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ChangeEmailRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
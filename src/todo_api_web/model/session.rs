@@ -0,0 +1,39 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::todo_api::model::session::Session;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub device: String,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub current: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl SessionResponse {
+    pub fn new(session: Session, current: Option<Uuid>) -> Self {
+        Self {
+            current: current == Some(session.id),
+            id: session.id,
+            device: session.device,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+            client_id: session.client_id,
+            scope: session.scope,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
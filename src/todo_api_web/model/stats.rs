@@ -0,0 +1,45 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub static DEFAULT_STATS_WINDOW: i64 = 7;
+pub static MAX_STATS_WINDOW: i64 = 90;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct StatsQuery {
+    pub days: Option<i64>,
+}
+
+impl StatsQuery {
+    pub fn window(&self) -> i64 {
+        self.days
+            .unwrap_or(DEFAULT_STATS_WINDOW)
+            .clamp(1, MAX_STATS_WINDOW)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UserStats {
+    pub owner: Uuid,
+    pub todo: usize,
+    pub doing: usize,
+    pub done: usize,
+    pub tasks_total: usize,
+    pub tasks_done: usize,
+    pub completion_ratio: f64,
+    pub avg_cycle_time_secs: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DailyStats {
+    pub date: NaiveDate,
+    pub created: usize,
+    pub completed: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct StatsResponse {
+    pub users: Vec<UserStats>,
+    pub daily: Vec<DailyStats>,
+    pub avg_cycle_time_secs: Option<f64>,
+}
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::todo_api_web::model::todo::Task;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TodoTemplate {
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub owner: Option<Uuid>,
+    pub tasks: Vec<Task>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TemplatesResponse {
+    pub templates: Vec<TodoTemplate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct InstantiateTemplate {
+    /// Defaults to the caller, anyone else needs the `admin` scope.
+    pub owner: Option<Uuid>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct MissingVariables {
+    pub missing: Vec<String>,
+}
@@ -1,6 +1,9 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::todo_api::model::history::CardHistoryEntry;
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Task {
     pub is_done: bool,
@@ -32,6 +35,139 @@ pub struct TodoCard {
     pub owner: Uuid,
     pub tasks: Vec<Task>,
     pub state: State,
+    pub created_at: Option<NaiveDateTime>,
+    pub doing_at: Option<NaiveDateTime>,
+    pub done_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub transfers: Vec<Transfer>,
+    #[serde(default)]
+    pub collaborators: Vec<Collaborator>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Transfer {
+    pub from: Uuid,
+    pub to: Uuid,
+    pub at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum Role {
+    Viewer,
+    Editor,
+}
+impl From<&String> for Role {
+    fn from(s: &String) -> Self {
+        match s.as_str() {
+            "Editor" | "editor" => Role::Editor,
+            _ => Role::Viewer,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Collaborator {
+    pub user: Uuid,
+    pub role: Role,
+}
+
+impl TodoCard {
+    pub fn transition(&mut self, state: State, at: NaiveDateTime) {
+        match state {
+            State::Todo => {
+                self.doing_at = None;
+                self.done_at = None;
+            }
+            State::Doing => {
+                self.doing_at = Some(at);
+                self.done_at = None;
+            }
+            State::Done => self.done_at = Some(at),
+        }
+        self.state = state;
+    }
+
+    pub fn transfer(&mut self, to: Uuid, at: NaiveDateTime) {
+        self.transfers.push(Transfer {
+            from: self.owner,
+            to,
+            at,
+        });
+        self.owner = to;
+        self.collaborators.retain(|c| c.user != to);
+    }
+
+    pub fn role_of(&self, user: Uuid) -> Option<Role> {
+        self.collaborators
+            .iter()
+            .find(|c| c.user == user)
+            .map(|c| c.role.clone())
+    }
+
+    pub fn can_read(&self, user: Uuid) -> bool {
+        self.owner == user || self.role_of(user).is_some()
+    }
+
+    pub fn can_edit(&self, user: Uuid) -> bool {
+        self.owner == user || self.role_of(user) == Some(Role::Editor)
+    }
+
+    pub fn share(&mut self, user: Uuid, role: Role) {
+        match self.collaborators.iter_mut().find(|c| c.user == user) {
+            Some(collaborator) => collaborator.role = role,
+            None => self.collaborators.push(Collaborator { user, role }),
+        }
+    }
+
+    pub fn revoke(&mut self, user: Uuid) -> bool {
+        let before = self.collaborators.len();
+        self.collaborators.retain(|c| c.user != user);
+        before != self.collaborators.len()
+    }
+
+    pub fn duplicate(&self, owner: Uuid, keep_progress: bool) -> TodoCard {
+        let mut card = self.clone();
+        card.id = None;
+        card.owner = owner;
+        card.transfers = Vec::new();
+        card.collaborators = Vec::new();
+        if !keep_progress {
+            card.state = State::Todo;
+            card.tasks.iter_mut().for_each(|t| t.is_done = false);
+        }
+        card
+    }
+
+    pub fn apply_update(&mut self, update: TodoCard, at: NaiveDateTime) {
+        self.title = update.title;
+        self.description = update.description;
+        self.tasks = update.tasks;
+        if self.state != update.state {
+            self.transition(update.state, at);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct StateTransition {
+    pub state: State,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DuplicateCard {
+    #[serde(default)]
+    pub keep_progress: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TransferCard {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ShareCard {
+    pub email: String,
+    pub role: Role,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -54,3 +190,90 @@ impl TodoIdResponse {
 pub struct TodoCardsResponse {
     pub cards: Vec<TodoCard>,
 }
+
+#[derive(Serialize, Deserialize, PartialEq)]
+pub struct CardHistoryResponse {
+    pub history: Vec<CardHistoryEntry>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn card() -> TodoCard {
+        TodoCard {
+            id: Some(Uuid::new_v4()),
+            title: "title".to_string(),
+            description: "description".to_string(),
+            owner: Uuid::new_v4(),
+            tasks: vec![Task {
+                is_done: true,
+                title: "task".to_string(),
+            }],
+            state: State::Doing,
+            created_at: None,
+            doing_at: None,
+            done_at: None,
+            transfers: vec![],
+            collaborators: vec![],
+        }
+    }
+
+    #[test]
+    fn duplicate_resets_progress() {
+        let owner = Uuid::new_v4();
+        let copy = card().duplicate(owner, false);
+
+        assert_eq!(copy.id, None);
+        assert_eq!(copy.owner, owner);
+        assert_eq!(copy.state, State::Todo);
+        assert!(copy.tasks.iter().all(|t| !t.is_done));
+    }
+
+    #[test]
+    fn duplicate_keeps_progress() {
+        let copy = card().duplicate(Uuid::new_v4(), true);
+
+        assert_eq!(copy.state, State::Doing);
+        assert!(copy.tasks.iter().all(|t| t.is_done));
+    }
+
+    #[test]
+    fn roles_limit_access() {
+        let viewer = Uuid::new_v4();
+        let editor = Uuid::new_v4();
+        let mut card = card();
+        card.share(viewer, Role::Viewer);
+        card.share(editor, Role::Editor);
+
+        assert!(card.can_read(card.owner) && card.can_edit(card.owner));
+        assert!(card.can_read(viewer) && !card.can_edit(viewer));
+        assert!(card.can_read(editor) && card.can_edit(editor));
+        assert!(!card.can_read(Uuid::new_v4()));
+    }
+
+    #[test]
+    fn sharing_again_changes_role_and_revoke_removes_it() {
+        let user = Uuid::new_v4();
+        let mut card = card();
+        card.share(user, Role::Viewer);
+        card.share(user, Role::Editor);
+
+        assert_eq!(card.collaborators.len(), 1);
+        assert_eq!(card.role_of(user), Some(Role::Editor));
+        assert!(card.revoke(user));
+        assert!(!card.revoke(user));
+        assert!(!card.can_read(user));
+    }
+
+    #[test]
+    fn transfer_drops_new_owner_from_collaborators() {
+        let user = Uuid::new_v4();
+        let mut card = card();
+        card.share(user, Role::Editor);
+        card.transfer(user, chrono::Utc::now().naive_utc());
+
+        assert_eq!(card.owner, user);
+        assert!(card.collaborators.is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct EnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ConfirmTwoFactorRequest {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Disabling asks for the password again on top of a current code.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    pub code: String,
+}
+
+/// Login answer for accounts with two-factor enabled, in place of the JWT.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TwoFactorChallenge {
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+    pub expires_in: i64,
+}
+
+/// `code` is either a TOTP code or one of the recovery codes.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    pub code: String,
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub static UNDO_TOKEN_HEADER: &str = "x-undo-token";
+pub static DEFAULT_UNDO_WINDOW_SECS: i64 = 600;
+pub static MAX_UNDO_COUNT: i64 = 20;
+
+pub fn undo_window_secs() -> i64 {
+    std::env::var("UNDO_WINDOW_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_UNDO_WINDOW_SECS)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UndoRequest {
+    pub token: Option<Uuid>,
+    pub count: Option<i64>,
+}
+
+impl UndoRequest {
+    pub fn count(&self) -> i64 {
+        match self.token {
+            Some(_) => 1,
+            None => self.count.unwrap_or(1).clamp(1, MAX_UNDO_COUNT),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UndoResponse {
+    pub undone: Vec<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UndoConflict {
+    pub entry: Uuid,
+    pub card_id: Uuid,
+    pub conflicting_entry: Uuid,
+    pub conflicting_actor: Uuid,
+}
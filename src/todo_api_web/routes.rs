@@ -1,25 +1,107 @@
 use crate::todo_api_web::controller::{
-    auth::{login, logout, signup_user},
-    ping, readiness,
-    todo::{create_todo, show_all_todo},
+    admin::admin_unlock_account,
+    api_token::{create_api_token, revoke_api_token, show_api_tokens},
+    auth::{
+        forgot_password, login, login_two_factor, logout, refresh, resend_verification,
+        reset_password, signup_user, unlock_account, verify_email,
+    },
+    jwks::jwks,
+    oauth::{
+        approve_authorization, authorize, introspect, register_client, remove_client, revoke,
+        show_clients, token,
+    },
+    oidc::{oidc_callback, oidc_login},
+    ping,
+    profile::{change_email, change_password, show_profile, update_profile},
+    readiness,
+    session::{revoke_session, show_sessions},
+    stats::show_stats,
+    template::{
+        create_template, instantiate_template, remove_template, show_all_templates, show_template,
+        update_template,
+    },
+    todo::{
+        create_todo, duplicate_todo, remove_todo, revoke_todo, share_todo, show_all_todo,
+        show_todo_history, transfer_todo, update_todo, update_todo_state,
+    },
+    two_factor::{confirm_two_factor, disable_two_factor, enroll_two_factor},
+    undo::undo,
 };
 
+use crate::todo_api_web::middleware::authentication_middleware;
+
 use actix_web::{web, HttpResponse};
+use actix_web_lab::middleware::from_fn;
 
 pub fn app_routes(config: &mut web::ServiceConfig) {
     config.service(
         web::scope("")
             .service(
                 web::scope("/api")
+                    .wrap(from_fn(authentication_middleware))
                     .service(create_todo)
-                    .service(show_all_todo),
+                    .service(show_all_todo)
+                    .service(update_todo)
+                    .service(update_todo_state)
+                    .service(remove_todo)
+                    .service(duplicate_todo)
+                    .service(transfer_todo)
+                    .service(share_todo)
+                    .service(revoke_todo)
+                    .service(show_todo_history)
+                    .service(show_stats)
+                    .service(undo)
+                    .service(show_sessions)
+                    .service(revoke_session)
+                    .service(create_template)
+                    .service(show_all_templates)
+                    .service(show_template)
+                    .service(update_template)
+                    .service(remove_template)
+                    .service(instantiate_template)
+                    .service(admin_unlock_account)
+                    .service(enroll_two_factor)
+                    .service(confirm_two_factor)
+                    .service(disable_two_factor)
+                    .service(create_api_token)
+                    .service(show_api_tokens)
+                    .service(revoke_api_token)
+                    .service(register_client)
+                    .service(show_clients)
+                    .service(remove_client),
             )
             .service(
                 web::scope("/auth")
                     .service(signup_user)
                     .service(login)
+                    .service(login_two_factor)
+                    .service(refresh)
+                    .service(forgot_password)
+                    .service(reset_password)
+                    .service(verify_email)
+                    .service(resend_verification)
+                    .service(unlock_account)
+                    .service(oidc_login)
+                    .service(oidc_callback)
+                    .service(
+                        web::scope("/me")
+                            .wrap(from_fn(authentication_middleware))
+                            .service(show_profile)
+                            .service(update_profile)
+                            .service(change_email)
+                            .service(change_password),
+                    )
                     .service(logout),
             )
+            .service(
+                web::scope("/oauth")
+                    .service(authorize)
+                    .service(approve_authorization)
+                    .service(token)
+                    .service(introspect)
+                    .service(revoke),
+            )
+            .service(jwks)
             .service(ping)
             .service(readiness)
             .default_service(web::to(|| HttpResponse::NotFound())),
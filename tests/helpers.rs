@@ -1,6 +1,10 @@
 use std::io::Read;
 use std::{fs::File, str::FromStr};
 
+use todo_server::todo_api::{
+    core::create_token,
+    model::{auth::User, core::UpdateUserStatus},
+};
 use todo_server::todo_api_web::model::todo::{State, Task, TodoCard};
 
 pub fn read_json(file: &str) -> String {
@@ -11,6 +15,20 @@ pub fn read_json(file: &str) -> String {
     data
 }
 
+/// Owner of the fixture cards and templates.
+pub static FIXTURE_OWNER: &str = "ae75c4d8-5241-4f1c-8e85-ff380c041442";
+
+pub fn auth_token() -> String {
+    let mut user = User::from(String::from("my@email.com"), String::from("this is a hash"));
+    user.id = uuid::Uuid::parse_str(FIXTURE_OWNER).unwrap();
+    let update_date = UpdateUserStatus {
+        email: user.email.clone(),
+        expires_at: user.expires_at,
+        is_active: true,
+    };
+    create_token(user, update_date, None)
+}
+
 pub fn mock_get_todos() -> Vec<TodoCard> {
     vec![TodoCard {
         id: Some(uuid::Uuid::from_str("be75c4d8-5241-4f1c-8e85-ff380c041664").unwrap()),
@@ -32,5 +50,10 @@ pub fn mock_get_todos() -> Vec<TodoCard> {
             },
         ],
         state: State::Doing,
+        created_at: None,
+        doing_at: None,
+        done_at: None,
+        transfers: vec![],
+        collaborators: vec![],
     }]
 }
@@ -1,3 +1,24 @@
+mod jwks {
+    use serde_json::from_slice;
+    use todo_server::todo_api::model::jwks::JwksResponse;
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{test, web, App};
+
+    #[actix_web::test]
+    async fn publishes_key_set_without_authentication() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get()
+            .uri("/.well-known/jwks.json")
+            .to_request();
+
+        let bytes = test::call_and_read_body(&app, req).await;
+        assert!(from_slice::<JwksResponse>(&bytes).is_ok());
+    }
+}
+
 mod ping_readiness {
     use actix_web::{body, http::StatusCode, test, web, App};
     use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
@@ -29,7 +50,7 @@ mod ping_readiness {
 }
 
 mod create_todo {
-    use crate::helpers::read_json;
+    use crate::helpers::{auth_token, read_json};
     use todo_server::{
         todo_api::db::helpers::TODO_FILE,
         todo_api_web::{model::http::Clients, model::todo::TodoIdResponse, routes::app_routes},
@@ -37,7 +58,10 @@ mod create_todo {
 
     use actix_web::{
         body,
-        http::header::{ContentType, CONTENT_TYPE},
+        http::{
+            header::{ContentType, CONTENT_TYPE},
+            StatusCode,
+        },
         test, web, App,
     };
     use serde_json::from_str;
@@ -50,6 +74,7 @@ mod create_todo {
         let req = test::TestRequest::post()
             .uri("/api/create")
             .insert_header((CONTENT_TYPE, ContentType::json()))
+            .insert_header(("x-auth", auth_token()))
             .set_payload(read_json(TODO_FILE).as_bytes().to_owned())
             .to_request();
 
@@ -59,6 +84,26 @@ mod create_todo {
         let id = from_str::<TodoIdResponse>(&String::from_utf8(bytes.to_vec()).unwrap()).unwrap();
         assert!(uuid::Uuid::parse_str(&id.get_id()).is_ok());
     }
+
+    #[actix_web::test]
+    async fn cards_of_someone_else_need_admin() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let payload = read_json(TODO_FILE).replace(
+            "ae75c4d8-5241-4f1c-8e85-ff380c041442",
+            &uuid::Uuid::new_v4().to_string(),
+        );
+        let req = test::TestRequest::post()
+            .uri("/api/create")
+            .insert_header((CONTENT_TYPE, ContentType::json()))
+            .insert_header(("x-auth", auth_token()))
+            .set_payload(payload)
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
 }
 
 mod read_all_todos {
@@ -77,7 +122,7 @@ mod read_all_todos {
         test, web, App,
     };
 
-    use crate::helpers::{mock_get_todos, read_json};
+    use crate::helpers::{auth_token, mock_get_todos, read_json};
 
     #[actix_web::test]
     async fn test_todo_index_ok() {
@@ -85,7 +130,10 @@ mod read_all_todos {
         let mut app =
             test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
 
-        let req = test::TestRequest::get().uri("/api/index").to_request();
+        let req = test::TestRequest::get()
+            .uri("/api/index")
+            .insert_header(("x-auth", auth_token()))
+            .to_request();
 
         let resp = test::call_service(&mut app, req).await;
         assert_eq!(resp.status(), StatusCode::OK);
@@ -100,11 +148,15 @@ mod read_all_todos {
         let post_req = test::TestRequest::post()
             .uri("/api/create")
             .insert_header((CONTENT_TYPE, ContentType::json()))
+            .insert_header(("x-auth", auth_token()))
             .set_payload(read_json(TODO_FILE).as_bytes().to_owned())
             .to_request();
 
         let _ = test::call_service(&mut app, post_req).await;
-        let get_req = test::TestRequest::get().uri("/api/index").to_request();
+        let get_req = test::TestRequest::get()
+            .uri("/api/index")
+            .insert_header(("x-auth", auth_token()))
+            .to_request();
         let resp_body = test::call_service(&mut app, get_req).await.into_body();
         let bytes = body::to_bytes(resp_body).await.unwrap();
         let todo_cards =
@@ -113,6 +165,18 @@ mod read_all_todos {
         assert_eq!(todo_cards.cards.len(), 1);
     }
 
+    #[actix_web::test]
+    async fn test_todo_index_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+
+        let req = test::TestRequest::get().uri("/api/index").to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
     #[actix_web::test]
     async fn test_todo_cards_with_value() {
         let client = web::Data::new(Clients::new().await);
@@ -122,11 +186,14 @@ mod read_all_todos {
         let post_req = test::TestRequest::post()
             .uri("/api/create")
             .insert_header((CONTENT_TYPE, ContentType::json()))
+            .insert_header(("x-auth", auth_token()))
             .set_payload(read_json(TODO_FILE).as_bytes().to_owned())
             .to_request();
 
         let _ = test::call_service(&mut app, post_req).await;
-        let req = test::TestRequest::with_uri("/api/index").to_request();
+        let req = test::TestRequest::with_uri("/api/index")
+            .insert_header(("x-auth", auth_token()))
+            .to_request();
         let resp_body = test::call_service(&mut app, req).await.into_body();
         let bytes = body::to_bytes(resp_body).await.unwrap();
         let todo_cards: TodoCardsResponse =
@@ -136,6 +203,717 @@ mod read_all_todos {
     }
 }
 
+mod history {
+    use crate::helpers::{auth_token, read_json};
+    use serde_json::from_slice;
+    use todo_server::{
+        todo_api::db::helpers::TODO_FILE,
+        todo_api_web::{
+            model::http::Clients,
+            model::todo::{CardHistoryResponse, TodoIdResponse},
+            routes::app_routes,
+        },
+    };
+
+    use actix_web::{
+        body,
+        http::{
+            header::{ContentType, CONTENT_TYPE},
+            StatusCode,
+        },
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn create_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/api/create")
+            .insert_header((CONTENT_TYPE, ContentType::json()))
+            .set_payload(read_json(TODO_FILE).as_bytes().to_owned())
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn created_card_has_history() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let post_req = test::TestRequest::post()
+            .uri("/api/create")
+            .insert_header((CONTENT_TYPE, ContentType::json()))
+            .insert_header(("x-auth", auth_token()))
+            .set_payload(read_json(TODO_FILE).as_bytes().to_owned())
+            .to_request();
+
+        let bytes = test::call_and_read_body(&app, post_req).await;
+        let id = from_slice::<TodoIdResponse>(&bytes).unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/todo/{}/history", id.get_id()))
+            .insert_header(("x-auth", auth_token()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let bytes = body::to_bytes(resp.into_body()).await.unwrap();
+        let history = from_slice::<CardHistoryResponse>(&bytes).unwrap();
+        assert_eq!(history.history[0].action, "Create");
+    }
+}
+
+mod duplicate {
+    use crate::helpers::{auth_token, read_json};
+    use serde_json::{from_slice, json};
+    use todo_server::{
+        todo_api::db::helpers::TODO_FILE,
+        todo_api_web::{model::http::Clients, model::todo::TodoIdResponse, routes::app_routes},
+    };
+
+    use actix_web::{
+        http::{
+            header::{ContentType, CONTENT_TYPE},
+            StatusCode,
+        },
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn duplicate_creates_new_card() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let post_req = test::TestRequest::post()
+            .uri("/api/create")
+            .insert_header((CONTENT_TYPE, ContentType::json()))
+            .insert_header(("x-auth", auth_token()))
+            .set_payload(read_json(TODO_FILE).as_bytes().to_owned())
+            .to_request();
+        let bytes = test::call_and_read_body(&app, post_req).await;
+        let id = from_slice::<TodoIdResponse>(&bytes).unwrap().get_id();
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/todo/{}/duplicate", id))
+            .insert_header(("x-auth", auth_token()))
+            .set_json(json!({ "keep_progress": false }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+}
+
+mod share {
+    use crate::helpers::auth_token;
+    use serde_json::json;
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn revoking_unknown_collaborator_is_not_found() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::delete()
+            .uri(&format!(
+                "/api/todo/{}/share/{}",
+                uuid::Uuid::new_v4(),
+                uuid::Uuid::new_v4()
+            ))
+            .insert_header(("x-auth", auth_token()))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn share_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/todo/{}/share", uuid::Uuid::new_v4()))
+            .set_json(json!({ "email": "my@email.com", "role": "Viewer" }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+mod undo {
+    use crate::helpers::{auth_token, read_json};
+    use serde_json::{from_slice, json};
+    use todo_server::{
+        todo_api::db::helpers::TODO_FILE,
+        todo_api_web::{
+            model::http::Clients,
+            model::undo::{UndoResponse, UNDO_TOKEN_HEADER},
+            routes::app_routes,
+        },
+    };
+
+    use actix_web::{
+        body,
+        http::{
+            header::{ContentType, CONTENT_TYPE},
+            StatusCode,
+        },
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn undo_token_reverts_creation() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let post_req = test::TestRequest::post()
+            .uri("/api/create")
+            .insert_header((CONTENT_TYPE, ContentType::json()))
+            .insert_header(("x-auth", auth_token()))
+            .set_payload(read_json(TODO_FILE).as_bytes().to_owned())
+            .to_request();
+
+        let resp = test::call_service(&app, post_req).await;
+        let token = resp
+            .headers()
+            .get(UNDO_TOKEN_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let undo_req = test::TestRequest::post()
+            .uri("/api/undo")
+            .insert_header(("x-auth", auth_token()))
+            .set_json(json!({ "token": token }))
+            .to_request();
+        let resp = test::call_service(&app, undo_req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let bytes = body::to_bytes(resp.into_body()).await.unwrap();
+        let undone = from_slice::<UndoResponse>(&bytes).unwrap();
+        assert_eq!(undone.undone.len(), 1);
+    }
+}
+
+mod templates {
+    use crate::helpers::auth_token;
+    use serde_json::{from_slice, json};
+    use todo_server::todo_api_web::{
+        model::http::Clients,
+        model::template::{MissingVariables, TemplatesResponse},
+        routes::app_routes,
+    };
+
+    use actix_web::{body, http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn list_templates_ok() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/templates")
+            .insert_header(("x-auth", auth_token()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let bytes = body::to_bytes(resp.into_body()).await.unwrap();
+        assert!(from_slice::<TemplatesResponse>(&bytes).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn instantiate_requires_all_variables() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let create_req = test::TestRequest::post()
+            .uri("/api/templates")
+            .insert_header(("x-auth", auth_token()))
+            .set_json(json!({
+                "name": "release",
+                "title": "Release {{version}}",
+                "description": "Release checklist",
+                "tasks": [{ "title": "Tag {{version}}", "is_done": true }]
+            }))
+            .to_request();
+        let bytes = test::call_and_read_body(&app, create_req).await;
+        let id = from_slice::<serde_json::Value>(&bytes).unwrap()["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/templates/{}/instantiate", id))
+            .insert_header(("x-auth", auth_token()))
+            .set_json(json!({ "variables": {} }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let bytes = body::to_bytes(resp.into_body()).await.unwrap();
+        let missing = from_slice::<MissingVariables>(&bytes).unwrap();
+        assert_eq!(missing.missing, vec!["version".to_string()]);
+    }
+}
+
+mod stats {
+    use crate::helpers::auth_token;
+    use serde_json::from_str;
+    use todo_server::todo_api_web::{
+        model::http::Clients, model::stats::StatsResponse, routes::app_routes,
+    };
+
+    use actix_web::{body, http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn test_stats_window() {
+        let client = web::Data::new(Clients::new().await);
+        let mut app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/stats?days=3")
+            .insert_header(("x-auth", auth_token()))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let bytes = body::to_bytes(resp.into_body()).await.unwrap();
+        let stats = from_str::<StatsResponse>(&String::from_utf8(bytes.to_vec()).unwrap()).unwrap();
+        assert_eq!(stats.daily.len(), 3);
+    }
+}
+
+mod refresh {
+    use serde_json::json;
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn unknown_refresh_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/auth/refresh")
+            .set_json(json!({ "refresh_token": "not a refresh token" }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+mod sessions {
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn sessions_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get().uri("/api/sessions").to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+mod password {
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{
+        http::{header::ContentType, StatusCode},
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn forgot_password_for_unknown_email_is_accepted() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/auth/password/forgot")
+            .insert_header(ContentType::json())
+            .set_payload("{\"email\": \"nobody@email.com\"}")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    }
+
+    #[actix_web::test]
+    async fn reset_with_weak_password_is_bad_request() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/auth/password/reset")
+            .insert_header(ContentType::json())
+            .set_payload("{\"token\": \"token\", \"password\": \"short\"}")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn signup_reports_field_errors() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/auth/signup")
+            .insert_header(ContentType::json())
+            .set_payload("{\"email\": \"not an email\", \"password\": \"short\"}")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("\"field\":\"email\""));
+        assert!(body.contains("\"reason\":\"too_short\""));
+    }
+}
+
+mod verification {
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{
+        http::{header::ContentType, StatusCode},
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn unknown_verification_token_is_bad_request() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/auth/email/verify")
+            .insert_header(ContentType::json())
+            .set_payload("{\"token\": \"unknown\"}")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+mod lockout {
+    use crate::helpers::auth_token;
+    use todo_server::todo_api_web::{
+        model::http::{AuthError, Clients},
+        routes::app_routes,
+    };
+
+    use actix_web::{
+        http::{header::ContentType, StatusCode},
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn unknown_unlock_token_is_bad_request() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/auth/unlock")
+            .insert_header(ContentType::json())
+            .set_payload("{\"token\": \"unknown\"}")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn admin_unlock_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::delete()
+            .uri("/api/admin/lockouts/my@email.com")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn admin_unlock_without_admin_scope_is_forbidden() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::delete()
+            .uri("/api/admin/lockouts/my@email.com")
+            .insert_header(("Authorization", format!("Bearer {}", auth_token())))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let body: AuthError = test::read_body_json(resp).await;
+        assert_eq!(body.error, "insufficient_scope");
+    }
+}
+
+mod two_factor {
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{
+        http::{header::ContentType, StatusCode},
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn invalid_challenge_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/auth/login/2fa")
+            .insert_header(ContentType::json())
+            .set_payload("{\"challenge_token\": \"not-a-jwt\", \"code\": \"123456\"}")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn enroll_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/api/2fa/enroll")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+mod api_token {
+    use todo_server::todo_api_web::{
+        model::http::{AuthError, Clients},
+        routes::app_routes,
+    };
+
+    use actix_web::{http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn listing_tokens_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get().uri("/api/tokens").to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn unknown_api_token_is_invalid() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/index")
+            .insert_header(("Authorization", "Bearer tdp_unknown"))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let body: AuthError = test::read_body_json(resp).await;
+        assert_eq!(body.error, "invalid_token");
+    }
+}
+
+mod oauth {
+    use todo_server::todo_api_web::{
+        model::http::{AuthError, Clients},
+        routes::app_routes,
+    };
+
+    use actix_web::{http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn authorize_with_unknown_client_is_bad_request() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get()
+            .uri("/oauth/authorize?response_type=code&client_id=tdc_unknown&redirect_uri=https%3A%2F%2Fclient.example%2Fcb&code_challenge=abc&code_challenge_method=S256")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(resp.headers().get("Location").is_none());
+        assert_eq!(resp.headers().get("X-Frame-Options").unwrap(), "DENY");
+        assert_eq!(
+            resp.headers().get("Content-Security-Policy").unwrap(),
+            "frame-ancestors 'none'"
+        );
+    }
+
+    #[actix_web::test]
+    async fn token_with_unknown_client_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "authorization_code"),
+                ("client_id", "tdc_unknown"),
+                ("code", "code"),
+            ])
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let body: AuthError = test::read_body_json(resp).await;
+        assert_eq!(body.error, "invalid_client");
+    }
+}
+
+mod oidc {
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn oidc_login_without_provider_is_not_found() {
+        let client = web::Data::new(Clients {
+            oidc: None,
+            ..Clients::new().await
+        });
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get()
+            .uri("/auth/oidc/login")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
+
+mod profile {
+    use todo_server::todo_api_web::{model::http::Clients, routes::app_routes};
+
+    use actix_web::{
+        http::{header::ContentType, StatusCode},
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn profile_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get().uri("/auth/me").to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn password_change_without_token_is_unauthorized() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/auth/me/password")
+            .insert_header(ContentType::json())
+            .set_payload(
+                "{\"current_password\": \"old\", \"new_password\": \"My cr4azy p@ssw0rd My cr4azy p@ssw0rd\"}",
+            )
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+mod middleware {
+    use crate::helpers::auth_token;
+    use todo_server::todo_api::core::{jwt_from_token, revocation::revoke_token};
+    use todo_server::todo_api_web::{
+        model::http::{AuthError, Clients},
+        routes::app_routes,
+    };
+
+    use actix_web::{
+        http::{header::WWW_AUTHENTICATE, StatusCode},
+        test, web, App,
+    };
+
+    #[actix_web::test]
+    async fn missing_token_is_challenged() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get().uri("/api/index").to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(resp.headers().contains_key(WWW_AUTHENTICATE));
+
+        let body: AuthError = test::read_body_json(resp).await;
+        assert_eq!(body.error, "missing_token");
+    }
+
+    #[actix_web::test]
+    async fn malformed_bearer_token_is_invalid() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/index")
+            .insert_header(("Authorization", "Bearer not-a-jwt"))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[cfg(not(feature = "dbtest"))]
+    #[actix_web::test]
+    async fn encoded_paths_are_authenticated() {
+        let client = web::Data::new(Clients::new().await);
+        let app =
+            test::init_service(App::new().app_data(client.clone()).configure(app_routes)).await;
+        let token = auth_token();
+        let jwt = jwt_from_token(&token).unwrap();
+        let user_id = uuid::Uuid::parse_str(&jwt.id).unwrap();
+        assert!(revoke_token(&client, jwt.jti.unwrap(), user_id, jwt.expires_at).await);
+
+        let req = test::TestRequest::get()
+            .uri("/%61pi/index")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
 mod auth {
     use crate::helpers::read_json;
     use actix_service::Service;